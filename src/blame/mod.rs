@@ -0,0 +1,97 @@
+// src/blame.rs
+// Attributes annotation lines to their authors using the local git history
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Author and commit information for a single line, as reported by `git blame`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameInfo {
+    pub author: String,
+    pub author_email: String,
+    pub commit: String,
+    pub commit_date: i64,
+}
+
+/// Runs `git blame` on a file and returns the attribution for every committed line,
+/// keyed by its 1-based line number. Lines that are not committed yet are left out.
+pub fn blame_file(file_path: &Path) -> Result<HashMap<usize, BlameInfo>> {
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid file path: {}", file_path.display()))?;
+    let parent = match file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(parent)
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(file_name)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {e}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("git blame failed: {}", stderr.trim()));
+    }
+
+    Ok(parse_line_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses the output of `git blame --line-porcelain`
+fn parse_line_porcelain(output: &str) -> HashMap<usize, BlameInfo> {
+    let mut result = HashMap::new();
+    let mut commit = String::new();
+    let mut line_number = 0;
+    let mut author = String::new();
+    let mut author_email = String::new();
+    let mut commit_date = 0;
+
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            // The content line closes the header block of the current line
+            if !commit.chars().all(|c| c == '0') {
+                result.insert(
+                    line_number,
+                    BlameInfo {
+                        author: author.clone(),
+                        author_email: author_email.clone(),
+                        commit: commit.clone(),
+                        commit_date,
+                    },
+                );
+            }
+        } else if let Some(value) = line.strip_prefix("author-mail ") {
+            author_email = value
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string();
+        } else if let Some(value) = line.strip_prefix("author-time ") {
+            commit_date = value.trim().parse().unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("author ") {
+            author = value.to_string();
+        } else {
+            let mut parts = line.split(' ');
+            if let (Some(hash), Some(_), Some(final_line)) =
+                (parts.next(), parts.next(), parts.next())
+                && hash.len() == 40
+                && hash.chars().all(|c| c.is_ascii_hexdigit())
+                && let Ok(number) = final_line.parse()
+            {
+                commit = hash.to_string();
+                line_number = number;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,80 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Alice",
+            "-c",
+            "user.email=alice@example.com",
+        ])
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(status.status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn test_parse_line_porcelain() {
+    let output = "\
+1234567890123456789012345678901234567890 1 1 2
+author Alice
+author-mail <alice@example.com>
+author-time 1700000000
+author-tz +0000
+summary Initial commit
+filename test.rs
+\t// TODO: First
+1234567890123456789012345678901234567890 2 2
+author Alice
+author-mail <alice@example.com>
+author-time 1700000000
+author-tz +0000
+summary Initial commit
+filename test.rs
+\tfn main() {}
+0000000000000000000000000000000000000000 3 3 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1700000100
+filename test.rs
+\t// FIXME: Uncommitted
+";
+
+    let result = parse_line_porcelain(output);
+    assert_eq!(result.len(), 2);
+    let first = &result[&1];
+    assert_eq!(first.author, "Alice");
+    assert_eq!(first.author_email, "alice@example.com");
+    assert_eq!(first.commit_date, 1_700_000_000);
+    assert!(!result.contains_key(&3));
+}
+
+#[test]
+fn test_blame_file_in_repository() {
+    let temp_dir = tempdir().unwrap();
+    git(temp_dir.path(), &["init", "-q"]);
+    let test_file = temp_dir.path().join("test.rs");
+    fs::write(&test_file, "// TODO: Blame me\nfn main() {}\n").unwrap();
+    git(temp_dir.path(), &["add", "test.rs"]);
+    git(temp_dir.path(), &["commit", "-q", "-m", "Initial commit"]);
+
+    let result = blame_file(&test_file).unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[&1].author, "Alice");
+    assert_eq!(result[&1].author_email, "alice@example.com");
+    assert_eq!(result[&1].commit.len(), 40);
+}
+
+#[test]
+fn test_blame_file_outside_repository() {
+    let temp_dir = tempdir().unwrap();
+    let test_file = temp_dir.path().join("test.rs");
+    fs::write(&test_file, "// TODO: Not tracked\n").unwrap();
+
+    assert!(blame_file(&test_file).is_err());
+}
@@ -2,13 +2,31 @@
 // Handles the ci command for codemarks
 
 use anyhow::Result;
-use ignore::{WalkBuilder, overrides::OverrideBuilder};
-use regex::Regex;
-use std::fs;
-use std::io::{BufRead, BufReader};
 use std::path::Path;
 
-use crate::default_annotation_pattern;
+use crate::config_layers::{ConfigOverrides, LayeredConfig};
+use crate::filters::FileSelection;
+use crate::formats::{format_location, read_source_lines};
+use crate::list::current_timestamp;
+use crate::matcher::Matcher;
+use crate::output::{Record, print_records};
+use crate::query::Query;
+use crate::scan::attribute_codemarks;
+use crate::terminal::{ColorChoice, Styler};
+use crate::{Codemark, OutputFormat, detect_project_name, load_global_config};
+
+/// Resolves the annotation matcher and ignore patterns from the layered configuration.
+/// CI reads the configuration like every other command, and only skips the global one
+/// in ephemeral mode; it never touches the projects database.
+fn resolve_settings(
+    directory: &Path,
+    overrides: &ConfigOverrides,
+    ephemeral: bool,
+) -> Result<(Matcher, LayeredConfig)> {
+    let layered = load_global_config(directory, overrides, ephemeral)?;
+    let matcher = Matcher::new(&layered.config)?;
+    Ok((matcher, layered))
+}
 
 /// Helper function that returns the count instead of exiting (for testing)
 #[allow(dead_code)]
@@ -16,40 +34,27 @@ pub fn count_annotations(
     directory: &Path,
     pattern: Option<String>,
     ignore_patterns: &[String],
+    selection: &FileSelection,
 ) -> Result<usize> {
-    let pattern_to_use = pattern.unwrap_or_else(default_annotation_pattern);
-    let codemark_regex = Regex::new(&pattern_to_use)?;
+    let overrides = ConfigOverrides {
+        pattern,
+        ignore: ignore_patterns.to_vec(),
+        ..Default::default()
+    };
+    // Tests stay independent of the global configuration of whoever runs them
+    let (matcher, layered) = resolve_settings(directory, &overrides, true)?;
     let mut found = 0;
 
-    let mut builder = WalkBuilder::new(directory);
-
-    // Add custom ignore patterns using overrides
-    if !ignore_patterns.is_empty() {
-        let mut override_builder = OverrideBuilder::new(directory);
-        for pattern in ignore_patterns {
-            // Add as negative override (ignore pattern)
-            if let Err(e) = override_builder.add(&format!("!{pattern}")) {
-                eprintln!("Warning: Invalid ignore pattern '{pattern}': {e}");
-            }
-        }
-        if let Ok(overrides) = override_builder.build() {
-            builder.overrides(overrides);
-        }
-    }
+    let builder = selection.walker(directory, &layered.ignore)?;
 
     for result in builder.build() {
         match result {
             Ok(entry) => {
                 let path = entry.path();
                 if path.is_file()
-                    && let Ok(file) = std::fs::File::open(path)
+                    && let Ok(lines) = read_source_lines(path)
                 {
-                    let reader = BufReader::new(file);
-                    for line_content in reader.lines().map_while(Result::ok) {
-                        if codemark_regex.is_match(&line_content) {
-                            found += 1;
-                        }
-                    }
+                    found += matcher.match_file_lines(path, lines).matches.len();
                 }
             }
             Err(err) => eprintln!("Error accessing path: {err}"),
@@ -59,57 +64,149 @@ pub fn count_annotations(
     Ok(found)
 }
 
-pub fn run_ci(directory: &Path, pattern: Option<String>, ignore_patterns: &[String]) -> ! {
-    let pattern_to_use = pattern.unwrap_or_else(default_annotation_pattern);
-    let codemark_regex = Regex::new(&pattern_to_use).expect("Invalid regex pattern");
-    let mut found = 0;
-
-    let mut builder = WalkBuilder::new(directory);
-
-    // Add custom ignore patterns using overrides
-    if !ignore_patterns.is_empty() {
-        let mut override_builder = OverrideBuilder::new(directory);
-        for pattern in ignore_patterns {
-            // Add as negative override (ignore pattern)
-            if let Err(e) = override_builder.add(&format!("!{pattern}")) {
-                eprintln!("Warning: Invalid ignore pattern '{pattern}': {e}");
-            }
+/// Checks a directory for annotations and exits with the result. `overrides` holds the
+/// `--pattern` and `--ignore` options.
+pub fn run_ci(
+    directory: &Path,
+    overrides: &ConfigOverrides,
+    selection: &FileSelection,
+    query: Option<&Query>,
+    format: OutputFormat,
+    color: ColorChoice,
+    ephemeral: bool,
+) -> ! {
+    let (matcher, layered) = match resolve_settings(directory, overrides, ephemeral) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
         }
-        if let Ok(overrides) = override_builder.build() {
-            builder.overrides(overrides);
+    };
+    let max_annotations = layered.max_annotations.unwrap_or(0);
+    let project_name = layered
+        .project_name_for(directory)
+        .unwrap_or_else(|| detect_project_name(directory));
+    let machine_readable = format.is_machine_readable();
+    let styler = Styler::new(color, &layered.config.kinds);
+    let mut found = Vec::new();
+    let mut suppressed = 0;
+    let now = current_timestamp();
+    let needs_blame = query.is_some_and(Query::uses_age);
+
+    let builder = match selection.walker(directory, &layered.ignore) {
+        Ok(builder) => builder,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
         }
-    }
+    };
 
     #[allow(clippy::manual_flatten)]
     for result in builder.build() {
         if let Ok(entry) = result {
             let file_path = entry.path();
             if entry.file_type().is_some_and(|ft| ft.is_file())
-                && let Ok(file) = fs::File::open(file_path)
+                && let Ok(lines) = read_source_lines(file_path)
             {
-                let reader = BufReader::new(file);
-                for (line_number, line) in reader.lines().enumerate() {
-                    if let Ok(line_content) = line
-                        && codemark_regex.is_match(&line_content)
-                    {
-                        found += 1;
+                let file_matches = matcher.match_file_lines(file_path, lines);
+                suppressed += file_matches.suppressed;
+                // Diagnostics keep the path as walked, so they resolve from the current
+                // directory rather than from the scanned one
+                let file = if format.is_diagnostic() {
+                    file_path.strip_prefix(".").unwrap_or(file_path)
+                } else {
+                    file_path.strip_prefix(directory).unwrap_or(file_path)
+                };
+                let mut file_codemarks = Vec::new();
+                for (line, line_match) in file_matches.matches {
+                    let codemark = Codemark {
+                        file: file.to_string_lossy().to_string(),
+                        line_number: line.line_number,
+                        cell: line.cell,
+                        column: Some(line_match.column),
+                        description: line_match.description,
+                        kind: line_match.kind,
+                        owner: line_match.owner,
+                        issue: line_match.issue,
+                        created_at: Some(now),
+                        ..Default::default()
+                    };
+                    file_codemarks.push((line, codemark));
+                }
+                // Nothing is stored in ci, so ages come from the commits that added the lines
+                if needs_blame
+                    && !file_codemarks.is_empty()
+                    && file_path.extension().is_none_or(|ext| ext != "ipynb")
+                {
+                    attribute_codemarks(
+                        file_path,
+                        file_codemarks.iter_mut().map(|(_, codemark)| codemark),
+                    );
+                }
+                for (line, codemark) in file_codemarks {
+                    if query.is_some_and(|query| !query.matches(&project_name, &codemark, now)) {
+                        continue;
+                    }
+                    if !machine_readable {
+                        let location = format_location(
+                            &file_path.to_string_lossy(),
+                            line.line_number,
+                            line.cell,
+                        );
+                        let target = codemark.file_line();
                         println!(
-                            "{}:{}: {}",
-                            file_path.display(),
-                            line_number + 1,
-                            line_content
+                            "{}: {}",
+                            styler.link(&location, file_path, target),
+                            styler.kind(codemark.kind.as_deref(), &line.text)
                         );
                     }
+                    found.push(codemark);
                 }
             }
         }
     }
 
-    if found > 0 {
-        println!("Found {found} codemarks matching pattern.");
+    // With machine readable output, stdout only holds the records
+    let report = |message: String| {
+        if machine_readable {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    };
+    if machine_readable {
+        let records: Vec<Record> = found
+            .iter()
+            .map(|codemark| Record::new(&project_name, codemark, &layered.config.kinds))
+            .collect();
+        if let Err(e) = print_records(format, &records) {
+            eprintln!("Error writing output: {e}");
+            std::process::exit(2);
+        }
+    }
+    let found = found.len();
+
+    // Keep suppressions visible, so they are not silently piling up
+    if suppressed > 0 {
+        report(format!(
+            "{suppressed} codemarks suppressed by codemarks-ignore directives."
+        ));
+    }
+    if found > max_annotations {
+        report(styler.error(&format!("Found {found} codemarks matching pattern.")));
+        if max_annotations > 0 {
+            report(styler.error(&format!(
+                "This exceeds the maximum of {max_annotations} allowed by the CI policy."
+            )));
+        }
         std::process::exit(1);
+    } else if found > 0 {
+        report(format!(
+            "Found {found} codemarks matching pattern, within the maximum of {max_annotations} allowed by the CI policy."
+        ));
+        std::process::exit(0);
     } else {
-        println!("No codemarks found matching pattern.");
+        report(styler.success("No codemarks found matching pattern."));
         std::process::exit(0);
     }
 }
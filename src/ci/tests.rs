@@ -15,7 +15,7 @@ fn setup_test_env() {
 fn test_count_annotations_empty_directory() {
     setup_test_env();
     let temp_dir = tempdir().unwrap();
-    let result = count_annotations(temp_dir.path(), None, &[]);
+    let result = count_annotations(temp_dir.path(), None, &[], &FileSelection::default());
     assert_eq!(result.unwrap(), 0);
 }
 
@@ -30,7 +30,7 @@ fn test_count_annotations_with_todos() {
     )
     .unwrap();
 
-    let result = count_annotations(temp_dir.path(), None, &[]);
+    let result = count_annotations(temp_dir.path(), None, &[], &FileSelection::default());
     assert_eq!(result.unwrap(), 2);
 }
 
@@ -45,7 +45,12 @@ fn test_count_annotations_with_custom_pattern() {
     )
     .unwrap();
 
-    let result = count_annotations(temp_dir.path(), Some("CUSTOM".to_string()), &[]);
+    let result = count_annotations(
+        temp_dir.path(),
+        Some("CUSTOM".to_string()),
+        &[],
+        &FileSelection::default(),
+    );
     assert_eq!(result.unwrap(), 1);
 }
 
@@ -53,7 +58,12 @@ fn test_count_annotations_with_custom_pattern() {
 fn test_count_annotations_invalid_regex() {
     setup_test_env();
     let temp_dir = tempdir().unwrap();
-    let result = count_annotations(temp_dir.path(), Some("[".to_string()), &[]);
+    let result = count_annotations(
+        temp_dir.path(),
+        Some("[".to_string()),
+        &[],
+        &FileSelection::default(),
+    );
     assert!(result.is_err());
 }
 
@@ -66,6 +76,79 @@ fn test_count_annotations_with_ignore_patterns() {
     fs::write(&test_file, "// TODO: Fix this").unwrap();
     fs::write(&ignored_file, "// TODO: Ignored todo").unwrap();
 
-    let result = count_annotations(temp_dir.path(), None, &["ignored.rs".to_string()]);
+    let result = count_annotations(
+        temp_dir.path(),
+        None,
+        &["ignored.rs".to_string()],
+        &FileSelection::default(),
+    );
+    assert_eq!(result.unwrap(), 1);
+}
+
+#[test]
+fn test_count_annotations_in_notebook_code_cells() {
+    setup_test_env();
+    let temp_dir = tempdir().unwrap();
+    let notebook = temp_dir.path().join("analysis.ipynb");
+    fs::write(
+        &notebook,
+        r##"{"cells": [
+            {"cell_type": "markdown", "source": ["Notes about # TODO: not code\n"]},
+            {"cell_type": "code", "source": ["import os\n", "# TODO: Load data\n"]}
+        ]}"##,
+    )
+    .unwrap();
+
+    let result = count_annotations(temp_dir.path(), None, &[], &FileSelection::default());
+    assert_eq!(result.unwrap(), 1);
+}
+
+#[test]
+fn test_count_annotations_with_file_types() {
+    setup_test_env();
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("main.rs"), "// TODO: Rust task").unwrap();
+    fs::write(temp_dir.path().join("script.py"), "# TODO: Python task").unwrap();
+    fs::write(temp_dir.path().join("app.js"), "// TODO: JS task").unwrap();
+
+    let selection = FileSelection {
+        types: vec!["rust".to_string(), "py".to_string()],
+        ..Default::default()
+    };
+    let result = count_annotations(temp_dir.path(), None, &[], &selection);
+    assert_eq!(result.unwrap(), 2);
+}
+
+#[test]
+fn test_count_annotations_with_repo_config() {
+    setup_test_env();
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(".codemarks.toml"),
+        "kinds = [\"XXX\"]\nignore = [\"vendor/\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("main.rs"),
+        "// XXX: Custom\n// TODO: Not a kind",
+    )
+    .unwrap();
+    fs::create_dir_all(temp_dir.path().join("vendor")).unwrap();
+    fs::write(
+        temp_dir.path().join("vendor").join("lib.rs"),
+        "// XXX: Vendored",
+    )
+    .unwrap();
+
+    let result = count_annotations(temp_dir.path(), None, &[], &FileSelection::default());
+    assert_eq!(result.unwrap(), 1);
+
+    // A pattern given on the command line takes precedence
+    let result = count_annotations(
+        temp_dir.path(),
+        Some("TODO".to_string()),
+        &[],
+        &FileSelection::default(),
+    );
     assert_eq!(result.unwrap(), 1);
 }
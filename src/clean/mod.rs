@@ -1,9 +1,16 @@
+use crate::list::current_timestamp;
+use crate::query::Query;
 use crate::{ProjectsDatabase, load_global_projects, save_global_projects};
 use anyhow::Result;
 use std::collections::HashMap;
 
-pub fn clean_resolved(dry_run: bool, project_filter: Option<String>) -> Result<()> {
+pub fn clean_resolved(
+    dry_run: bool,
+    project_filter: Option<String>,
+    query: Option<&Query>,
+) -> Result<()> {
     let projects_db = load_global_projects(false);
+    let now = current_timestamp();
     let mut total_removed = 0;
     let mut projects_affected = 0;
 
@@ -13,6 +20,8 @@ pub fn clean_resolved(dry_run: bool, project_filter: Option<String>) -> Result<(
     // Create a new database with only unresolved items
     let mut cleaned_db = ProjectsDatabase {
         projects: HashMap::new(),
+        scanned_at: projects_db.scanned_at.clone(),
+        roots: projects_db.roots.clone(),
     };
 
     for (project_name, codemarks) in &projects_db.projects {
@@ -30,7 +39,11 @@ pub fn clean_resolved(dry_run: bool, project_filter: Option<String>) -> Result<(
         let original_count = codemarks.len();
         let unresolved_codemarks: Vec<_> = codemarks
             .iter()
-            .filter(|codemark| !codemark.resolved)
+            .filter(|codemark| {
+                // With a query, only the resolved annotations matching it are removed
+                !codemark.resolved
+                    || query.is_some_and(|query| !query.matches(project_name, codemark, now))
+            })
             .cloned()
             .collect();
 
@@ -16,11 +16,11 @@ fn test_clean_resolved_basic() {
     let _temp_home = setup_temp_home();
 
     // Test basic dry run functionality
-    let result = clean_resolved(true, None);
+    let result = clean_resolved(true, None, None);
     assert!(result.is_ok());
 
     // Test with project filter
-    let result = clean_resolved(true, Some("nonexistent".to_string()));
+    let result = clean_resolved(true, Some("nonexistent".to_string()), None);
     assert!(result.is_ok());
 }
 
@@ -29,11 +29,11 @@ fn test_clean_resolved_edge_cases() {
     let _temp_home = setup_temp_home();
 
     // Test dry run (safe operation)
-    let result = clean_resolved(true, None);
+    let result = clean_resolved(true, None, None);
     assert!(result.is_ok());
 
     // Test with project filter
-    let result = clean_resolved(true, Some("nonexistent".to_string()));
+    let result = clean_resolved(true, Some("nonexistent".to_string()), None);
     assert!(result.is_ok());
 }
 
@@ -48,6 +48,7 @@ fn test_clean_resolved_data_structures() {
         line_number: 1,
         description: "Done".to_string(),
         resolved: true,
+        ..Default::default()
     };
 
     let unresolved_item = Codemark {
@@ -55,6 +56,7 @@ fn test_clean_resolved_data_structures() {
         line_number: 2,
         description: "TODO".to_string(),
         resolved: false,
+        ..Default::default()
     };
 
     test_db
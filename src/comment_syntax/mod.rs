@@ -0,0 +1,153 @@
+// src/comment_syntax.rs
+// Comment leaders per file type, so annotations are only found inside comments
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Comment leaders of C-like languages, including the continuation of block comments
+const C_STYLE: &[&str] = &["//", "/*", "*"];
+const HASH: &[&str] = &["#"];
+const DOUBLE_DASH: &[&str] = &["--", "/*", "*"];
+const SEMICOLON: &[&str] = &[";"];
+const PERCENT: &[&str] = &["%"];
+const MARKUP: &[&str] = &["<!--"];
+
+/// Built-in comment leaders, keyed by lowercase file extension or file name
+const BUILTIN_SYNTAX: &[(&[&str], &[&str])] = &[
+    (
+        &[
+            "rs", "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "cs", "java", "kt", "kts",
+            "scala", "swift", "go", "js", "jsx", "mjs", "cjs", "ts", "tsx", "dart", "groovy",
+            "gradle", "proto", "scss", "less", "sol", "zig", "v",
+        ],
+        C_STYLE,
+    ),
+    (&["css"], &["/*", "*"]),
+    (&["php"], &["//", "#", "/*", "*"]),
+    (
+        &[
+            "py",
+            "pyi",
+            "rb",
+            "sh",
+            "bash",
+            "zsh",
+            "fish",
+            "pl",
+            "pm",
+            "r",
+            "yaml",
+            "yml",
+            "toml",
+            "conf",
+            "cfg",
+            "mk",
+            "cmake",
+            "tf",
+            "nix",
+            "ex",
+            "exs",
+            "jl",
+            "cr",
+            "nim",
+            "makefile",
+            "dockerfile",
+            "gemfile",
+            "rakefile",
+            "cmakelists.txt",
+        ],
+        HASH,
+    ),
+    (&["ps1", "psm1"], &["#", "<#"]),
+    (&["sql"], DOUBLE_DASH),
+    (&["hs", "elm", "purs"], &["--", "{-"]),
+    (&["lua"], &["--[[", "--"]),
+    (&["ada", "adb", "ads", "vhd", "vhdl"], &["--"]),
+    (
+        &[
+            "lisp", "lsp", "cl", "el", "clj", "cljs", "cljc", "edn", "scm", "ss", "rkt",
+        ],
+        SEMICOLON,
+    ),
+    (&["asm", "s", "nasm"], &[";", "#"]),
+    (&["ini"], &[";", "#"]),
+    (&["tex", "sty", "bib", "erl", "hrl", "pro"], PERCENT),
+    (&["m"], &["//", "/*", "*", "%"]),
+    (&["vb", "vbs", "bas", "vba"], &["'", "REM"]),
+    (&["bat", "cmd"], &["REM", "::"]),
+    (&["f", "f90", "f95", "f03", "f08"], &["!"]),
+    (&["vim"], &["\""]),
+    (&["ml", "mli"], &["(*", "*"]),
+    (&["fs", "fsi", "fsx"], &["//", "(*", "*"]),
+    (
+        &["html", "htm", "xml", "xhtml", "svg", "xaml", "plist"],
+        MARKUP,
+    ),
+    (&["vue", "svelte"], &["<!--", "//", "/*", "*"]),
+];
+
+/// Returns the built-in comment leaders for a lowercase file extension or file name
+#[must_use]
+pub fn builtin_leaders(key: &str) -> Option<&'static [&'static str]> {
+    BUILTIN_SYNTAX
+        .iter()
+        .find(|(keys, _)| keys.contains(&key))
+        .map(|(_, leaders)| *leaders)
+}
+
+/// Returns every file extension and file name that has built-in comment leaders
+pub fn builtin_keys() -> impl Iterator<Item = &'static str> {
+    BUILTIN_SYNTAX
+        .iter()
+        .flat_map(|(keys, _)| keys.iter().copied())
+}
+
+/// Finds the comment leaders for a file. Configured leaders take precedence over the
+/// built-in ones, and a file name (e.g. `Makefile`) over an extension.
+#[must_use]
+pub fn leaders_for(path: &Path, custom: &BTreeMap<String, Vec<String>>) -> Option<Vec<String>> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let custom_leaders = |key: &str| {
+        custom
+            .iter()
+            .find(|(custom_key, _)| custom_key.eq_ignore_ascii_case(key))
+            .map(|(_, leaders)| leaders.clone())
+    };
+    let builtin = |key: &str| {
+        builtin_leaders(key).map(|leaders| leaders.iter().map(ToString::to_string).collect())
+    };
+
+    custom_leaders(&name)
+        .or_else(|| extension.as_deref().and_then(custom_leaders))
+        .or_else(|| builtin(&name))
+        .or_else(|| extension.as_deref().and_then(builtin))
+}
+
+/// Builds an annotation pattern that matches the keywords after one of the comment leaders
+#[must_use]
+pub fn pattern_for_leaders(leaders: &[String], keywords: &[String]) -> String {
+    let leaders = leaders
+        .iter()
+        .map(|leader| {
+            // Word leaders such as REM must not match the start of a longer word
+            if leader.ends_with(|c: char| c.is_alphanumeric()) {
+                format!(r"{}\b", regex::escape(leader))
+            } else {
+                regex::escape(leader)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("|");
+    let keywords = keywords
+        .iter()
+        .map(|keyword| regex::escape(keyword))
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(r"(?i)(?:{leaders})\s*(?:{keywords})\s*:?\s*(.*)$")
+}
+
+#[cfg(test)]
+mod tests;
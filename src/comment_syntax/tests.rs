@@ -0,0 +1,65 @@
+use super::*;
+use regex::Regex;
+
+fn keywords() -> Vec<String> {
+    vec!["TODO".to_string(), "FIXME".to_string()]
+}
+
+#[test]
+fn test_builtin_leaders() {
+    assert_eq!(builtin_leaders("rs"), Some(C_STYLE));
+    assert_eq!(builtin_leaders("sql"), Some(DOUBLE_DASH));
+    assert_eq!(builtin_leaders("makefile"), Some(HASH));
+    assert_eq!(builtin_leaders("unknown"), None);
+}
+
+#[test]
+fn test_leaders_for_path() {
+    let custom = BTreeMap::new();
+    assert_eq!(
+        leaders_for(Path::new("src/Query.SQL"), &custom),
+        Some(vec!["--".to_string(), "/*".to_string(), "*".to_string()])
+    );
+    assert_eq!(
+        leaders_for(Path::new("Makefile"), &custom),
+        Some(vec!["#".to_string()])
+    );
+    assert_eq!(leaders_for(Path::new("notes.txt"), &custom), None);
+    assert_eq!(leaders_for(Path::new("README"), &custom), None);
+}
+
+#[test]
+fn test_custom_leaders_take_precedence() {
+    let mut custom = BTreeMap::new();
+    custom.insert("sql".to_string(), vec!["#".to_string()]);
+    custom.insert("txt".to_string(), vec![">".to_string()]);
+
+    assert_eq!(
+        leaders_for(Path::new("query.sql"), &custom),
+        Some(vec!["#".to_string()])
+    );
+    assert_eq!(
+        leaders_for(Path::new("notes.txt"), &custom),
+        Some(vec![">".to_string()])
+    );
+}
+
+#[test]
+fn test_pattern_for_leaders() {
+    let sql = Regex::new(&pattern_for_leaders(
+        &["--".to_string(), "/*".to_string()],
+        &keywords(),
+    ))
+    .unwrap();
+    let captures = sql.captures("SELECT 1; -- TODO: use a join").unwrap();
+    assert_eq!(&captures[1], "use a join");
+    assert!(sql.is_match("/* FIXME slow */"));
+    assert!(!sql.is_match("// TODO: wrong leader"));
+
+    let batch = Regex::new(&pattern_for_leaders(&["REM".to_string()], &keywords())).unwrap();
+    assert!(batch.is_match("rem TODO: quote paths"));
+    assert!(!batch.is_match("REMTODO"));
+
+    let vb = Regex::new(&pattern_for_leaders(&["'".to_string()], &keywords())).unwrap();
+    assert!(vb.is_match("x = 1 ' TODO check overflow"));
+}
@@ -1,19 +1,43 @@
 // src/config.rs
 // Handles the config command for codemarks
 
+use crate::config_layers::ConfigOverrides;
+use crate::formats::{format_location, read_source_lines};
+use crate::matcher::{LineMatch, Matcher, compile_pattern};
+use crate::open::edit_file;
 use crate::{
-    CodemarksConfig, ConfigAction, get_global_config_path, get_global_projects_path,
-    load_global_config, save_global_config,
+    AnnotationKind, CodemarksConfig, ConfigAction, IgnoreAction, IgnoreTarget, KindAction,
+    default_annotation_pattern, detect_project_name, get_global_config_path,
+    get_global_projects_path, load_global_config, read_global_config, save_global_config,
 };
 use anyhow::Result;
 use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 pub fn handle_config(action: ConfigAction) -> Result<()> {
     match action {
-        ConfigAction::Show => {
-            let config = load_global_config(false);
+        ConfigAction::Show { origin: true } => {
+            let layered = load_global_config(Path::new("."), &ConfigOverrides::default(), false)?;
+            println!("Effective configuration for the current directory:");
+            for (name, value) in layered.settings() {
+                let origins: Vec<String> = layered
+                    .origin(name)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                println!("{name} = {value}");
+                println!("    from {}", origins.join(" + "));
+            }
+        }
+        ConfigAction::Show { origin: false } => {
+            let config = read_global_config(false);
             println!("Global code annotation pattern:");
             println!("{}", config.annotation_pattern);
+            println!("\nAnnotation kinds:");
+            for kind in &config.kinds {
+                println!("  {} ({})", kind.name, kind.severity);
+            }
             if let Ok(config_path) = get_global_config_path() {
                 println!("\nConfig file location: {}", config_path.display());
             }
@@ -21,27 +45,479 @@ pub fn handle_config(action: ConfigAction) -> Result<()> {
                 println!("Projects file location: {}", projects_path.display());
             }
         }
-        ConfigAction::SetPattern { pattern } => match Regex::new(&pattern) {
+        ConfigAction::SetPattern { pattern } => match compile_pattern(&pattern) {
             Ok(_) => {
-                let config = CodemarksConfig {
-                    annotation_pattern: pattern.clone(),
-                };
+                let mut config = read_global_config(false);
+                config.annotation_pattern.clone_from(&pattern);
                 save_global_config(&config, false)?;
                 println!("Global code annotation pattern updated to: {pattern}");
             }
             Err(e) => {
-                eprintln!("Invalid regex pattern: {e}");
-                return Err(e.into());
+                eprintln!("{e}");
+                return Err(e);
             }
         },
         ConfigAction::Reset => {
-            let config = CodemarksConfig::default();
+            // Only the pattern is reset, other settings are kept
+            let mut config = read_global_config(false);
+            config.annotation_pattern = default_annotation_pattern();
             save_global_config(&config, false)?;
             println!(
                 "Global code annotation pattern reset to default: {0}",
                 config.annotation_pattern
             );
         }
+        ConfigAction::Get { key } => {
+            let setting = find_setting(&key)?;
+            let config = serde_json::to_value(read_global_config(false))?;
+            match config.get(setting.key) {
+                Some(serde_json::Value::String(value)) => println!("{value}"),
+                Some(value) => println!("{}", serde_json::to_string_pretty(value)?),
+                None => println!("(not set)"),
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let setting = find_setting(&key)?;
+            let value = setting
+                .value_type
+                .parse(&value)
+                .map_err(|e| anyhow::anyhow!("Invalid value for {}: {e}", setting.key))?;
+            let mut config = serde_json::to_value(read_global_config(false))?;
+            config[setting.key] = value;
+            let config = validate_config(config)
+                .map_err(|e| anyhow::anyhow!("Invalid value for {}: {e}", setting.key))?;
+            save_global_config(&config, false)?;
+            println!("Setting {} updated", setting.key);
+        }
+        ConfigAction::Unset { key } => {
+            let setting = find_setting(&key)?;
+            let mut config = serde_json::to_value(read_global_config(false))?;
+            if let Some(config) = config.as_object_mut() {
+                config.remove(setting.key);
+            }
+            save_global_config(&validate_config(config)?, false)?;
+            println!("Setting {} restored to its default", setting.key);
+        }
+        ConfigAction::Edit => edit_config()?,
+        ConfigAction::Ignore { action } => handle_ignore(action)?,
+        ConfigAction::Kind { action } => handle_kind(action)?,
+        ConfigAction::Test {
+            pattern,
+            line,
+            file,
+        } => {
+            let mut samples: Vec<SampleLine> = line
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| SampleLine {
+                    location: format!("line {}", index + 1),
+                    file: None,
+                    text,
+                })
+                .collect();
+            if let Some(file) = &file {
+                for source_line in read_source_lines(file)? {
+                    samples.push(SampleLine {
+                        location: format_location(
+                            &file.to_string_lossy(),
+                            source_line.line_number,
+                            source_line.cell,
+                        ),
+                        file: Some(file.clone()),
+                        text: source_line.text,
+                    });
+                }
+            }
+            if samples.is_empty() {
+                anyhow::bail!("Nothing to test, pass sample lines with --line or a --file");
+            }
+            let layered = load_global_config(Path::new("."), &ConfigOverrides::default(), false)?;
+            for report_line in pattern_report(pattern.as_deref(), &layered.config, &samples)? {
+                println!("{report_line}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A line to test a pattern against, with the file it was read from
+struct SampleLine {
+    location: String,
+    file: Option<PathBuf>,
+    text: String,
+}
+
+impl SampleLine {
+    /// Matches the line, using the comment syntax of its file when there is one
+    fn match_with(&self, matcher: &Matcher) -> Option<LineMatch> {
+        match &self.file {
+            Some(file) => matcher.match_file_line(file, &self.text),
+            None => matcher.match_line(&self.text),
+        }
+    }
+}
+
+/// Describes how a pattern matches the sample lines: what it extracts from each
+/// matching line and how that differs from the configured pattern
+fn pattern_report(
+    pattern: Option<&str>,
+    config: &CodemarksConfig,
+    samples: &[SampleLine],
+) -> Result<Vec<String>> {
+    let configured = Matcher::new(config)?;
+    let tested_pattern = pattern.map_or_else(|| config.effective_pattern(), str::to_string);
+    let tested = match pattern {
+        Some(pattern) => Matcher::with_pattern(pattern, &config.kinds)?,
+        None => Matcher::new(config)?,
+    };
+    let regex = Regex::new(&tested_pattern)?;
+    let group_names: Vec<Option<&str>> = regex.capture_names().collect();
+
+    let mut report = vec![format!("Testing pattern: {tested_pattern}")];
+    let mut matched = 0;
+    let mut only_configured = Vec::new();
+    for sample in samples {
+        let configured_match = sample.match_with(&configured);
+        let Some(line_match) = sample.match_with(&tested) else {
+            if pattern.is_some() && configured_match.is_some() {
+                only_configured.push(format!("  {}: {}", sample.location, sample.text));
+            }
+            continue;
+        };
+        matched += 1;
+        report.push(format!("\n{}: {}", sample.location, sample.text));
+        report.push(format!("    description: {}", line_match.description));
+        report.push(format!(
+            "    kind: {}",
+            line_match.kind.as_deref().unwrap_or("(none)")
+        ));
+        for (label, value) in [("owner", &line_match.owner), ("issue", &line_match.issue)] {
+            if let Some(value) = value {
+                report.push(format!("    {label}: {value}"));
+            }
+        }
+        if let Some(captures) = regex.captures(&sample.text) {
+            // Named groups are shown as fields above, and so is the unnamed group that
+            // serves as the description when there is no `desc` group
+            let description_group = if group_names.contains(&Some("desc")) {
+                None
+            } else {
+                group_names.iter().skip(1).position(Option::is_none)
+            };
+            for (index, name) in group_names.iter().enumerate().skip(1) {
+                if name.is_some() || description_group == Some(index - 1) {
+                    continue;
+                }
+                if let Some(value) = captures.get(index) {
+                    report.push(format!("    group {index}: {}", value.as_str()));
+                }
+            }
+        }
+        if pattern.is_some() {
+            match configured_match {
+                None => report.push("    not matched by the configured pattern".to_string()),
+                Some(configured_match) if configured_match != line_match => {
+                    report.push(format!(
+                        "    configured pattern extracts: {} ({})",
+                        configured_match.description,
+                        configured_match.kind.as_deref().unwrap_or("no kind")
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    if !only_configured.is_empty() {
+        report.push("\nOnly matched by the configured pattern:".to_string());
+        report.extend(only_configured);
+    }
+    report.push(format!("\n{matched} of {} lines match", samples.len()));
+    Ok(report)
+}
+
+/// How the value of a setting is given on the command line
+enum ValueType {
+    Text,
+    Integer,
+    /// Comma separated, or a JSON array
+    List,
+    Json,
+}
+
+impl ValueType {
+    /// Converts a command line value into JSON. The resulting configuration is
+    /// validated as a whole afterwards, which also checks the allowed values.
+    fn parse(&self, value: &str) -> Result<serde_json::Value> {
+        Ok(match self {
+            ValueType::Text => serde_json::Value::String(value.to_string()),
+            ValueType::Integer => value
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| anyhow::anyhow!("expected a whole number ({e})"))?
+                .into(),
+            ValueType::List if value.trim_start().starts_with('[') => serde_json::from_str(value)?,
+            ValueType::List => value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>()
+                .into(),
+            ValueType::Json => serde_json::from_str(value)?,
+        })
+    }
+}
+
+/// A setting of the global configuration that can be read and changed by name
+struct Setting {
+    key: &'static str,
+    value_type: ValueType,
+}
+
+const SETTINGS: [Setting; 10] = [
+    Setting {
+        key: "annotation_pattern",
+        value_type: ValueType::Text,
+    },
+    Setting {
+        key: "kinds",
+        value_type: ValueType::Json,
+    },
+    Setting {
+        key: "comment_syntax",
+        value_type: ValueType::Json,
+    },
+    Setting {
+        key: "ignore",
+        value_type: ValueType::List,
+    },
+    Setting {
+        key: "project_ignore",
+        value_type: ValueType::Json,
+    },
+    Setting {
+        key: "debounce",
+        value_type: ValueType::Integer,
+    },
+    Setting {
+        key: "output_format",
+        value_type: ValueType::Text,
+    },
+    Setting {
+        key: "profiles",
+        value_type: ValueType::Json,
+    },
+    Setting {
+        key: "repository_url",
+        value_type: ValueType::Text,
+    },
+    Setting {
+        key: "editor",
+        value_type: ValueType::Text,
+    },
+];
+
+fn find_setting(key: &str) -> Result<&'static Setting> {
+    // `pattern` is accepted as well, as that is what the repository config calls it
+    let key = if key == "pattern" {
+        "annotation_pattern"
+    } else {
+        key
+    };
+    SETTINGS
+        .iter()
+        .find(|setting| setting.key == key)
+        .ok_or_else(|| {
+            let keys: Vec<&str> = SETTINGS.iter().map(|setting| setting.key).collect();
+            anyhow::anyhow!(
+                "Unknown setting '{key}', expected one of: {}",
+                keys.join(", ")
+            )
+        })
+}
+
+/// Checks that a configuration has the right types and that its patterns compile
+fn validate_config(value: serde_json::Value) -> Result<CodemarksConfig> {
+    let config: CodemarksConfig = serde_json::from_value(value)?;
+    Matcher::new(&config)?;
+    Ok(config)
+}
+
+/// Opens the global configuration in the user's editor. When the edited file is not
+/// a valid configuration, the previous contents are restored.
+fn edit_config() -> Result<()> {
+    let config_path = get_global_config_path()?;
+    if !config_path.exists() {
+        save_global_config(&read_global_config(false), false)?;
+    }
+    let original = fs::read_to_string(&config_path)?;
+    edit_file(&config_path)?;
+
+    let edited = fs::read_to_string(&config_path)?;
+    let result = serde_json::from_str(&edited)
+        .map_err(anyhow::Error::from)
+        .and_then(validate_config);
+    if let Err(e) = result {
+        fs::write(&config_path, original)?;
+        anyhow::bail!("Invalid configuration, changes discarded: {e}");
+    }
+    println!("Configuration saved to {}", config_path.display());
+    Ok(())
+}
+
+fn handle_ignore(action: IgnoreAction) -> Result<()> {
+    let mut config = read_global_config(false);
+    match action {
+        IgnoreAction::List => {
+            println!("Global:");
+            for pattern in &config.ignore {
+                println!("  {pattern}");
+            }
+            for (project, patterns) in &config.project_ignore {
+                println!("{project}:");
+                for pattern in patterns {
+                    println!("  {pattern}");
+                }
+            }
+        }
+        IgnoreAction::Add { pattern, target } => {
+            let project = ignore_target(&target)?;
+            let patterns = match &project {
+                Some(project) => config.project_ignore.entry(project.clone()).or_default(),
+                None => &mut config.ignore,
+            };
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern.clone());
+            }
+            save_global_config(&config, false)?;
+            println!(
+                "Ignoring '{pattern}' {}",
+                describe_target(project.as_deref())
+            );
+        }
+        IgnoreAction::Remove { pattern, target } => {
+            let project = ignore_target(&target)?;
+            let patterns = match &project {
+                Some(project) => config.project_ignore.entry(project.clone()).or_default(),
+                None => &mut config.ignore,
+            };
+            let count = patterns.len();
+            patterns.retain(|existing| *existing != pattern);
+            if patterns.len() == count {
+                anyhow::bail!(
+                    "'{pattern}' is not ignored {}",
+                    describe_target(project.as_deref())
+                );
+            }
+            config
+                .project_ignore
+                .retain(|_, patterns| !patterns.is_empty());
+            save_global_config(&config, false)?;
+            println!(
+                "No longer ignoring '{pattern}' {}",
+                describe_target(project.as_deref())
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the project whose ignore patterns are changed, `None` meaning the global ones
+fn ignore_target(target: &IgnoreTarget) -> Result<Option<String>> {
+    if target.global {
+        return Ok(None);
+    }
+    if let Some(project) = &target.project {
+        return Ok(Some(project.clone()));
+    }
+    let directory = Path::new(".");
+    let layered = load_global_config(directory, &ConfigOverrides::default(), false)?;
+    Ok(Some(
+        layered
+            .project_name_for(directory)
+            .unwrap_or_else(|| detect_project_name(directory)),
+    ))
+}
+
+fn describe_target(project: Option<&str>) -> String {
+    match project {
+        Some(project) => format!("in project '{project}'"),
+        None => "in all projects".to_string(),
+    }
+}
+
+/// Colors that can be used to display annotation kinds
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+fn handle_kind(action: KindAction) -> Result<()> {
+    let mut config = read_global_config(false);
+    match action {
+        KindAction::List => {
+            for kind in &config.kinds {
+                println!(
+                    "{} ({}): {}",
+                    kind.name,
+                    kind.severity,
+                    kind.keywords().join(", ")
+                );
+                if let Some(pattern) = &kind.pattern {
+                    println!("    pattern: {pattern}");
+                }
+                if let Some(color) = &kind.color {
+                    println!("    color: {color}");
+                }
+            }
+        }
+        KindAction::Add {
+            name,
+            keyword,
+            pattern,
+            severity,
+            color,
+        } => {
+            if let Some(pattern) = &pattern {
+                Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid regex pattern: {e}"))?;
+            }
+            if let Some(color) = &color
+                && !COLORS.contains(&color.as_str())
+            {
+                anyhow::bail!(
+                    "Unknown color '{color}', expected one of: {}",
+                    COLORS.join(", ")
+                );
+            }
+            let kind = AnnotationKind {
+                name,
+                keywords: keyword,
+                pattern,
+                severity,
+                color,
+            };
+            let message = format!("Annotation kind {} saved", kind.name);
+            match config
+                .kinds
+                .iter_mut()
+                .find(|existing| existing.name.eq_ignore_ascii_case(&kind.name))
+            {
+                Some(existing) => *existing = kind,
+                None => config.kinds.push(kind),
+            }
+            save_global_config(&config, false)?;
+            println!("{message}");
+        }
+        KindAction::Remove { name } => {
+            let count = config.kinds.len();
+            config
+                .kinds
+                .retain(|kind| !kind.name.eq_ignore_ascii_case(&name));
+            if config.kinds.len() == count {
+                anyhow::bail!("Unknown annotation kind: {name}");
+            }
+            save_global_config(&config, false)?;
+            println!("Annotation kind {name} removed");
+        }
     }
     Ok(())
 }
@@ -1,5 +1,5 @@
 use super::*;
-use crate::default_annotation_pattern;
+use crate::{Severity, default_annotation_pattern};
 use std::env;
 use tempfile::TempDir;
 
@@ -16,7 +16,7 @@ fn test_config_show() {
     let _temp_home = setup_temp_home();
 
     // Test show config functionality
-    let result = handle_config(ConfigAction::Show);
+    let result = handle_config(ConfigAction::Show { origin: false });
     assert!(result.is_ok());
 }
 
@@ -52,3 +52,302 @@ fn test_config_invalid_pattern() {
     // Should handle the error gracefully
     assert!(result.is_ok() || result.is_err()); // Either way is fine, just shouldn't panic
 }
+
+#[test]
+fn test_config_kinds() {
+    let _temp_home = setup_temp_home();
+
+    let result = handle_config(ConfigAction::Kind {
+        action: KindAction::Add {
+            name: "NOTE".to_string(),
+            keyword: vec!["NOTE".to_string(), "NB".to_string()],
+            pattern: None,
+            severity: Severity::Info,
+            color: Some("blue".to_string()),
+        },
+    });
+    assert!(result.is_ok());
+    let config = read_global_config(false);
+    let note = config
+        .kinds
+        .iter()
+        .find(|kind| kind.name == "NOTE")
+        .unwrap();
+    assert_eq!(note.severity, Severity::Info);
+    assert_eq!(note.keywords, vec!["NOTE", "NB"]);
+
+    // Adding an existing kind updates it
+    let result = handle_config(ConfigAction::Kind {
+        action: KindAction::Add {
+            name: "todo".to_string(),
+            keyword: Vec::new(),
+            pattern: None,
+            severity: Severity::Error,
+            color: None,
+        },
+    });
+    assert!(result.is_ok());
+    let config = read_global_config(false);
+    assert_eq!(config.kinds.len(), 4);
+    assert_eq!(config.kinds[1].severity, Severity::Error);
+
+    let result = handle_config(ConfigAction::Kind {
+        action: KindAction::Add {
+            name: "BAD".to_string(),
+            keyword: Vec::new(),
+            pattern: None,
+            severity: Severity::Info,
+            color: Some("purple".to_string()),
+        },
+    });
+    assert!(result.is_err());
+
+    let result = handle_config(ConfigAction::Kind {
+        action: KindAction::Remove {
+            name: "NOTE".to_string(),
+        },
+    });
+    assert!(result.is_ok());
+    assert_eq!(read_global_config(false).kinds.len(), 3);
+
+    let result = handle_config(ConfigAction::Kind {
+        action: KindAction::Remove {
+            name: "NOTE".to_string(),
+        },
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_set_pattern_keeps_kinds() {
+    let _temp_home = setup_temp_home();
+
+    handle_config(ConfigAction::Kind {
+        action: KindAction::Remove {
+            name: "HACK".to_string(),
+        },
+    })
+    .unwrap();
+    handle_config(ConfigAction::SetPattern {
+        pattern: "CUSTOM".to_string(),
+    })
+    .unwrap();
+
+    let config = read_global_config(false);
+    assert_eq!(config.annotation_pattern, "CUSTOM");
+    assert_eq!(config.kinds.len(), 2);
+}
+
+fn samples(lines: &[&str]) -> Vec<SampleLine> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, text)| SampleLine {
+            location: format!("line {}", index + 1),
+            file: None,
+            text: (*text).to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_pattern_report_compares_with_configured_pattern() {
+    let config = CodemarksConfig::default();
+    let samples = samples(&[
+        "// TODO(alice): rename this",
+        "// HACK: temporary",
+        "fn main() {}",
+    ]);
+
+    let report = pattern_report(
+        Some(r"//\s*TODO\((?P<owner>\w+)\):\s*(.*)"),
+        &config,
+        &samples,
+    )
+    .unwrap();
+    let report = report.join("\n");
+
+    assert!(report.contains("line 1: // TODO(alice): rename this"));
+    assert!(report.contains("owner: alice"));
+    assert!(report.contains("kind: TODO"));
+    // Group 1 of the tested pattern is the owner, so the description differs
+    assert!(report.contains("configured pattern extracts: (alice): rename this (TODO)"));
+    assert!(
+        report.contains("Only matched by the configured pattern:\n  line 2: // HACK: temporary")
+    );
+    assert!(report.contains("1 of 3 lines match"));
+}
+
+#[test]
+fn test_pattern_report_for_configured_pattern() {
+    let config = CodemarksConfig::default();
+    let report = pattern_report(None, &config, &samples(&["# FIXME crash on empty input"]))
+        .unwrap()
+        .join("\n");
+
+    assert!(report.contains("description: crash on empty input"));
+    assert!(report.contains("kind: FIXME"));
+    assert!(!report.contains("configured pattern extracts"));
+    assert!(report.contains("1 of 1 lines match"));
+}
+
+#[test]
+fn test_pattern_report_invalid_pattern() {
+    let config = CodemarksConfig::default();
+    assert!(pattern_report(Some("[invalid("), &config, &samples(&["x"])).is_err());
+}
+
+#[test]
+fn test_config_set_get_unset() {
+    let _temp_home = setup_temp_home();
+
+    handle_config(ConfigAction::Set {
+        key: "debounce".to_string(),
+        value: "750".to_string(),
+    })
+    .unwrap();
+    handle_config(ConfigAction::Set {
+        key: "ignore".to_string(),
+        value: "vendor/, *.min.js".to_string(),
+    })
+    .unwrap();
+    handle_config(ConfigAction::Set {
+        key: "pattern".to_string(),
+        value: "CUSTOM".to_string(),
+    })
+    .unwrap();
+    assert!(
+        handle_config(ConfigAction::Get {
+            key: "debounce".to_string()
+        })
+        .is_ok()
+    );
+
+    let config = read_global_config(false);
+    assert_eq!(config.debounce, Some(750));
+    assert_eq!(config.ignore, vec!["vendor/", "*.min.js"]);
+    assert_eq!(config.annotation_pattern, "CUSTOM");
+    // Other settings are kept
+    assert_eq!(config.kinds.len(), 3);
+
+    handle_config(ConfigAction::Unset {
+        key: "debounce".to_string(),
+    })
+    .unwrap();
+    let config = read_global_config(false);
+    assert_eq!(config.debounce, None);
+    assert_eq!(config.annotation_pattern, "CUSTOM");
+}
+
+#[test]
+fn test_config_set_validates_values() {
+    let _temp_home = setup_temp_home();
+
+    let invalid = [
+        ("debounce", "soon"),
+        ("output_format", "xml"),
+        ("annotation_pattern", "[invalid("),
+        ("kinds", "not json"),
+        ("kinds", r#"[{"name": "BAD", "pattern": "(unclosed"}]"#),
+        ("unknown", "value"),
+    ];
+    for (key, value) in invalid {
+        let result = handle_config(ConfigAction::Set {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+        assert!(result.is_err(), "{key} = {value} should be rejected");
+    }
+    assert!(
+        handle_config(ConfigAction::Get {
+            key: "unknown".to_string()
+        })
+        .is_err()
+    );
+
+    handle_config(ConfigAction::Set {
+        key: "kinds".to_string(),
+        value: r#"[{"name": "NOTE", "severity": "info"}]"#.to_string(),
+    })
+    .unwrap();
+    let config = read_global_config(false);
+    assert_eq!(config.kinds.len(), 1);
+    assert_eq!(config.kinds[0].severity, Severity::Info);
+}
+
+#[test]
+fn test_value_types() {
+    assert_eq!(
+        ValueType::List.parse(r#"["a", "b"]"#).unwrap(),
+        serde_json::json!(["a", "b"])
+    );
+    assert_eq!(
+        ValueType::List.parse("a,,b ").unwrap(),
+        serde_json::json!(["a", "b"])
+    );
+    assert_eq!(
+        ValueType::Integer.parse(" 42 ").unwrap(),
+        serde_json::json!(42)
+    );
+    assert!(ValueType::Integer.parse("-1").is_err());
+}
+
+#[test]
+fn test_config_ignore_patterns() {
+    let _temp_home = setup_temp_home();
+    let project = IgnoreTarget {
+        project: Some("my-app".to_string()),
+        global: false,
+    };
+    let global = IgnoreTarget {
+        project: None,
+        global: true,
+    };
+
+    handle_config(ConfigAction::Ignore {
+        action: IgnoreAction::Add {
+            pattern: "vendor/".to_string(),
+            target: project,
+        },
+    })
+    .unwrap();
+    handle_config(ConfigAction::Ignore {
+        action: IgnoreAction::Add {
+            pattern: "*.min.js".to_string(),
+            target: global,
+        },
+    })
+    .unwrap();
+    let config = read_global_config(false);
+    assert_eq!(config.project_ignore["my-app"], vec!["vendor/"]);
+    assert_eq!(config.ignore, vec!["*.min.js"]);
+    assert!(
+        handle_config(ConfigAction::Ignore {
+            action: IgnoreAction::List
+        })
+        .is_ok()
+    );
+
+    handle_config(ConfigAction::Ignore {
+        action: IgnoreAction::Remove {
+            pattern: "vendor/".to_string(),
+            target: IgnoreTarget {
+                project: Some("my-app".to_string()),
+                global: false,
+            },
+        },
+    })
+    .unwrap();
+    assert!(read_global_config(false).project_ignore.is_empty());
+
+    let result = handle_config(ConfigAction::Ignore {
+        action: IgnoreAction::Remove {
+            pattern: "vendor/".to_string(),
+            target: IgnoreTarget {
+                project: None,
+                global: true,
+            },
+        },
+    });
+    assert!(result.is_err());
+}
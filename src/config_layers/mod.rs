@@ -0,0 +1,464 @@
+// src/config_layers.rs
+// Layers the configuration sources and remembers where each setting came from
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::repo_config::{KindSpec, Profile, RepoConfig, load_repo_config};
+use crate::watch::DEFAULT_DEBOUNCE_MS;
+use crate::{CodemarksConfig, detect_project_name, get_global_config_path};
+
+/// Environment variables that override configuration settings
+pub const ENV_PATTERN: &str = "CODEMARKS_PATTERN";
+pub const ENV_KINDS: &str = "CODEMARKS_KINDS";
+pub const ENV_IGNORE: &str = "CODEMARKS_IGNORE";
+pub const ENV_PROJECT: &str = "CODEMARKS_PROJECT";
+pub const ENV_MAX_ANNOTATIONS: &str = "CODEMARKS_MAX_ANNOTATIONS";
+pub const ENV_PROFILE: &str = "CODEMARKS_PROFILE";
+
+/// Profile selected with the global `--profile` flag
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Selects the profile given on the command line, which wins over `CODEMARKS_PROFILE`
+pub fn select_profile(name: &str) {
+    let _ = SELECTED_PROFILE.set(name.to_string());
+}
+
+/// A source of configuration, in increasing order of precedence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigLayer {
+    Default,
+    Global(PathBuf),
+    Repo(PathBuf),
+    Env(&'static str),
+    Cli(&'static str),
+    /// A named profile defined in one of the configuration files
+    Profile(String, Box<ConfigLayer>),
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigLayer::Default => write!(f, "default"),
+            ConfigLayer::Global(path) => write!(f, "global config ({})", path.display()),
+            ConfigLayer::Repo(path) => write!(f, "repository config ({})", path.display()),
+            ConfigLayer::Env(name) => write!(f, "environment variable {name}"),
+            ConfigLayer::Cli(flag) => write!(f, "command line flag {flag}"),
+            ConfigLayer::Profile(name, layer) => write!(f, "profile '{name}' in {layer}"),
+        }
+    }
+}
+
+/// Settings given on the command line, which take precedence over every other layer
+#[derive(Debug, Default, Clone)]
+pub struct ConfigOverrides {
+    pub pattern: Option<String>,
+    pub ignore: Vec<String>,
+    pub debounce: Option<u64>,
+    pub repository_url: Option<String>,
+}
+
+/// The effective configuration for a directory, together with the origin of each setting
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: CodemarksConfig,
+    pub repo: RepoConfig,
+    /// Ignore patterns of all layers, which add up instead of replacing each other
+    pub ignore: Vec<String>,
+    pub max_annotations: Option<usize>,
+    project: Option<String>,
+    /// Name of the selected profile
+    profile: Option<String>,
+    origins: BTreeMap<&'static str, Vec<ConfigLayer>>,
+}
+
+impl LayeredConfig {
+    /// Starts from the built-in defaults
+    fn new() -> Self {
+        let origins = SETTINGS
+            .iter()
+            .map(|&name| (name, vec![ConfigLayer::Default]))
+            .collect();
+        Self {
+            config: CodemarksConfig::default(),
+            repo: RepoConfig::default(),
+            ignore: Vec::new(),
+            max_annotations: None,
+            project: None,
+            profile: None,
+            origins,
+        }
+    }
+
+    /// Loads all layers that apply to `directory`. Ephemeral mode skips the global config.
+    pub fn load(directory: &Path, overrides: &ConfigOverrides, ephemeral: bool) -> Result<Self> {
+        let global = if ephemeral {
+            None
+        } else {
+            get_global_config_path().ok().and_then(|config_path| {
+                let content = fs::read_to_string(&config_path).ok()?;
+                Some((content, config_path))
+            })
+        };
+        Self::build(directory, overrides, global, |name| {
+            std::env::var(name).ok()
+        })
+    }
+
+    /// Layers the global config contents, the repository config of `directory`, the
+    /// environment variables looked up with `var` and the command line overrides
+    fn build(
+        directory: &Path,
+        overrides: &ConfigOverrides,
+        global: Option<(String, PathBuf)>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut layered = Self::new();
+        layered.select_profile(SELECTED_PROFILE.get().cloned(), var(ENV_PROFILE));
+        let mut global_path = None;
+        if let Some((content, config_path)) = global {
+            layered.apply_global(&content, &config_path);
+            global_path = Some(config_path);
+        }
+        let repo = load_repo_config(directory)?;
+        let mut profiles = Vec::new();
+        if let Some(name) = layered.profile.clone() {
+            if let (Some(profile), Some(path)) = (layered.config.profiles.get(&name), &global_path)
+            {
+                profiles.push((profile.clone(), ConfigLayer::Global(path.clone())));
+            }
+            if let Some(profile) = repo.profile.get(&name) {
+                let repo_path = repo.path.clone().unwrap_or_default();
+                profiles.push((profile.clone(), ConfigLayer::Repo(repo_path)));
+            }
+            if profiles.is_empty() {
+                anyhow::bail!("Unknown profile '{name}'");
+            }
+        }
+        layered.apply_repo(repo);
+        if let Some(path) = global_path {
+            layered.apply_project_ignore(directory, var(ENV_PROJECT), path);
+        }
+        // The selected profile overrides both files, so its ignore patterns replace all of theirs
+        for (profile, source) in &profiles {
+            layered.apply_profile(profile, source.clone());
+        }
+        layered.apply_env(var)?;
+        layered.apply_overrides(overrides);
+        Ok(layered)
+    }
+
+    fn set_origin(&mut self, name: &'static str, layer: ConfigLayer) {
+        self.origins.insert(name, vec![layer]);
+    }
+
+    fn add_origin(&mut self, name: &'static str, layer: ConfigLayer) {
+        let origins = self.origins.entry(name).or_default();
+        origins.retain(|origin| *origin != ConfigLayer::Default);
+        if !origins.contains(&layer) {
+            origins.push(layer);
+        }
+    }
+
+    /// Applies the settings present in the global `config.json`
+    fn apply_global(&mut self, content: &str, path: &Path) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(content) else {
+            return;
+        };
+        let Ok(config) = serde_json::from_value::<CodemarksConfig>(value.clone()) else {
+            return;
+        };
+        let layer = ConfigLayer::Global(path.to_path_buf());
+        for (key, name) in [
+            ("annotation_pattern", "pattern"),
+            ("kinds", "kinds"),
+            ("comment_syntax", "comment_syntax"),
+            ("debounce", "debounce"),
+            ("output_format", "output_format"),
+            ("repository_url", "repository_url"),
+            ("editor", "editor"),
+        ] {
+            if value.get(key).is_some() {
+                self.set_origin(name, layer.clone());
+            }
+        }
+        if !config.ignore.is_empty() {
+            self.ignore.extend(config.ignore.iter().cloned());
+            self.add_origin("ignore", layer);
+        }
+        self.config = config;
+    }
+
+    /// Applies the settings of the repository `.codemarks.toml`
+    fn apply_repo(&mut self, repo: RepoConfig) {
+        if let Some(path) = &repo.path {
+            let layer = ConfigLayer::Repo(path.clone());
+            if !repo.kinds.is_empty() {
+                self.set_origin("kinds", layer.clone());
+                self.set_origin("pattern", layer.clone());
+            }
+            if repo.pattern.is_some() {
+                self.set_origin("pattern", layer.clone());
+            }
+            if !repo.comment_syntax.is_empty() {
+                self.add_origin("comment_syntax", layer.clone());
+            }
+            if !repo.ignore.is_empty() {
+                self.add_origin("ignore", layer.clone());
+            }
+            if repo.project.is_some() {
+                self.set_origin("project", layer.clone());
+            }
+            if repo.repository_url.is_some() {
+                self.set_origin("repository_url", layer.clone());
+            }
+            if repo.ci.max_annotations.is_some() {
+                self.set_origin("ci.max_annotations", layer);
+            }
+        }
+        repo.apply_to(&mut self.config);
+        self.ignore.extend(repo.ignore.iter().cloned());
+        if repo.ci.max_annotations.is_some() {
+            self.max_annotations = repo.ci.max_annotations;
+        }
+        self.repo = repo;
+    }
+
+    /// Adds the ignore patterns that the global config stores for the project in `directory`,
+    /// which `CODEMARKS_PROJECT` names when it is set
+    fn apply_project_ignore(
+        &mut self,
+        directory: &Path,
+        env_project: Option<String>,
+        path: PathBuf,
+    ) {
+        if self.config.project_ignore.is_empty() {
+            return;
+        }
+        let project = env_project
+            .or_else(|| self.project_name_for(directory))
+            .unwrap_or_else(|| detect_project_name(directory));
+        if let Some(patterns) = self.config.project_ignore.get(&project)
+            && !patterns.is_empty()
+        {
+            self.ignore.extend(patterns.iter().cloned());
+            self.add_origin("ignore", ConfigLayer::Global(path));
+        }
+    }
+
+    /// Remembers which profile to apply, the command line one taking precedence
+    fn select_profile(&mut self, cli: Option<String>, env: Option<String>) {
+        if let Some(name) = cli {
+            self.profile = Some(name);
+            self.set_origin("profile", ConfigLayer::Cli("--profile"));
+        } else if let Some(name) = env.filter(|name| !name.is_empty()) {
+            self.profile = Some(name);
+            self.set_origin("profile", ConfigLayer::Env(ENV_PROFILE));
+        }
+    }
+
+    /// Applies a profile from the configuration file `source`. Unlike the files
+    /// themselves, a profile replaces the ignore patterns instead of adding to them.
+    fn apply_profile(&mut self, profile: &Profile, source: ConfigLayer) {
+        let name = self.profile.clone().unwrap_or_default();
+        let layer = ConfigLayer::Profile(name, Box::new(source));
+        if !profile.kinds.is_empty() {
+            self.set_origin("kinds", layer.clone());
+            self.set_origin("pattern", layer.clone());
+        }
+        if profile.pattern.is_some() {
+            self.set_origin("pattern", layer.clone());
+        }
+        if !profile.ignore.is_empty() {
+            self.ignore.clone_from(&profile.ignore);
+            self.set_origin("ignore", layer.clone());
+        }
+        if profile.ci.max_annotations.is_some() {
+            self.max_annotations = profile.ci.max_annotations;
+            self.set_origin("ci.max_annotations", layer);
+        }
+        profile.apply_to(&mut self.config);
+    }
+
+    /// Applies the settings given through `CODEMARKS_*` environment variables
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(kinds) = var(ENV_KINDS) {
+            let specs: Vec<KindSpec> = split_list(&kinds)
+                .map(|keyword| KindSpec::Keyword(keyword.to_string()))
+                .collect();
+            if !specs.is_empty() {
+                let repo = RepoConfig {
+                    kinds: specs,
+                    ..Default::default()
+                };
+                repo.apply_to(&mut self.config);
+                self.set_origin("kinds", ConfigLayer::Env(ENV_KINDS));
+                self.set_origin("pattern", ConfigLayer::Env(ENV_KINDS));
+            }
+        }
+        if let Some(pattern) = var(ENV_PATTERN) {
+            self.config.annotation_pattern = pattern;
+            self.set_origin("pattern", ConfigLayer::Env(ENV_PATTERN));
+        }
+        if let Some(ignore) = var(ENV_IGNORE) {
+            let before = self.ignore.len();
+            self.ignore.extend(split_list(&ignore).map(str::to_string));
+            if self.ignore.len() > before {
+                self.add_origin("ignore", ConfigLayer::Env(ENV_IGNORE));
+            }
+        }
+        if let Some(project) = var(ENV_PROJECT) {
+            self.project = Some(project);
+            self.set_origin("project", ConfigLayer::Env(ENV_PROJECT));
+        }
+        if let Some(max_annotations) = var(ENV_MAX_ANNOTATIONS) {
+            let max_annotations = max_annotations
+                .trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid {ENV_MAX_ANNOTATIONS}: {e}"))?;
+            self.max_annotations = Some(max_annotations);
+            self.set_origin("ci.max_annotations", ConfigLayer::Env(ENV_MAX_ANNOTATIONS));
+        }
+        Ok(())
+    }
+
+    /// Applies the settings given on the command line
+    fn apply_overrides(&mut self, overrides: &ConfigOverrides) {
+        if let Some(pattern) = &overrides.pattern {
+            self.config.annotation_pattern.clone_from(pattern);
+            self.set_origin("pattern", ConfigLayer::Cli("--pattern"));
+        }
+        if !overrides.ignore.is_empty() {
+            // Command line patterns come first, as they always did
+            self.ignore.splice(0..0, overrides.ignore.iter().cloned());
+            self.add_origin("ignore", ConfigLayer::Cli("--ignore"));
+        }
+        if let Some(debounce) = overrides.debounce {
+            self.config.debounce = Some(debounce);
+            self.set_origin("debounce", ConfigLayer::Cli("--debounce"));
+        }
+        if let Some(repository_url) = &overrides.repository_url {
+            self.config.repository_url = Some(repository_url.clone());
+            self.set_origin("repository_url", ConfigLayer::Cli("--repository-url"));
+        }
+    }
+
+    /// Returns the configured project name if it applies to `directory`. A project set
+    /// in the environment applies everywhere, the repository one only to its own project.
+    pub fn project_name_for(&self, directory: &Path) -> Option<String> {
+        self.project
+            .clone()
+            .or_else(|| self.repo.project_name_for(directory))
+    }
+
+    /// Returns the layers that define a setting
+    pub fn origin(&self, name: &str) -> &[ConfigLayer] {
+        self.origins.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns each setting with its effective value
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let kinds: Vec<&str> = self
+            .config
+            .kinds
+            .iter()
+            .map(|kind| kind.name.as_str())
+            .collect();
+        let project = self
+            .project
+            .clone()
+            .or_else(|| self.repo.project.clone())
+            .unwrap_or_else(|| "(detected from project files)".to_string());
+        let ignore = if self.ignore.is_empty() {
+            "(none)".to_string()
+        } else {
+            self.ignore.join(", ")
+        };
+        let comment_syntax: Vec<String> = self
+            .config
+            .comment_syntax
+            .iter()
+            .map(|(key, leaders)| format!("{key}: {}", leaders.join(" ")))
+            .collect();
+        let comment_syntax = if comment_syntax.is_empty() {
+            "(built-in)".to_string()
+        } else {
+            format!("built-in, {}", comment_syntax.join(", "))
+        };
+        vec![
+            (
+                "profile",
+                self.profile.clone().unwrap_or_else(|| "(none)".to_string()),
+            ),
+            ("pattern", self.config.effective_pattern()),
+            ("kinds", kinds.join(", ")),
+            ("comment_syntax", comment_syntax),
+            ("ignore", ignore),
+            ("project", project),
+            (
+                "ci.max_annotations",
+                self.max_annotations.unwrap_or(0).to_string(),
+            ),
+            (
+                "debounce",
+                self.config
+                    .debounce
+                    .unwrap_or(DEFAULT_DEBOUNCE_MS)
+                    .to_string(),
+            ),
+            (
+                "output_format",
+                self.config
+                    .output_format
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "repository_url",
+                self.config
+                    .repository_url
+                    .clone()
+                    .unwrap_or_else(|| "(none)".to_string()),
+            ),
+            (
+                "editor",
+                self.config
+                    .editor
+                    .clone()
+                    .unwrap_or_else(|| "(from $VISUAL or $EDITOR)".to_string()),
+            ),
+        ]
+    }
+}
+
+/// Names of the settings that are tracked per layer
+const SETTINGS: [&str; 11] = [
+    "profile",
+    "pattern",
+    "kinds",
+    "comment_syntax",
+    "ignore",
+    "project",
+    "ci.max_annotations",
+    "debounce",
+    "output_format",
+    "repository_url",
+    "editor",
+];
+
+/// Splits a comma separated environment variable into its non-empty items
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,316 @@
+use super::*;
+use crate::default_annotation_pattern;
+use crate::repo_config::REPO_CONFIG_FILE;
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn test_defaults() {
+    let layered = LayeredConfig::new();
+    assert_eq!(
+        layered.config.annotation_pattern,
+        default_annotation_pattern()
+    );
+    for name in SETTINGS {
+        assert_eq!(layered.origin(name), &[ConfigLayer::Default]);
+    }
+}
+
+#[test]
+fn test_global_layer_only_claims_present_settings() {
+    let mut layered = LayeredConfig::new();
+    let path = Path::new("/home/user/.codemarks/config.json");
+    layered.apply_global(r#"{"annotation_pattern": "CUSTOM"}"#, path);
+
+    assert_eq!(layered.config.annotation_pattern, "CUSTOM");
+    assert_eq!(
+        layered.origin("pattern"),
+        &[ConfigLayer::Global(path.to_path_buf())]
+    );
+    assert_eq!(layered.origin("kinds"), &[ConfigLayer::Default]);
+
+    // Invalid files are ignored, just like before layering
+    let mut layered = LayeredConfig::new();
+    layered.apply_global("not json", path);
+    assert_eq!(layered.origin("pattern"), &[ConfigLayer::Default]);
+}
+
+#[test]
+fn test_global_ignore_patterns() {
+    let mut layered = LayeredConfig::new();
+    let path = Path::new("config.json");
+    layered.apply_global(r#"{"ignore": ["target/"], "debounce": 500}"#, path);
+
+    assert_eq!(layered.ignore, vec!["target/"]);
+    assert_eq!(
+        layered.origin("ignore"),
+        &[ConfigLayer::Global(path.to_path_buf())]
+    );
+    assert_eq!(
+        layered.origin("debounce"),
+        &[ConfigLayer::Global(path.to_path_buf())]
+    );
+}
+
+#[test]
+fn test_precedence() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        r#"
+pattern = "REPO"
+ignore = ["vendor/"]
+project = "repo-project"
+
+[ci]
+max_annotations = 3
+"#,
+    )
+    .unwrap();
+    let repo_path = temp_dir
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(REPO_CONFIG_FILE);
+
+    let mut layered = LayeredConfig::new();
+    layered.apply_global(
+        r#"{"annotation_pattern": "GLOBAL"}"#,
+        Path::new("config.json"),
+    );
+    layered.apply_repo(load_repo_config(temp_dir.path()).unwrap());
+    assert_eq!(layered.config.annotation_pattern, "REPO");
+    assert_eq!(
+        layered.origin("pattern"),
+        &[ConfigLayer::Repo(repo_path.clone())]
+    );
+
+    layered
+        .apply_env(env(&[
+            (ENV_PATTERN, "ENV"),
+            (ENV_IGNORE, "dist/, build/"),
+            (ENV_MAX_ANNOTATIONS, "7"),
+        ]))
+        .unwrap();
+    assert_eq!(layered.config.annotation_pattern, "ENV");
+    assert_eq!(layered.max_annotations, Some(7));
+    assert_eq!(
+        layered.origin("ci.max_annotations"),
+        &[ConfigLayer::Env(ENV_MAX_ANNOTATIONS)]
+    );
+
+    layered.apply_overrides(&ConfigOverrides {
+        pattern: Some("CLI".to_string()),
+        ignore: vec!["*.log".to_string()],
+        debounce: Some(100),
+        repository_url: Some("https://example.com/{file}#L{line}".to_string()),
+    });
+    assert_eq!(layered.config.annotation_pattern, "CLI");
+    assert_eq!(
+        layered.origin("repository_url"),
+        &[ConfigLayer::Cli("--repository-url")]
+    );
+    assert_eq!(layered.origin("pattern"), &[ConfigLayer::Cli("--pattern")]);
+    assert_eq!(layered.config.debounce, Some(100));
+
+    // Ignore patterns of all layers add up
+    assert_eq!(layered.ignore, vec!["*.log", "vendor/", "dist/", "build/"]);
+    assert_eq!(
+        layered.origin("ignore"),
+        &[
+            ConfigLayer::Repo(repo_path.clone()),
+            ConfigLayer::Env(ENV_IGNORE),
+            ConfigLayer::Cli("--ignore"),
+        ]
+    );
+    assert_eq!(layered.origin("project"), &[ConfigLayer::Repo(repo_path)]);
+}
+
+#[test]
+fn test_env_kinds_and_project() {
+    let temp_dir = tempdir().unwrap();
+    let mut layered = LayeredConfig::new();
+    layered
+        .apply_env(env(&[
+            (ENV_KINDS, "TODO, XXX"),
+            (ENV_PROJECT, "env-project"),
+        ]))
+        .unwrap();
+
+    let names: Vec<&str> = layered
+        .config
+        .kinds
+        .iter()
+        .map(|kind| kind.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["TODO", "XXX"]);
+    assert!(layered.config.effective_pattern().contains("XXX"));
+    assert_eq!(layered.origin("kinds"), &[ConfigLayer::Env(ENV_KINDS)]);
+    assert_eq!(
+        layered.project_name_for(temp_dir.path()).as_deref(),
+        Some("env-project")
+    );
+}
+
+#[test]
+fn test_invalid_env_value() {
+    let mut layered = LayeredConfig::new();
+    let result = layered.apply_env(env(&[(ENV_MAX_ANNOTATIONS, "many")]));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_settings_values() {
+    let layered = LayeredConfig::new();
+    let settings: HashMap<&str, String> = layered.settings().into_iter().collect();
+    assert_eq!(settings["kinds"], "FIXME, TODO, HACK");
+    assert_eq!(settings["ignore"], "(none)");
+    assert_eq!(settings["ci.max_annotations"], "0");
+}
+
+#[test]
+fn test_profiles() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        r#"
+ignore = ["vendor/", "docs/"]
+
+[ci]
+max_annotations = 20
+
+[profile.strict]
+kinds = ["FIXME", "TODO", "XXX"]
+ignore = ["vendor/"]
+
+[profile.strict.ci]
+max_annotations = 0
+"#,
+    )
+    .unwrap();
+    let global = r#"{"profiles": {"audit": {"pattern": "AUDIT(.*)"}}}"#.to_string();
+    let global_path = PathBuf::from("config.json");
+
+    // Without a profile, only the regular settings apply
+    let layered = LayeredConfig::build(
+        temp_dir.path(),
+        &ConfigOverrides::default(),
+        Some((global.clone(), global_path.clone())),
+        env(&[]),
+    )
+    .unwrap();
+    assert_eq!(layered.ignore, vec!["vendor/", "docs/"]);
+    assert_eq!(layered.max_annotations, Some(20));
+
+    let layered = LayeredConfig::build(
+        temp_dir.path(),
+        &ConfigOverrides::default(),
+        Some((global.clone(), global_path.clone())),
+        env(&[(ENV_PROFILE, "strict")]),
+    )
+    .unwrap();
+    assert_eq!(layered.config.kinds.len(), 3);
+    assert_eq!(layered.ignore, vec!["vendor/"]);
+    assert_eq!(layered.max_annotations, Some(0));
+    assert_eq!(layered.origin("profile"), &[ConfigLayer::Env(ENV_PROFILE)]);
+    assert!(matches!(
+        layered.origin("ci.max_annotations"),
+        [ConfigLayer::Profile(name, _)] if name == "strict"
+    ));
+
+    // Profiles can also be defined in the global config
+    let layered = LayeredConfig::build(
+        temp_dir.path(),
+        &ConfigOverrides::default(),
+        Some((global.clone(), global_path.clone())),
+        env(&[(ENV_PROFILE, "audit")]),
+    )
+    .unwrap();
+    assert_eq!(layered.config.annotation_pattern, "AUDIT(.*)");
+    assert_eq!(
+        layered.origin("pattern"),
+        &[ConfigLayer::Profile(
+            "audit".to_string(),
+            Box::new(ConfigLayer::Global(global_path.clone()))
+        )]
+    );
+
+    let result = LayeredConfig::build(
+        temp_dir.path(),
+        &ConfigOverrides::default(),
+        Some((global, global_path)),
+        env(&[(ENV_PROFILE, "missing")]),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_profile_overrides_both_files() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        "project = \"my-service\"\npattern = \"REPO(.*)\"\nignore = [\"docs/\"]\n",
+    )
+    .unwrap();
+    let global = r#"{
+        "ignore": ["target/"],
+        "project_ignore": {"my-service": ["vendor/"]},
+        "profiles": {"audit": {"pattern": "AUDIT(.*)", "ignore": ["tests/"]}}
+    }"#
+    .to_string();
+
+    let layered = LayeredConfig::build(
+        temp_dir.path(),
+        &ConfigOverrides::default(),
+        Some((global, PathBuf::from("config.json"))),
+        env(&[(ENV_PROFILE, "audit"), (ENV_IGNORE, "*.log")]),
+    )
+    .unwrap();
+    // The global profile wins over the plain repository settings
+    assert_eq!(layered.config.annotation_pattern, "AUDIT(.*)");
+    // Its ignore patterns replace those of both files, and later layers still add to them
+    assert_eq!(layered.ignore, vec!["tests/", "*.log"]);
+}
+
+#[test]
+fn test_project_ignore_patterns() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        "project = \"my-service\"\n",
+    )
+    .unwrap();
+    let global = r#"{
+        "ignore": ["target/"],
+        "project_ignore": {"my-service": ["vendor/"], "other": ["dist/"]}
+    }"#
+    .to_string();
+    let global_path = PathBuf::from("config.json");
+
+    let layered = LayeredConfig::build(
+        temp_dir.path(),
+        &ConfigOverrides {
+            ignore: vec!["*.log".to_string()],
+            ..Default::default()
+        },
+        Some((global, global_path.clone())),
+        env(&[]),
+    )
+    .unwrap();
+    assert_eq!(layered.ignore, vec!["*.log", "target/", "vendor/"]);
+    assert_eq!(
+        layered.origin("ignore"),
+        &[
+            ConfigLayer::Global(global_path),
+            ConfigLayer::Cli("--ignore")
+        ]
+    );
+}
@@ -0,0 +1,137 @@
+// src/filters.rs
+// File selection by include globs and file types, shared by scan, ci and watch
+
+use anyhow::Result;
+use clap::Args;
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::types::{Types, TypesBuilder};
+use std::path::{Path, PathBuf};
+
+/// File with gitignore syntax listing paths that codemarks should skip
+pub const CODEMARKSIGNORE_FILE: &str = ".codemarksignore";
+
+/// Command line options that select which files are scanned
+#[derive(Args, Debug, Default, Clone)]
+pub struct FileSelection {
+    /// Only scan files matching these glob patterns
+    #[arg(long)]
+    pub include: Vec<String>,
+    /// Only scan files of these types (see `codemarks types`)
+    #[arg(short = 't', long = "type")]
+    pub types: Vec<String>,
+    /// Skip files of these types
+    #[arg(short = 'T', long = "type-not")]
+    pub types_not: Vec<String>,
+}
+
+impl FileSelection {
+    /// Builds the file type matcher, failing on unknown type names
+    fn build_types(&self) -> Result<Types> {
+        let mut types_builder = TypesBuilder::new();
+        types_builder.add_defaults();
+        for name in &self.types {
+            types_builder.select(name);
+        }
+        for name in &self.types_not {
+            types_builder.negate(name);
+        }
+        types_builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid file type: {e}"))
+    }
+
+    /// Builds the overrides for the ignore and include patterns. Include patterns
+    /// whitelist files, while ignore patterns are added as negative overrides.
+    fn build_overrides(&self, directory: &Path, ignore_patterns: &[String]) -> Override {
+        let mut override_builder = OverrideBuilder::new(directory);
+        for pattern in &self.include {
+            if let Err(e) = override_builder.add(pattern) {
+                eprintln!("Warning: Invalid include pattern '{pattern}': {e}");
+            }
+        }
+        for pattern in ignore_patterns {
+            if let Err(e) = override_builder.add(&format!("!{pattern}")) {
+                eprintln!("Warning: Invalid ignore pattern '{pattern}': {e}");
+            }
+        }
+        override_builder
+            .build()
+            .unwrap_or_else(|_| Override::empty())
+    }
+
+    /// Creates a directory walker that honors the ignore patterns and this selection
+    pub fn walker(&self, directory: &Path, ignore_patterns: &[String]) -> Result<WalkBuilder> {
+        let mut builder = WalkBuilder::new(directory);
+        builder.add_custom_ignore_filename(CODEMARKSIGNORE_FILE);
+        builder.types(self.build_types()?);
+        if !ignore_patterns.is_empty() || !self.include.is_empty() {
+            builder.overrides(self.build_overrides(directory, ignore_patterns));
+        }
+        Ok(builder)
+    }
+
+    /// Creates a matcher for checking individual files against the ignore patterns and
+    /// this selection, as used by the watcher. Only the `.codemarksignore` in `directory`
+    /// itself is taken into account.
+    pub fn matcher(&self, directory: &Path, ignore_patterns: &[String]) -> Result<FileMatcher> {
+        let mut ignore_builder = GitignoreBuilder::new(directory);
+        let ignore_file = directory.join(CODEMARKSIGNORE_FILE);
+        if ignore_file.is_file()
+            && let Some(e) = ignore_builder.add(&ignore_file)
+        {
+            eprintln!("Warning: Invalid {CODEMARKSIGNORE_FILE}: {e}");
+        }
+        Ok(FileMatcher {
+            root: directory.to_path_buf(),
+            overrides: self.build_overrides(directory, ignore_patterns),
+            types: self.build_types()?,
+            ignore_file: ignore_builder
+                .build()
+                .unwrap_or_else(|_| Gitignore::empty()),
+        })
+    }
+}
+
+/// Checks single paths against a file selection
+pub struct FileMatcher {
+    root: PathBuf,
+    overrides: Override,
+    types: Types,
+    ignore_file: Gitignore,
+}
+
+impl FileMatcher {
+    /// Checks whether a file is selected by the include patterns and file types, and
+    /// not excluded by an ignore pattern or the `.codemarksignore` file
+    pub fn is_selected(&self, file_path: &Path) -> bool {
+        let ignored_by_file = file_path.starts_with(&self.root)
+            && self
+                .ignore_file
+                .matched_path_or_any_parents(file_path, false)
+                .is_ignore();
+        // Like the walker, which skips ignored directories, check the parent directories
+        let ignored_directory = file_path
+            .ancestors()
+            .skip(1)
+            .take_while(|ancestor| ancestor.starts_with(&self.root) && *ancestor != self.root)
+            .any(|ancestor| self.overrides.matched(ancestor, true).is_ignore());
+        !ignored_by_file
+            && !ignored_directory
+            && !self.overrides.matched(file_path, false).is_ignore()
+            && !self.types.matched(file_path, false).is_ignore()
+    }
+}
+
+/// Prints the built-in file type definitions
+pub fn list_types() {
+    let mut types_builder = TypesBuilder::new();
+    types_builder.add_defaults();
+    for definition in types_builder.definitions() {
+        println!("{}: {}", definition.name(), definition.globs().join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests;
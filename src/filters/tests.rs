@@ -0,0 +1,131 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+fn walked_files(
+    directory: &Path,
+    ignore_patterns: &[String],
+    selection: &FileSelection,
+) -> Vec<String> {
+    let mut files: Vec<String> = selection
+        .walker(directory, ignore_patterns)
+        .unwrap()
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+fn create_files(directory: &Path) {
+    for name in ["main.rs", "script.py", "app.js", "notes.md"] {
+        fs::write(directory.join(name), "// TODO: Task").unwrap();
+    }
+}
+
+#[test]
+fn test_walker_without_selection() {
+    let temp_dir = tempdir().unwrap();
+    create_files(temp_dir.path());
+
+    let files = walked_files(temp_dir.path(), &[], &FileSelection::default());
+    assert_eq!(files.len(), 4);
+}
+
+#[test]
+fn test_walker_with_types() {
+    let temp_dir = tempdir().unwrap();
+    create_files(temp_dir.path());
+
+    let selection = FileSelection {
+        types: vec!["rust".to_string(), "py".to_string()],
+        ..Default::default()
+    };
+    let files = walked_files(temp_dir.path(), &[], &selection);
+    assert_eq!(files, vec!["main.rs", "script.py"]);
+}
+
+#[test]
+fn test_walker_with_type_not() {
+    let temp_dir = tempdir().unwrap();
+    create_files(temp_dir.path());
+
+    let selection = FileSelection {
+        types_not: vec!["markdown".to_string()],
+        ..Default::default()
+    };
+    let files = walked_files(temp_dir.path(), &[], &selection);
+    assert_eq!(files, vec!["app.js", "main.rs", "script.py"]);
+}
+
+#[test]
+fn test_walker_with_include_and_ignore() {
+    let temp_dir = tempdir().unwrap();
+    create_files(temp_dir.path());
+
+    let selection = FileSelection {
+        include: vec!["*.rs".to_string(), "*.js".to_string()],
+        ..Default::default()
+    };
+    let files = walked_files(temp_dir.path(), &["app.js".to_string()], &selection);
+    assert_eq!(files, vec!["main.rs"]);
+}
+
+#[test]
+fn test_walker_with_unknown_type() {
+    let temp_dir = tempdir().unwrap();
+    let selection = FileSelection {
+        types: vec!["not-a-language".to_string()],
+        ..Default::default()
+    };
+    assert!(selection.walker(temp_dir.path(), &[]).is_err());
+}
+
+#[test]
+fn test_file_matcher() {
+    let temp_dir = tempdir().unwrap();
+    let selection = FileSelection {
+        include: vec!["src/**".to_string()],
+        types_not: vec!["js".to_string()],
+        ..Default::default()
+    };
+    let matcher = selection.matcher(temp_dir.path(), &[]).unwrap();
+
+    assert!(matcher.is_selected(&temp_dir.path().join("src").join("main.rs")));
+    assert!(!matcher.is_selected(&temp_dir.path().join("src").join("app.js")));
+    assert!(!matcher.is_selected(&temp_dir.path().join("docs").join("guide.rs")));
+    assert!(
+        FileSelection::default()
+            .matcher(temp_dir.path(), &[])
+            .unwrap()
+            .is_selected(&temp_dir.path().join("any.txt"))
+    );
+}
+
+#[test]
+fn test_codemarksignore_file() {
+    let temp_dir = tempdir().unwrap();
+    create_files(temp_dir.path());
+    fs::create_dir(temp_dir.path().join("vendor")).unwrap();
+    fs::write(
+        temp_dir.path().join("vendor").join("lib.rs"),
+        "// TODO: Task",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join(CODEMARKSIGNORE_FILE),
+        "vendor/\n*.md\n",
+    )
+    .unwrap();
+
+    let files = walked_files(temp_dir.path(), &[], &FileSelection::default());
+    assert_eq!(files, vec!["app.js", "main.rs", "script.py"]);
+
+    let root = temp_dir.path().canonicalize().unwrap();
+    let matcher = FileSelection::default().matcher(&root, &[]).unwrap();
+    assert!(matcher.is_selected(&root.join("main.rs")));
+    assert!(!matcher.is_selected(&root.join("notes.md")));
+    assert!(!matcher.is_selected(&root.join("vendor").join("lib.rs")));
+}
@@ -0,0 +1,172 @@
+// src/formats.rs
+// Format-aware extraction of source lines from container files (notebooks, Markdown)
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// A line of source text together with its location inside the containing file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    /// 1-based line number, within the cell for notebooks and within the file otherwise
+    pub line_number: usize,
+    /// 1-based notebook cell or Markdown fenced code block the line belongs to
+    pub cell: Option<usize>,
+    pub text: String,
+}
+
+/// The kinds of files that need format-aware extraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileFormat {
+    Plain,
+    Notebook,
+    Markdown,
+}
+
+fn file_format(file: &Path) -> FileFormat {
+    match file
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .as_deref()
+    {
+        Some("ipynb") => FileFormat::Notebook,
+        Some("md" | "markdown") => FileFormat::Markdown,
+        _ => FileFormat::Plain,
+    }
+}
+
+/// Reads a file and extracts its source lines. Lines that are not valid UTF-8 are
+/// skipped, but still count towards the line numbers of the lines that follow.
+pub fn read_source_lines(file_path: &Path) -> Result<Vec<SourceLine>> {
+    let bytes = fs::read(file_path)?;
+    if let Ok(content) = std::str::from_utf8(&bytes) {
+        return Ok(source_lines(file_path, content));
+    }
+
+    let lines = bytes
+        .strip_suffix(b"\n")
+        .unwrap_or(&bytes)
+        .split(|&byte| byte == b'\n')
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let text = std::str::from_utf8(line).ok()?;
+            Some(SourceLine {
+                line_number: index + 1,
+                cell: None,
+                text: text.trim_end_matches('\r').to_string(),
+            })
+        })
+        .collect();
+    Ok(lines)
+}
+
+/// Extracts the source lines of already loaded file content based on the file format
+pub fn source_lines(file_path: &Path, content: &str) -> Vec<SourceLine> {
+    match file_format(file_path) {
+        FileFormat::Notebook => notebook_lines(content).unwrap_or_else(|| plain_lines(content)),
+        FileFormat::Markdown => markdown_lines(content),
+        FileFormat::Plain => plain_lines(content),
+    }
+}
+
+fn plain_lines(content: &str) -> Vec<SourceLine> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, text)| SourceLine {
+            line_number: index + 1,
+            cell: None,
+            text: text.to_string(),
+        })
+        .collect()
+}
+
+/// Extracts the lines of the code cells of a Jupyter notebook, numbered per cell.
+/// Returns `None` when the content is not a valid notebook.
+fn notebook_lines(content: &str) -> Option<Vec<SourceLine>> {
+    let notebook: serde_json::Value = serde_json::from_str(content).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+
+    let mut lines = Vec::new();
+    for (index, cell) in cells.iter().enumerate() {
+        if cell.get("cell_type").and_then(|t| t.as_str()) != Some("code") {
+            continue;
+        }
+        // Cell sources are stored either as one string or as a list of lines
+        let source = match cell.get("source") {
+            Some(serde_json::Value::String(source)) => source.clone(),
+            Some(serde_json::Value::Array(parts)) => {
+                parts.iter().filter_map(|part| part.as_str()).collect()
+            }
+            _ => continue,
+        };
+        for (line_index, text) in source.lines().enumerate() {
+            lines.push(SourceLine {
+                line_number: line_index + 1,
+                cell: Some(index + 1),
+                text: text.to_string(),
+            });
+        }
+    }
+    Some(lines)
+}
+
+/// Extracts the lines of a Markdown file, tagging lines inside fenced code blocks
+/// with the number of their block. Line numbers stay relative to the file.
+fn markdown_lines(content: &str) -> Vec<SourceLine> {
+    let mut lines = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut block = 0;
+
+    for (index, text) in content.lines().enumerate() {
+        let trimmed = text.trim_start();
+        let marker: String = match trimmed.chars().next() {
+            Some(c @ ('`' | '~')) => trimmed.chars().take_while(|&x| x == c).collect(),
+            _ => String::new(),
+        };
+        let is_fence = marker.len() >= 3;
+
+        let cell = match &fence {
+            Some(open) if is_fence && marker.starts_with(open.as_str()) => {
+                fence = None;
+                continue;
+            }
+            Some(_) => Some(block),
+            None if is_fence => {
+                block += 1;
+                fence = Some(marker);
+                continue;
+            }
+            None => None,
+        };
+        lines.push(SourceLine {
+            line_number: index + 1,
+            cell,
+            text: text.to_string(),
+        });
+    }
+    lines
+}
+
+/// Returns the line of an annotation in its file. Lines inside notebook cells are only
+/// numbered within their cell, while Markdown code blocks keep the lines of the file.
+#[must_use]
+pub fn file_line(file: &str, line_number: usize, cell: Option<usize>) -> Option<usize> {
+    match (cell, file_format(Path::new(file))) {
+        (Some(_), FileFormat::Notebook) => None,
+        _ => Some(line_number),
+    }
+}
+
+/// Renders the location of an annotation for display, e.g. `src/main.rs:12`,
+/// `analysis.ipynb[cell 3]:2` or `README.md:40 (code block 2)`
+pub fn format_location(file: &str, line_number: usize, cell: Option<usize>) -> String {
+    match (cell, file_format(Path::new(file))) {
+        (Some(cell), FileFormat::Notebook) => format!("{file}[cell {cell}]:{line_number}"),
+        (Some(block), _) => format!("{file}:{line_number} (code block {block})"),
+        (None, _) => format!("{file}:{line_number}"),
+    }
+}
+
+#[cfg(test)]
+mod tests;
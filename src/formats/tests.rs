@@ -0,0 +1,101 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_plain_lines() {
+    let lines = source_lines(Path::new("main.rs"), "fn main() {}\n// TODO: Plain\n");
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].line_number, 2);
+    assert_eq!(lines[1].cell, None);
+    assert_eq!(lines[1].text, "// TODO: Plain");
+}
+
+#[test]
+fn test_notebook_lines() {
+    let notebook = r##"{
+  "cells": [
+    {"cell_type": "markdown", "source": ["# TODO list\n"]},
+    {"cell_type": "code", "source": ["import os\n", "# TODO: Load data\n"]},
+    {"cell_type": "code", "source": "x = 1\ny = 2\n# FIXME: Wrong value"}
+  ],
+  "metadata": {},
+  "nbformat": 4,
+  "nbformat_minor": 5
+}"##;
+
+    let lines = source_lines(Path::new("analysis.ipynb"), notebook);
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[1].text, "# TODO: Load data");
+    assert_eq!(lines[1].cell, Some(2));
+    assert_eq!(lines[1].line_number, 2);
+    assert_eq!(lines[4].text, "# FIXME: Wrong value");
+    assert_eq!(lines[4].cell, Some(3));
+    assert_eq!(lines[4].line_number, 3);
+}
+
+#[test]
+fn test_invalid_notebook_falls_back_to_plain_lines() {
+    let lines = source_lines(Path::new("broken.ipynb"), "not json\n# TODO: Still found");
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].cell, None);
+    assert_eq!(lines[1].line_number, 2);
+}
+
+#[test]
+fn test_markdown_fenced_code_blocks() {
+    let markdown = "# Title\n\n```rust\n// TODO: In block\n```\n\ntext\n~~~~\n# FIXME: Shell\n```\nstill inside\n~~~~\n<!-- HACK: Prose -->\n";
+
+    let lines = source_lines(Path::new("README.md"), markdown);
+    let block_line = lines
+        .iter()
+        .find(|l| l.text == "// TODO: In block")
+        .unwrap();
+    assert_eq!(block_line.line_number, 4);
+    assert_eq!(block_line.cell, Some(1));
+
+    // A shorter or different fence does not close the block
+    let inner = lines.iter().find(|l| l.text == "still inside").unwrap();
+    assert_eq!(inner.cell, Some(2));
+
+    let prose = lines
+        .iter()
+        .find(|l| l.text == "<!-- HACK: Prose -->")
+        .unwrap();
+    assert_eq!(prose.line_number, 13);
+    assert_eq!(prose.cell, None);
+
+    // Fence lines themselves are not part of the extracted source
+    assert!(!lines.iter().any(|l| [3, 5, 8, 12].contains(&l.line_number)));
+}
+
+#[test]
+fn test_read_source_lines_skips_invalid_utf8_lines() {
+    let temp_dir = tempdir().unwrap();
+    let test_file = temp_dir.path().join("mixed.txt");
+    std::fs::write(&test_file, b"\xFF\xFE\r\n// TODO: Valid line\r\n").unwrap();
+
+    let lines = read_source_lines(&test_file).unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].line_number, 2);
+    assert_eq!(lines[0].text, "// TODO: Valid line");
+}
+
+#[test]
+fn test_format_location() {
+    assert_eq!(format_location("src/main.rs", 12, None), "src/main.rs:12");
+    assert_eq!(
+        format_location("analysis.ipynb", 2, Some(3)),
+        "analysis.ipynb[cell 3]:2"
+    );
+    assert_eq!(
+        format_location("README.md", 40, Some(2)),
+        "README.md:40 (code block 2)"
+    );
+}
+
+#[test]
+fn test_file_line() {
+    assert_eq!(file_line("src/main.rs", 12, None), Some(12));
+    assert_eq!(file_line("README.md", 40, Some(2)), Some(40));
+    assert_eq!(file_line("analysis.ipynb", 2, Some(3)), None);
+}
@@ -0,0 +1,380 @@
+// src/history.rs
+// Handles the history command for codemarks
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config_layers::ConfigOverrides;
+use crate::formats::source_lines;
+use crate::matcher::Matcher;
+use crate::{
+    Codemark, detect_project_name, load_global_config, load_global_projects, save_global_projects,
+};
+
+/// The lifetime of a single annotation line as recorded in the git history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryRecord {
+    pub file: String,
+    pub line_number: usize,
+    pub description: String,
+    pub introduced_at: i64,
+    pub removed_at: Option<i64>,
+}
+
+/// Walks the git history of a directory and backfills creation and resolution
+/// timestamps of the annotations stored for its project
+pub fn backfill_history(directory: &Path, ephemeral: bool) -> Result<()> {
+    let layered = load_global_config(directory, &ConfigOverrides::default(), ephemeral)?;
+    let matcher = Matcher::new(&layered.config)?;
+    let project_name = layered
+        .project_name_for(directory)
+        .unwrap_or_else(|| detect_project_name(directory));
+
+    let canonical_dir = directory.canonicalize()?;
+
+    let log = read_git_log(directory)?;
+    let records = parse_git_log(&log, &matcher, |revision, file| {
+        read_revision(directory, revision, file)
+    });
+
+    let mut projects_db = load_global_projects(ephemeral);
+    let by_project = assign_records(&projects_db.roots, &canonical_dir, &project_name, records);
+    let mut names: Vec<&String> = by_project.keys().collect();
+    names.sort();
+    for name in names {
+        let records = &by_project[name];
+        let codemarks = projects_db.projects.entry(name.clone()).or_default();
+        let (dated, added) = apply_history(codemarks, records);
+        println!(
+            "Backfilled history for project '{name}' from {} annotation records",
+            records.len()
+        );
+        println!("  - {dated} existing annotations dated");
+        println!("  - {added} removed annotations added as resolved");
+    }
+    save_global_projects(&projects_db, ephemeral)?;
+    Ok(())
+}
+
+/// Assigns history records, with paths relative to `directory`, to the project whose
+/// recorded root is nearest to their file, making the paths relative to that root the
+/// way scan stores them. Records outside every known root stay with `project_name`.
+fn assign_records(
+    roots: &HashMap<String, PathBuf>,
+    directory: &Path,
+    project_name: &str,
+    records: Vec<HistoryRecord>,
+) -> HashMap<String, Vec<HistoryRecord>> {
+    let mut by_project: HashMap<String, Vec<HistoryRecord>> = HashMap::new();
+    for record in records {
+        let path = directory.join(&record.file);
+        let (project, file) = roots
+            .iter()
+            .filter_map(|(name, root)| {
+                let relative = path.strip_prefix(root).ok()?;
+                Some((root.components().count(), name, relative))
+            })
+            .max_by_key(|(depth, _, _)| *depth)
+            .map_or_else(
+                || (project_name.to_string(), record.file.clone()),
+                |(_, name, relative)| (name.clone(), relative.to_string_lossy().to_string()),
+            );
+        by_project
+            .entry(project)
+            .or_default()
+            .push(HistoryRecord { file, ..record });
+    }
+    by_project
+}
+
+/// Compares stored and recorded paths, which older scans of `.` prefixed with ./
+fn same_file(stored: &str, recorded: &str) -> bool {
+    stored.trim_start_matches("./") == recorded.trim_start_matches("./")
+}
+
+/// Returns the full patch history of a directory, oldest commit first
+fn read_git_log(directory: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args([
+            "log",
+            "--reverse",
+            "--no-renames",
+            "--relative",
+            "-p",
+            "-U0",
+            "--no-color",
+            "--format=%x00%H %at",
+        ])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("git log failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Returns the content of a file, relative to `directory`, at a revision
+fn read_revision(directory: &Path, revision: &str, file: &str) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .arg("show")
+        .arg(format!("{revision}:./{file}"))
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// A line added or removed by a commit that looks like an annotation
+struct ChangedLine {
+    file: String,
+    /// Line number in the new version of the file for added lines, in the old one otherwise
+    line_number: usize,
+    text: String,
+}
+
+/// Parses the output of `git log --reverse -p -U0 --format=%x00%H %at` into the
+/// lifetimes of every annotation, identified by their description like in a scan.
+/// `read_file` returns the content of a file at a revision, so that annotations are
+/// matched in the context of their whole file the way a scan finds them.
+fn parse_git_log(
+    output: &str,
+    matcher: &Matcher,
+    read_file: impl Fn(&str, &str) -> Option<String>,
+) -> Vec<HistoryRecord> {
+    let mut records: Vec<HistoryRecord> = Vec::new();
+    // Indices into `records` of the annotations that are still present, per file and line
+    let mut open: HashMap<(String, String), Vec<usize>> = HashMap::new();
+
+    let mut hash = String::new();
+    let mut timestamp = 0;
+    let mut current_file = String::new();
+    let mut in_hunk = false;
+    let mut old_line = 0;
+    let mut new_line = 0;
+    let mut added: Vec<ChangedLine> = Vec::new();
+    let mut removed: Vec<ChangedLine> = Vec::new();
+
+    let mut finish_commit = |hash: &str,
+                             timestamp: i64,
+                             added: &mut Vec<ChangedLine>,
+                             removed: &mut Vec<ChangedLine>| {
+        let added_matches = match_changed_lines(matcher, added, |file| read_file(hash, file));
+        let removed_matches = match_changed_lines(matcher, removed, |file| {
+            read_file(&format!("{hash}^"), file)
+        });
+        apply_commit(
+            &mut records,
+            &mut open,
+            &added_matches,
+            &removed_matches,
+            timestamp,
+        );
+        added.clear();
+        removed.clear();
+    };
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix('\0') {
+            finish_commit(&hash, timestamp, &mut added, &mut removed);
+            let mut parts = header.split(' ');
+            hash = parts.next().unwrap_or_default().to_string();
+            timestamp = parts
+                .next()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0);
+            in_hunk = false;
+        } else if line.starts_with("diff --git ") {
+            in_hunk = false;
+        } else if !in_hunk {
+            if let Some(path) = line.strip_prefix("--- a/") {
+                current_file = path.to_string();
+            } else if let Some(path) = line.strip_prefix("+++ b/") {
+                current_file = path.to_string();
+            } else if line.starts_with("@@") {
+                in_hunk = true;
+                (old_line, new_line) = parse_hunk_starts(line);
+            }
+        } else if line.starts_with("@@") {
+            (old_line, new_line) = parse_hunk_starts(line);
+        } else if let Some(content) = line.strip_prefix('+') {
+            added.push(ChangedLine {
+                file: current_file.clone(),
+                line_number: new_line,
+                text: content.to_string(),
+            });
+            new_line += 1;
+        } else if let Some(content) = line.strip_prefix('-') {
+            removed.push(ChangedLine {
+                file: current_file.clone(),
+                line_number: old_line,
+                text: content.to_string(),
+            });
+            old_line += 1;
+        }
+    }
+    finish_commit(&hash, timestamp, &mut added, &mut removed);
+
+    records
+}
+
+/// Returns the annotations among the changed lines of a commit as (file, description,
+/// line) tuples. Files with an annotation line are matched as a whole, so that
+/// `codemarks-ignore-*` directives and Markdown code blocks are honored like in a scan.
+/// Notebooks are left out, as their diffs show the JSON rather than the cells.
+fn match_changed_lines(
+    matcher: &Matcher,
+    lines: &[ChangedLine],
+    read_file: impl Fn(&str) -> Option<String>,
+) -> Vec<(String, String, usize)> {
+    let mut file_matches: HashMap<&str, Option<HashMap<usize, String>>> = HashMap::new();
+    let mut result = Vec::new();
+    for line in lines {
+        let path = Path::new(&line.file);
+        if path.extension().is_some_and(|ext| ext == "ipynb") {
+            continue;
+        }
+        let Some(line_match) = matcher.match_file_line(path, &line.text) else {
+            continue;
+        };
+        let matches = file_matches.entry(&line.file).or_insert_with(|| {
+            let content = read_file(&line.file)?;
+            let lines = source_lines(path, &content);
+            Some(
+                matcher
+                    .match_file_lines(path, lines)
+                    .matches
+                    .into_iter()
+                    .map(|(line, line_match)| (line.line_number, line_match.description))
+                    .collect(),
+            )
+        });
+        // Without the file content the line is taken as it is
+        let description = match matches {
+            Some(matches) => match matches.get(&line.line_number) {
+                Some(description) => description.clone(),
+                None => continue,
+            },
+            None => line_match.description,
+        };
+        result.push((line.file.clone(), description, line.line_number));
+    }
+    result
+}
+
+/// Returns the first line numbers of the old and new sides of a `@@ -a,b +c,d @@` hunk header
+fn parse_hunk_starts(header: &str) -> (usize, usize) {
+    let start = |prefix: char| {
+        header
+            .split(' ')
+            .find_map(|part| part.strip_prefix(prefix))
+            .and_then(|range| range.split(',').next())
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(0)
+    };
+    (start('-'), start('+'))
+}
+
+/// Applies the annotation lines added and removed by a single commit. A line that is
+/// both removed and added within the same file in one commit has only moved.
+fn apply_commit(
+    records: &mut Vec<HistoryRecord>,
+    open: &mut HashMap<(String, String), Vec<usize>>,
+    added: &[(String, String, usize)],
+    removed: &[(String, String, usize)],
+    timestamp: i64,
+) {
+    let mut moved: HashMap<(String, String), usize> = HashMap::new();
+
+    for (file, description, line_number) in added {
+        let key = (file.clone(), description.clone());
+        let removed_count = removed
+            .iter()
+            .filter(|(f, d, _)| f == file && d == description)
+            .count();
+        let moved_count = moved.entry(key.clone()).or_insert(0);
+        if *moved_count < removed_count
+            && let Some(&index) = open.get(&key).and_then(|indices| indices.first())
+        {
+            *moved_count += 1;
+            records[index].line_number = *line_number;
+            continue;
+        }
+        open.entry(key).or_default().push(records.len());
+        records.push(HistoryRecord {
+            file: file.clone(),
+            line_number: *line_number,
+            description: description.clone(),
+            introduced_at: timestamp,
+            removed_at: None,
+        });
+    }
+
+    for (file, description, _) in removed {
+        let key = (file.clone(), description.clone());
+        if let Some(count) = moved.get_mut(&key)
+            && *count > 0
+        {
+            *count -= 1;
+            continue;
+        }
+        if let Some(indices) = open.get_mut(&key)
+            && !indices.is_empty()
+        {
+            let index = indices.remove(0);
+            records[index].removed_at = Some(timestamp);
+        }
+    }
+}
+
+/// Merges history records into the stored codemarks of a project. Returns the number of
+/// existing codemarks that were dated and the number of resolved codemarks that were added.
+fn apply_history(codemarks: &mut Vec<Codemark>, records: &[HistoryRecord]) -> (usize, usize) {
+    let mut dated = 0;
+    let mut added = 0;
+
+    for record in records {
+        let existing = codemarks.iter().position(|codemark| {
+            same_file(&codemark.file, &record.file)
+                && codemark.description == record.description
+                && codemark.resolved == record.removed_at.is_some()
+        });
+        if let Some(index) = existing {
+            let codemark = &mut codemarks[index];
+            codemark.created_at = Some(record.introduced_at);
+            if record.removed_at.is_some() {
+                codemark.resolved_at = record.removed_at;
+            }
+            dated += 1;
+        } else if record.removed_at.is_some()
+            && !codemarks.iter().any(|codemark| {
+                same_file(&codemark.file, &record.file)
+                    && codemark.description == record.description
+            })
+        {
+            codemarks.push(Codemark {
+                file: record.file.clone(),
+                line_number: record.line_number,
+                description: record.description.clone(),
+                resolved: true,
+                created_at: Some(record.introduced_at),
+                resolved_at: record.removed_at,
+                ..Default::default()
+            });
+            added += 1;
+        }
+    }
+
+    (dated, added)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,270 @@
+use super::*;
+use crate::{default_annotation_pattern, default_kinds};
+use std::fs;
+use tempfile::tempdir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Alice",
+            "-c",
+            "user.email=alice@example.com",
+        ])
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(status.status.success(), "git {args:?} failed");
+}
+
+fn matcher() -> Matcher {
+    Matcher::with_pattern(&default_annotation_pattern(), &default_kinds()).unwrap()
+}
+
+fn repository_records(dir: &Path) -> Vec<HistoryRecord> {
+    let log = read_git_log(dir).unwrap();
+    parse_git_log(&log, &matcher(), |revision, file| {
+        read_revision(dir, revision, file)
+    })
+}
+
+#[test]
+fn test_parse_git_log_tracks_lifetimes() {
+    let output = "\0aaaa 100
+
+diff --git a/x.rs b/x.rs
+new file mode 100644
+--- /dev/null
++++ b/x.rs
+@@ -0,0 +1,3 @@
++a
++// TODO: one
++// FIXME: two
+\0bbbb 200
+
+diff --git a/x.rs b/x.rs
+--- a/x.rs
++++ b/x.rs
+@@ -2 +1,0 @@ a
+-// TODO: one
+@@ -3,0 +3 @@ b
++// TODO: one
+\0cccc 300
+
+diff --git a/x.rs b/x.rs
+--- a/x.rs
++++ b/x.rs
+@@ -2 +1,0 @@ a
+-// FIXME: two
+";
+
+    let records = parse_git_log(output, &matcher(), |_, _| None);
+    assert_eq!(records.len(), 2);
+
+    // Moving a line within a file keeps its original introduction date
+    assert_eq!(records[0].description, "one");
+    assert_eq!(records[0].introduced_at, 100);
+    assert_eq!(records[0].line_number, 3);
+    assert_eq!(records[0].removed_at, None);
+
+    assert_eq!(records[1].description, "two");
+    assert_eq!(records[1].introduced_at, 100);
+    assert_eq!(records[1].removed_at, Some(300));
+}
+
+#[test]
+fn test_parse_git_log_ignores_header_like_content() {
+    let output = "\0aaaa 100
+
+diff --git a/x.sql b/x.sql
+--- /dev/null
++++ b/x.sql
+@@ -0,0 +1,2 @@
++-- a/not-a-header
+++++ b/// TODO: still content
+";
+
+    let records = parse_git_log(output, &matcher(), |_, _| None);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].file, "x.sql");
+    assert_eq!(records[0].line_number, 2);
+}
+
+#[test]
+fn test_apply_history() {
+    let mut codemarks = vec![Codemark {
+        file: "x.rs".to_string(),
+        line_number: 3,
+        description: "// TODO: one".to_string(),
+        ..Default::default()
+    }];
+    let records = vec![
+        HistoryRecord {
+            file: "x.rs".to_string(),
+            line_number: 3,
+            description: "// TODO: one".to_string(),
+            introduced_at: 100,
+            removed_at: None,
+        },
+        HistoryRecord {
+            file: "x.rs".to_string(),
+            line_number: 2,
+            description: "// FIXME: two".to_string(),
+            introduced_at: 100,
+            removed_at: Some(300),
+        },
+    ];
+
+    let (dated, added) = apply_history(&mut codemarks, &records);
+    assert_eq!((dated, added), (1, 1));
+    assert_eq!(codemarks[0].created_at, Some(100));
+    assert!(!codemarks[0].resolved);
+    assert!(codemarks[1].resolved);
+    assert_eq!(codemarks[1].created_at, Some(100));
+    assert_eq!(codemarks[1].resolved_at, Some(300));
+
+    // Applying the same history twice does not duplicate resolved records
+    let (_, added) = apply_history(&mut codemarks, &records);
+    assert_eq!(added, 0);
+    assert_eq!(codemarks.len(), 2);
+}
+
+#[test]
+fn test_assign_records_to_project_roots() {
+    let record = |file: &str| HistoryRecord {
+        file: file.to_string(),
+        line_number: 1,
+        description: "one".to_string(),
+        introduced_at: 100,
+        removed_at: None,
+    };
+    let roots = HashMap::from([
+        ("repo".to_string(), PathBuf::from("/work/repo")),
+        ("web".to_string(), PathBuf::from("/work/repo/web")),
+    ]);
+
+    let by_project = assign_records(
+        &roots,
+        Path::new("/work/repo"),
+        "repo",
+        vec![record("src/main.rs"), record("web/app.js")],
+    );
+    assert_eq!(by_project["repo"][0].file, "src/main.rs");
+    assert_eq!(by_project["web"][0].file, "app.js");
+
+    // Records outside every known root stay with the project of the directory
+    let by_project = assign_records(
+        &HashMap::new(),
+        Path::new("/tmp/x"),
+        "x",
+        vec![record("a.rs")],
+    );
+    assert_eq!(by_project["x"][0].file, "a.rs");
+}
+
+#[test]
+fn test_apply_history_to_prefixed_paths() {
+    let mut codemarks = vec![Codemark {
+        file: "./x.rs".to_string(),
+        line_number: 3,
+        description: "one".to_string(),
+        ..Default::default()
+    }];
+    let records = vec![HistoryRecord {
+        file: "x.rs".to_string(),
+        line_number: 3,
+        description: "one".to_string(),
+        introduced_at: 100,
+        removed_at: None,
+    }];
+
+    assert_eq!(apply_history(&mut codemarks, &records), (1, 0));
+    assert_eq!(codemarks[0].created_at, Some(100));
+}
+
+#[test]
+fn test_parse_git_log_from_repository() {
+    let temp_dir = tempdir().unwrap();
+    git(temp_dir.path(), &["init", "-q"]);
+    fs::write(
+        temp_dir.path().join("x.rs"),
+        "// TODO: kept\n// FIXME: gone\n",
+    )
+    .unwrap();
+    git(temp_dir.path(), &["add", "x.rs"]);
+    git(temp_dir.path(), &["commit", "-q", "-m", "Add annotations"]);
+    fs::write(temp_dir.path().join("x.rs"), "// TODO: kept\n").unwrap();
+    git(temp_dir.path(), &["commit", "-q", "-a", "-m", "Fix it"]);
+
+    let records = repository_records(temp_dir.path());
+
+    assert_eq!(records.len(), 2);
+    assert!(records[0].removed_at.is_none());
+    assert!(records[1].removed_at.is_some());
+}
+
+#[test]
+fn test_parse_git_log_honors_suppression_directives() {
+    let temp_dir = tempdir().unwrap();
+    git(temp_dir.path(), &["init", "-q"]);
+    fs::write(
+        temp_dir.path().join("x.rs"),
+        "// TODO: kept\n// codemarks-ignore-next-line\n// TODO: silenced\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("y.rs"), "// FIXME: silenced too\n").unwrap();
+    git(temp_dir.path(), &["add", "."]);
+    git(temp_dir.path(), &["commit", "-q", "-m", "Add annotations"]);
+    // A directive added later silences the whole file, even outside the diff
+    fs::write(
+        temp_dir.path().join("y.rs"),
+        "// FIXME: silenced too\n\n// codemarks-ignore-file\n// HACK: new\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("x.rs"), "// TODO: kept\n").unwrap();
+    git(
+        temp_dir.path(),
+        &["commit", "-q", "-a", "-m", "Change annotations"],
+    );
+
+    let records = repository_records(temp_dir.path());
+    let descriptions: Vec<_> = records
+        .iter()
+        .map(|record| (record.file.as_str(), record.description.as_str()))
+        .collect();
+    assert_eq!(descriptions, [("x.rs", "kept"), ("y.rs", "silenced too")]);
+    // Removing a suppressed annotation does not resolve anything
+    assert!(records[0].removed_at.is_none());
+}
+
+#[test]
+fn test_parse_git_log_skips_notebooks() {
+    let temp_dir = tempdir().unwrap();
+    git(temp_dir.path(), &["init", "-q"]);
+    fs::write(
+        temp_dir.path().join("analysis.ipynb"),
+        r##"{"cells": [{"cell_type": "code", "source": ["# TODO: In a cell\n"]}]}"##,
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("notes.md"),
+        "TODO: prose\n\n```rust\n// TODO: in code\n```\n",
+    )
+    .unwrap();
+    git(temp_dir.path(), &["add", "."]);
+    git(temp_dir.path(), &["commit", "-q", "-m", "Add notebook"]);
+
+    let records = repository_records(temp_dir.path());
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].file, "notes.md");
+    assert_eq!(records[0].description, "in code");
+    assert_eq!(records[0].line_number, 4);
+}
+
+#[test]
+fn test_read_git_log_outside_repository() {
+    let temp_dir = tempdir().unwrap();
+    assert!(read_git_log(temp_dir.path()).is_err());
+}
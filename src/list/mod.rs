@@ -1,34 +1,408 @@
 // src/list.rs
 // Handles the list command for codemarks
 
-use crate::load_global_projects;
+use crate::config_layers::ConfigOverrides;
+use crate::output::{self, Record};
+use crate::query::Query;
+use crate::terminal::{ColorChoice, Styler};
+use crate::{
+    Codemark, OutputFormat, ProjectsDatabase, default_kinds, detect_project_name,
+    load_global_config, load_global_projects,
+};
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use globset::{Glob, GlobMatcher};
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn list_codemarks(ephemeral: bool) {
+/// Resolution status of the annotations to list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Status {
+    Open,
+    Resolved,
+    All,
+}
+
+/// Key to sort the listed annotations by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortKey {
+    /// By project, then by file and line
+    #[default]
+    Project,
+    /// By file and line, across projects
+    File,
+    /// By line number
+    Line,
+    /// By kind name, annotations without a kind last
+    Kind,
+    /// Oldest first, annotations of unknown age last
+    Age,
+}
+
+/// Headings to group the listed annotations under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum GroupBy {
+    #[default]
+    Project,
+    File,
+    Kind,
+    Owner,
+    /// A single list without headings
+    None,
+}
+
+/// Command line options that order and decorate the listed annotations
+#[derive(Args, Debug, Default, Clone, Copy)]
+pub struct ListLayout {
+    /// Sort annotations by this key, ties are broken by project, file and line
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: SortKey,
+    /// Group annotations under headings (ignored by machine readable formats)
+    #[arg(long, value_enum, default_value_t)]
+    pub group_by: GroupBy,
+    /// Show the id of each annotation, to open it with 'codemarks open'
+    #[arg(long)]
+    pub ids: bool,
+}
+
+/// Command line options that narrow down the listed annotations
+#[derive(Args, Debug, Default, Clone)]
+pub struct ListFilter {
+    /// Only show annotations of this project, instead of the one of the current repository
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Show the annotations of all projects, also inside a repository with a project name
+    #[arg(long, conflicts_with = "project")]
+    pub all_projects: bool,
+    /// Only show annotations in files matching this glob pattern
+    #[arg(short, long, value_parser = parse_glob)]
+    pub file: Option<GlobMatcher>,
+    /// Only show annotations of these kinds
+    #[arg(short, long)]
+    pub kind: Vec<String>,
+    /// Only show open or resolved annotations [default: all, or open for reports]
+    #[arg(short, long, value_enum)]
+    pub status: Option<Status>,
+    /// Only show annotations whose description contains this text
+    #[arg(short, long)]
+    pub grep: Option<String>,
+    /// Only show annotations whose author name or email contains this text
+    #[arg(short, long)]
+    pub author: Option<String>,
+    /// Only show annotations matching this query, e.g. 'kind:FIXME age>30d -resolved'
+    #[arg(short, long)]
+    pub query: Option<Query>,
+    /// Show at most this many annotations
+    #[arg(short = 'n', long)]
+    pub limit: Option<usize>,
+}
+
+fn parse_glob(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    Glob::new(pattern).map(|glob| glob.compile_matcher())
+}
+
+impl ListFilter {
+    /// Checks whether a codemark of a project passes every filter except the project
+    /// and the limit
+    fn matches(&self, project: &str, codemark: &Codemark, now: i64) -> bool {
+        let status = match self.status.unwrap_or(Status::All) {
+            Status::Open => !codemark.resolved,
+            Status::Resolved => codemark.resolved,
+            Status::All => true,
+        };
+        status
+            && self
+                .file
+                .as_ref()
+                .is_none_or(|glob| glob.is_match(&codemark.file))
+            && (self.kind.is_empty()
+                || codemark.kind.as_ref().is_some_and(|kind| {
+                    self.kind
+                        .iter()
+                        .any(|filter| filter.eq_ignore_ascii_case(kind))
+                }))
+            && self.grep.as_ref().is_none_or(|text| {
+                codemark
+                    .description
+                    .to_lowercase()
+                    .contains(&text.to_lowercase())
+            })
+            && self
+                .author
+                .as_deref()
+                .is_none_or(|author| matches_author(codemark, author))
+            && self
+                .query
+                .as_ref()
+                .is_none_or(|query| query.matches(project, codemark, now))
+    }
+}
+
+pub fn list_codemarks(
+    filter: &ListFilter,
+    layout: ListLayout,
+    format: OutputFormat,
+    color: ColorChoice,
+    ephemeral: bool,
+) -> Result<()> {
+    let machine_readable = format.is_machine_readable();
     if ephemeral {
-        println!("No code annotations available (ephemeral mode).");
-        return;
+        if machine_readable {
+            print_records(format, &[]);
+        } else {
+            println!("No code annotations available (ephemeral mode).");
+        }
+        return Ok(());
     }
+    let project_filter = project_filter(filter)?;
     let projects_db = load_global_projects(false);
-    if projects_db.projects.is_empty() {
+    if projects_db.projects.is_empty() && !machine_readable {
         println!("No code annotations found. Run 'codemarks scan' first to scan for annotations.");
-        return;
+        return Ok(());
     }
-    for (project_name, codemarks) in &projects_db.projects {
-        if codemarks.is_empty() {
-            continue;
+    let now = current_timestamp();
+    let (selected, total) = select_codemarks(
+        &projects_db,
+        filter,
+        project_filter.as_ref().map(|(project, _)| project.as_str()),
+        layout.sort,
+        now,
+    );
+
+    let (kinds, current_project) =
+        match load_global_config(Path::new("."), &ConfigOverrides::default(), false) {
+            Ok(layered) => (
+                layered.config.kinds.clone(),
+                layered.project_name_for(Path::new(".")),
+            ),
+            Err(_) => (default_kinds(), None),
+        };
+
+    if machine_readable {
+        let records: Vec<Record> = selected
+            .iter()
+            .map(|(project, codemark)| Record::new(project, codemark, &kinds))
+            .collect();
+        print_records(format, &records);
+        return Ok(());
+    }
+    if let Some((project, origin)) = &project_filter {
+        println!("Showing project '{project}' from {origin}\n");
+    }
+    let styler = Styler::new(color, &kinds);
+    // File paths are relative to the project root, so only the annotations of the project
+    // in the current directory can be linked
+    let current_project = current_project.unwrap_or_else(|| detect_project_name(Path::new(".")));
+    // Outside project groups, name the project of each annotation when there are several
+    let several_projects = selected
+        .iter()
+        .any(|(project, _)| *project != selected[0].0);
+    let qualify = several_projects && layout.group_by != GroupBy::Project;
+    let groups = group_codemarks(&selected, layout.group_by, qualify);
+    for (heading, codemarks) in &groups {
+        if let Some(heading) = heading {
+            println!("{}", styler.bold(heading));
         }
-        println!("{project_name}");
-        for codemark in codemarks {
+        for (project, codemark) in codemarks {
             let resolved_prefix = if codemark.resolved { "✅ " } else { "   " };
+            let location = if *project == current_project {
+                // Lines inside notebook cells are not lines of the file
+                let line = codemark.file_line();
+                styler.link(&codemark.location(), Path::new(&codemark.file), line)
+            } else {
+                codemark.location()
+            };
+            let description = if codemark.resolved {
+                styler.dim(&codemark.description)
+            } else {
+                styler.kind(codemark.kind.as_deref(), &codemark.description)
+            };
+            let mut prefix = if qualify {
+                format!("{project}: ")
+            } else {
+                String::new()
+            };
+            if layout.ids {
+                prefix.insert_str(0, &styler.dim(&format!("{} ", codemark.id(project))));
+            }
+            let attribution = match (&codemark.author, codemark.commit_date) {
+                (Some(author), Some(date)) => format!(" ({author}, {})", format_age(now - date)),
+                (Some(author), None) => format!(" ({author})"),
+                _ => String::new(),
+            };
             println!(
-                "{}{}:{} {}",
-                resolved_prefix, codemark.file, codemark.line_number, codemark.description
+                "{}{}{} {}{}",
+                resolved_prefix,
+                prefix,
+                location,
+                description,
+                styler.dim(&attribution)
             );
         }
-        if projects_db.projects.len() > 1 {
+        if groups.len() > 1 {
             println!();
         }
     }
+    if total == 0 {
+        println!("No code annotations match the given filters.");
+    } else if let Some(limit) = filter.limit
+        && limit < total
+    {
+        println!("Showing {limit} of {total} matching annotations.");
+    }
+    Ok(())
+}
+
+/// Heading of a group of codemarks, with the codemarks and their projects
+type Group<'a> = (Option<String>, Vec<(&'a str, &'a Codemark)>);
+
+/// Groups sorted codemarks under their headings, keeping the order within each group.
+/// Groups are ordered by heading, with the group of a missing kind or owner last.
+fn group_codemarks<'a>(
+    selected: &[(&'a str, &'a Codemark)],
+    group_by: GroupBy,
+    qualify: bool,
+) -> Vec<Group<'a>> {
+    if group_by == GroupBy::None {
+        return vec![(None, selected.to_vec())];
+    }
+    let mut groups: Vec<Group> = Vec::new();
+    for &(project, codemark) in selected {
+        let key = match group_by {
+            GroupBy::Project => Some(project.to_string()),
+            GroupBy::File if qualify => Some(format!("{project}: {}", codemark.file)),
+            GroupBy::File => Some(codemark.file.clone()),
+            GroupBy::Kind => codemark.kind.clone(),
+            GroupBy::Owner => codemark.owner.clone(),
+            GroupBy::None => None,
+        };
+        match groups.iter_mut().find(|(heading, _)| *heading == key) {
+            Some((_, codemarks)) => codemarks.push((project, codemark)),
+            None => groups.push((key, vec![(project, codemark)])),
+        }
+    }
+    groups.sort_by(|(a, _), (b, _)| (a.is_none(), a).cmp(&(b.is_none(), b)));
+    let missing = match group_by {
+        GroupBy::Kind => "(no kind)",
+        _ => "(no owner)",
+    };
+    groups
+        .into_iter()
+        .map(|(heading, codemarks)| {
+            (
+                Some(heading.unwrap_or_else(|| missing.to_string())),
+                codemarks,
+            )
+        })
+        .collect()
+}
+
+/// Returns the project to show with the layer it came from: the `--project` option, or
+/// inside a repository with a configured project name, that project
+pub fn project_filter(filter: &ListFilter) -> Result<Option<(String, String)>> {
+    if let Some(project) = &filter.project {
+        return Ok(Some((project.clone(), "--project".to_string())));
+    }
+    if filter.all_projects {
+        return Ok(None);
+    }
+    let layered = load_global_config(Path::new("."), &ConfigOverrides::default(), false)?;
+    Ok(layered.project_name_for(Path::new(".")).map(|project| {
+        let origin = layered
+            .origin("project")
+            .first()
+            .map_or_else(String::new, ToString::to_string);
+        (project, origin)
+    }))
+}
+
+/// Selects the codemarks that pass a filter, in the given order. Also returns the number
+/// of matching codemarks before the limit was applied.
+pub fn select_codemarks<'a>(
+    projects_db: &'a ProjectsDatabase,
+    filter: &ListFilter,
+    project: Option<&str>,
+    sort: SortKey,
+    now: i64,
+) -> (Vec<(&'a str, &'a Codemark)>, usize) {
+    let mut selected: Vec<(&str, &Codemark)> = projects_db
+        .projects
+        .iter()
+        .filter(|(project_name, _)| project.is_none_or(|project| project == *project_name))
+        .flat_map(|(project_name, codemarks)| {
+            codemarks
+                .iter()
+                .filter(|codemark| filter.matches(project_name, codemark, now))
+                .map(move |codemark| (project_name.as_str(), codemark))
+        })
+        .collect();
+    sort_codemarks(&mut selected, sort);
+    let total = selected.len();
+    selected.truncate(filter.limit.unwrap_or(usize::MAX));
+    (selected, total)
+}
+
+/// Sorts codemarks by a key, then by project, file and line, so the order never depends
+/// on how the database happens to be stored
+fn sort_codemarks(selected: &mut [(&str, &Codemark)], sort: SortKey) {
+    selected.sort_by(|&(project_a, a), &(project_b, b)| {
+        let primary = match sort {
+            SortKey::Project => Ordering::Equal,
+            SortKey::File => a.file.cmp(&b.file),
+            SortKey::Line => a.line_number.cmp(&b.line_number),
+            SortKey::Kind => (a.kind.is_none(), &a.kind).cmp(&(b.kind.is_none(), &b.kind)),
+            SortKey::Age => {
+                let (a, b) = (a.introduced_at(), b.introduced_at());
+                (a.is_none(), a).cmp(&(b.is_none(), b))
+            }
+        };
+        primary.then_with(|| {
+            (project_a, &a.file, a.cell, a.line_number).cmp(&(
+                project_b,
+                &b.file,
+                b.cell,
+                b.line_number,
+            ))
+        })
+    });
+}
+
+/// Prints records in a machine readable format, reporting failures on stderr
+fn print_records(format: OutputFormat, records: &[Record]) {
+    if let Err(e) = output::print_records(format, records) {
+        eprintln!("Error writing output: {e}");
+    }
+}
+
+/// Checks whether the author name or email of a codemark contains the given text
+fn matches_author(codemark: &Codemark, author: &str) -> bool {
+    let author = author.to_lowercase();
+    codemark
+        .author
+        .iter()
+        .chain(codemark.author_email.iter())
+        .any(|value| value.to_lowercase().contains(&author))
+}
+
+pub fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs().cast_signed())
+}
+
+/// Formats an age in seconds as a short human readable string (e.g. `3d`, `5mo`)
+pub fn format_age(seconds: i64) -> String {
+    let days = seconds.max(0) / 86_400;
+    if days >= 365 {
+        format!("{}y", days / 365)
+    } else if days >= 30 {
+        format!("{}mo", days / 30)
+    } else if days >= 1 {
+        format!("{days}d")
+    } else {
+        "today".to_string()
+    }
 }
 
 #[cfg(test)]
@@ -16,7 +16,14 @@ fn test_list_codemarks_empty() {
     let _temp_home = setup_temp_home();
 
     // Test listing when database is empty - should not crash
-    list_codemarks(false);
+    list_codemarks(
+        &ListFilter::default(),
+        ListLayout::default(),
+        OutputFormat::Text,
+        ColorChoice::Never,
+        false,
+    )
+    .unwrap();
 }
 
 #[test]
@@ -24,7 +31,14 @@ fn test_list_codemarks_with_data() {
     let _temp_home = setup_temp_home();
 
     // Test that the list function doesn't crash even if we can't save data
-    list_codemarks(false);
+    list_codemarks(
+        &ListFilter::default(),
+        ListLayout::default(),
+        OutputFormat::Text,
+        ColorChoice::Never,
+        false,
+    )
+    .unwrap();
 }
 
 #[test]
@@ -39,6 +53,7 @@ fn test_list_codemarks_functionality() {
         line_number: 1,
         description: "Resolved task".to_string(),
         resolved: true,
+        ..Default::default()
     };
 
     let unresolved_codemark = Codemark {
@@ -46,6 +61,7 @@ fn test_list_codemarks_functionality() {
         line_number: 2,
         description: "Unresolved task".to_string(),
         resolved: false,
+        ..Default::default()
     };
 
     projects_db.projects.insert(
@@ -75,3 +91,209 @@ fn test_list_codemarks_functionality() {
         }
     }
 }
+
+#[test]
+fn test_matches_author() {
+    let codemark = Codemark {
+        file: "test.rs".to_string(),
+        line_number: 1,
+        description: "Attributed task".to_string(),
+        author: Some("Alice Smith".to_string()),
+        author_email: Some("alice@example.com".to_string()),
+        ..Default::default()
+    };
+
+    assert!(matches_author(&codemark, "alice"));
+    assert!(matches_author(&codemark, "EXAMPLE.COM"));
+    assert!(!matches_author(&codemark, "bob"));
+    assert!(!matches_author(&Codemark::default(), "alice"));
+}
+
+#[test]
+fn test_format_age() {
+    assert_eq!(format_age(-5), "today");
+    assert_eq!(format_age(3_600), "today");
+    assert_eq!(format_age(3 * 86_400), "3d");
+    assert_eq!(format_age(65 * 86_400), "2mo");
+    assert_eq!(format_age(800 * 86_400), "2y");
+}
+
+#[test]
+fn test_list_filter_matches() {
+    let codemark = Codemark {
+        file: "src/db/query.rs".to_string(),
+        line_number: 4,
+        description: "Add an index".to_string(),
+        kind: Some("TODO".to_string()),
+        ..Default::default()
+    };
+    let filter = |update: fn(&mut ListFilter)| {
+        let mut filter = ListFilter::default();
+        update(&mut filter);
+        filter.matches("api", &codemark, 0)
+    };
+
+    assert!(filter(|_| {}));
+    assert!(filter(|f| f.file = Some(parse_glob("src/**/*.rs").unwrap())));
+    assert!(filter(|f| f.file = Some(parse_glob("*.rs").unwrap())));
+    assert!(!filter(|f| f.file = Some(parse_glob("*.js").unwrap())));
+    assert!(filter(
+        |f| f.kind = vec!["fixme".to_string(), "todo".to_string()]
+    ));
+    assert!(!filter(|f| f.kind = vec!["FIXME".to_string()]));
+    assert!(filter(|f| f.status = Some(Status::Open)));
+    assert!(!filter(|f| f.status = Some(Status::Resolved)));
+    assert!(filter(|f| f.grep = Some("INDEX".to_string())));
+    assert!(!filter(|f| f.grep = Some("cache".to_string())));
+    assert!(!filter(|f| f.author = Some("alice".to_string())));
+    assert!(filter(
+        |f| f.query = Some("project:api kind:todo".parse().unwrap())
+    ));
+    assert!(!filter(|f| f.query = Some("-path:src/**".parse().unwrap())));
+}
+
+fn ordering_database() -> ProjectsDatabase {
+    let codemark = |file: &str, line_number, kind: Option<&str>, created_at| Codemark {
+        file: file.to_string(),
+        line_number,
+        description: format!("{file}:{line_number}"),
+        kind: kind.map(str::to_string),
+        owner: (line_number == 7).then(|| "alice".to_string()),
+        created_at,
+        ..Default::default()
+    };
+    let mut projects_db = ProjectsDatabase::default();
+    projects_db.projects.insert(
+        "web".to_string(),
+        vec![
+            codemark("src/b.js", 7, Some("TODO"), Some(300)),
+            codemark("src/a.js", 9, None, None),
+        ],
+    );
+    projects_db.projects.insert(
+        "api".to_string(),
+        vec![
+            codemark("src/b.rs", 2, Some("FIXME"), Some(200)),
+            codemark("src/a.rs", 5, Some("TODO"), Some(100)),
+            codemark("src/a.rs", 1, Some("HACK"), None),
+        ],
+    );
+    projects_db
+}
+
+fn selected_order(projects_db: &ProjectsDatabase, sort: SortKey) -> Vec<String> {
+    select_codemarks(projects_db, &ListFilter::default(), None, sort, 0)
+        .0
+        .iter()
+        .map(|(project, codemark)| format!("{project} {}", codemark.description))
+        .collect()
+}
+
+#[test]
+fn test_select_codemarks_sorted() {
+    let projects_db = ordering_database();
+
+    assert_eq!(
+        selected_order(&projects_db, SortKey::Project),
+        [
+            "api src/a.rs:1",
+            "api src/a.rs:5",
+            "api src/b.rs:2",
+            "web src/a.js:9",
+            "web src/b.js:7"
+        ]
+    );
+    assert_eq!(
+        selected_order(&projects_db, SortKey::File),
+        [
+            "web src/a.js:9",
+            "api src/a.rs:1",
+            "api src/a.rs:5",
+            "web src/b.js:7",
+            "api src/b.rs:2"
+        ]
+    );
+    assert_eq!(
+        selected_order(&projects_db, SortKey::Line)[..2],
+        ["api src/a.rs:1", "api src/b.rs:2"]
+    );
+    assert_eq!(
+        selected_order(&projects_db, SortKey::Kind),
+        [
+            "api src/b.rs:2",
+            "api src/a.rs:1",
+            "api src/a.rs:5",
+            "web src/b.js:7",
+            "web src/a.js:9"
+        ]
+    );
+    assert_eq!(
+        selected_order(&projects_db, SortKey::Age),
+        [
+            "api src/a.rs:5",
+            "api src/b.rs:2",
+            "web src/b.js:7",
+            "api src/a.rs:1",
+            "web src/a.js:9"
+        ]
+    );
+
+    // The limit applies after sorting
+    let filter = ListFilter {
+        limit: Some(1),
+        ..Default::default()
+    };
+    let (selected, total) = select_codemarks(&projects_db, &filter, None, SortKey::Age, 0);
+    assert_eq!(total, 5);
+    assert_eq!(selected[0].1.description, "src/a.rs:5");
+}
+
+#[test]
+fn test_group_codemarks() {
+    let projects_db = ordering_database();
+    let (selected, _) = select_codemarks(
+        &projects_db,
+        &ListFilter::default(),
+        None,
+        SortKey::Project,
+        0,
+    );
+    let headings = |group_by, qualify| -> Vec<Option<String>> {
+        group_codemarks(&selected, group_by, qualify)
+            .into_iter()
+            .map(|(heading, _)| heading)
+            .collect()
+    };
+    let some = |headings: &[&str]| -> Vec<Option<String>> {
+        headings.iter().map(|h| Some((*h).to_string())).collect()
+    };
+
+    assert_eq!(headings(GroupBy::Project, false), some(&["api", "web"]));
+    assert_eq!(
+        headings(GroupBy::File, true),
+        some(&[
+            "api: src/a.rs",
+            "api: src/b.rs",
+            "web: src/a.js",
+            "web: src/b.js"
+        ])
+    );
+    assert_eq!(
+        headings(GroupBy::Kind, false),
+        some(&["FIXME", "HACK", "TODO", "(no kind)"])
+    );
+    assert_eq!(
+        headings(GroupBy::Owner, false),
+        some(&["alice", "(no owner)"])
+    );
+    assert_eq!(headings(GroupBy::None, false), vec![None]);
+
+    // Groups keep the sort order of their annotations
+    let groups = group_codemarks(&selected, GroupBy::Kind, false);
+    let todos: Vec<&str> = groups[2]
+        .1
+        .iter()
+        .map(|(_, codemark)| codemark.description.as_str())
+        .collect();
+    assert_eq!(todos, ["src/a.rs:5", "src/b.js:7"]);
+}
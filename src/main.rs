@@ -1,44 +1,295 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use config_layers::{ConfigOverrides, LayeredConfig};
+use filters::FileSelection;
+use list::{ListFilter, ListLayout};
+use query::Query;
+use repo_config::Profile;
+use report::ReportFormat;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use stats::StatsFormat;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use terminal::ColorChoice;
 
+mod blame;
 mod ci;
 mod clean;
+mod comment_syntax;
 mod config;
+mod config_layers;
+mod filters;
+mod formats;
+mod history;
 mod list;
+mod matcher;
+mod open;
+mod output;
 mod project_detection;
+mod query;
+mod repo_config;
+mod report;
 mod scan;
+mod stats;
+mod terminal;
+mod tui;
 mod watch;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Codemark {
     pub file: String,
     pub line_number: usize,
+    /// Notebook cell or Markdown code block containing the annotation (1-based)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
+    /// 1-based column where the annotation comment starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
     pub description: String,
     #[serde(default)]
     pub resolved: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Unix timestamp of the commit that last touched the annotation line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_date: Option<i64>,
+    /// Name of the annotation kind that matched, e.g. `TODO` or `FIXME`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Owner of the annotation, from the `owner` group of the annotation pattern
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Issue reference of the annotation, from the `issue` group of the annotation pattern
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue: Option<String>,
+    /// Unix timestamp of when the annotation was introduced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    /// Unix timestamp of when the annotation was resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Codemark {
+    /// Renders the location of the annotation, taking container formats into account
+    #[must_use]
+    pub fn location(&self) -> String {
+        formats::format_location(&self.file, self.line_number, self.cell)
+    }
+
+    /// The line of the annotation in its file, unless it is inside a notebook cell
+    #[must_use]
+    pub fn file_line(&self) -> Option<usize> {
+        formats::file_line(&self.file, self.line_number, self.cell)
+    }
+
+    /// Short identifier of the annotation within a project. It only depends on the file
+    /// and the description, so it survives the annotation moving to another line.
+    #[must_use]
+    pub fn id(&self, project: &str) -> String {
+        // FNV-1a, which unlike the std hasher is stable across Rust versions
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for part in [project, &self.file, &self.description] {
+            for byte in part.bytes().chain([0]) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        format!("{:07x}", hash >> 36)
+    }
+
+    /// When the annotation was introduced: the earlier of the scan that first found it
+    /// and the commit that last touched its line, as far as either is known
+    #[must_use]
+    pub fn introduced_at(&self) -> Option<i64> {
+        self.created_at.into_iter().chain(self.commit_date).min()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CodemarksConfig {
     #[serde(default = "default_annotation_pattern")]
     pub annotation_pattern: String,
+    #[serde(default = "default_kinds")]
+    pub kinds: Vec<AnnotationKind>,
+    /// Comment leaders per file extension or file name, extending the built-in table
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub comment_syntax: BTreeMap<String, Vec<String>>,
+    /// Patterns that are always ignored when scanning
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Patterns that are ignored when scanning a particular project, keyed by its name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub project_ignore: BTreeMap<String, Vec<String>>,
+    /// Debounce time of the watcher in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce: Option<u64>,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Named profiles, selected with `--profile` or `CODEMARKS_PROFILE`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Link to an annotation in reports, with `{file}` and `{line}` placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository_url: Option<String>,
+    /// Command that opens an annotation, with `{file}` and `{line}` placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
 }
 
 impl Default for CodemarksConfig {
     fn default() -> Self {
         Self {
             annotation_pattern: default_annotation_pattern(),
+            kinds: default_kinds(),
+            comment_syntax: BTreeMap::new(),
+            ignore: Vec::new(),
+            project_ignore: BTreeMap::new(),
+            debounce: None,
+            output_format: OutputFormat::default(),
+            profiles: BTreeMap::new(),
+            repository_url: None,
+            editor: None,
+        }
+    }
+}
+
+impl CodemarksConfig {
+    /// Returns the pattern used to find annotations. As long as the default pattern is
+    /// configured, it is derived from the keywords of the configured kinds.
+    #[must_use]
+    pub fn effective_pattern(&self) -> String {
+        if self.annotation_pattern == default_annotation_pattern() && !self.kinds.is_empty() {
+            let keywords: Vec<String> = self
+                .kinds
+                .iter()
+                .flat_map(AnnotationKind::keywords)
+                .collect();
+            pattern_for_keywords(&keywords)
+        } else {
+            self.annotation_pattern.clone()
+        }
+    }
+}
+
+/// Format in which annotations are printed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// A JSON array of records
+    Json,
+    /// One JSON record per line
+    Ndjson,
+    /// Comma separated values with a header row
+    Csv,
+    /// Tab separated values with a header row
+    Tsv,
+    /// `file:line:col: severity: KIND: message`, for the quickfix list of vim and emacs
+    Quickfix,
+    /// `file:line:col: severity: message [KIND]`, like GCC and Clang diagnostics
+    Gcc,
+    /// `file(line,col): severity KIND: message`, like MSVC diagnostics
+    Msvc,
+}
+
+/// How serious an annotation kind is
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Info,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A named kind of annotation, such as `TODO` or `FIXME`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AnnotationKind {
+    pub name: String,
+    /// Keywords that identify this kind, defaults to the name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Optional regex that identifies this kind in addition to the keywords
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Terminal color used when displaying annotations of this kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl AnnotationKind {
+    #[must_use]
+    pub fn new(name: &str, severity: Severity, color: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            keywords: Vec::new(),
+            pattern: None,
+            severity,
+            color: Some(color.to_string()),
+        }
+    }
+
+    /// Returns the keywords of this kind, falling back to its name
+    #[must_use]
+    pub fn keywords(&self) -> Vec<String> {
+        if self.keywords.is_empty() {
+            vec![self.name.clone()]
+        } else {
+            self.keywords.clone()
         }
     }
+
+    /// Finds a kind by name, ignoring case
+    #[must_use]
+    pub fn find<'a>(kinds: &'a [Self], name: Option<&str>) -> Option<&'a Self> {
+        let name = name?;
+        kinds
+            .iter()
+            .find(|kind| kind.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[must_use]
+pub fn default_kinds() -> Vec<AnnotationKind> {
+    vec![
+        AnnotationKind::new("FIXME", Severity::Error, "red"),
+        AnnotationKind::new("TODO", Severity::Warning, "yellow"),
+        AnnotationKind::new("HACK", Severity::Warning, "magenta"),
+    ]
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ProjectsDatabase {
     pub projects: HashMap<String, Vec<Codemark>>,
+    /// Unix timestamp of the last scan of each project
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub scanned_at: HashMap<String, i64>,
+    /// Root directory of each project, as of its last scan
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roots: HashMap<String, PathBuf>,
 }
 
 #[must_use]
@@ -46,6 +297,17 @@ pub fn default_annotation_pattern() -> String {
     r"(?i)(?://|#|<!--|\*)\s*(?:TODO|FIXME|HACK)\s*:?\s*(.*)$".to_string()
 }
 
+/// Builds an annotation pattern that matches the given keywords after a comment leader
+#[must_use]
+pub fn pattern_for_keywords(keywords: &[String]) -> String {
+    let alternatives = keywords
+        .iter()
+        .map(|keyword| regex::escape(keyword))
+        .collect::<Vec<_>>()
+        .join("|");
+    format!(r"(?i)(?://|#|<!--|\*)\s*(?:{alternatives})\s*:?\s*(.*)$")
+}
+
 fn get_global_file_path(filename: &str) -> Result<PathBuf> {
     let home_dir = std::env::var("HOME")
         .map_err(|_| anyhow::anyhow!("Could not find HOME environment variable"))?;
@@ -62,8 +324,9 @@ pub fn get_global_projects_path() -> Result<PathBuf> {
     get_global_file_path("projects.json")
 }
 
+/// Reads the global `config.json` on its own, as needed to modify and save it
 #[must_use]
-pub fn load_global_config(ephemeral: bool) -> CodemarksConfig {
+pub fn read_global_config(ephemeral: bool) -> CodemarksConfig {
     if ephemeral {
         return CodemarksConfig::default();
     }
@@ -78,6 +341,30 @@ pub fn load_global_config(ephemeral: bool) -> CodemarksConfig {
     CodemarksConfig::default()
 }
 
+/// Loads the effective configuration for a directory. Settings are layered from lowest
+/// to highest precedence: built-in defaults, the global `config.json`, the repository
+/// `.codemarks.toml`, `CODEMARKS_*` environment variables and command line flags.
+pub fn load_global_config(
+    directory: &Path,
+    overrides: &ConfigOverrides,
+    ephemeral: bool,
+) -> Result<LayeredConfig> {
+    LayeredConfig::load(directory, overrides, ephemeral)
+}
+
+/// Resolves the output format of a command, falling back to the configured one
+fn resolve_output_format(
+    requested: Option<OutputFormat>,
+    directory: &Path,
+    ephemeral: bool,
+) -> OutputFormat {
+    requested.unwrap_or_else(|| {
+        load_global_config(directory, &ConfigOverrides::default(), ephemeral)
+            .map(|layered| layered.config.output_format)
+            .unwrap_or_default()
+    })
+}
+
 pub fn save_global_config(config: &CodemarksConfig, ephemeral: bool) -> Result<()> {
     if ephemeral {
         return Ok(());
@@ -130,6 +417,10 @@ struct Cli {
     #[arg(long, global = true)]
     ephemeral: bool,
 
+    /// Use the settings of a named configuration profile
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -146,9 +437,68 @@ enum Commands {
         /// Patterns to ignore when scanning files
         #[arg(short, long)]
         ignore: Vec<String>,
+        #[command(flatten)]
+        selection: FileSelection,
+        /// Attribute annotations to their author using git blame
+        #[arg(long)]
+        blame: bool,
+        /// Print the found annotations in this format instead of a summary
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
     },
     /// List all persisted annotations
-    List,
+    List {
+        #[command(flatten)]
+        filter: ListFilter,
+        #[command(flatten)]
+        layout: ListLayout,
+        /// Output format (defaults to the output_format setting)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Color annotations and link them to their files (auto: only on a terminal,
+        /// unless NO_COLOR is set)
+        #[arg(long, value_enum, default_value_t)]
+        color: ColorChoice,
+    },
+    /// Search the annotations of all projects, e.g. 'kind:FIXME owner:alice age>30d -resolved'
+    Query {
+        /// Query of field predicates such as kind:, owner:, path: and age>, see the README
+        query: Query,
+        /// Show at most this many annotations
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        #[command(flatten)]
+        layout: ListLayout,
+        /// Output format (defaults to the output_format setting)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Color annotations and link them to their files (auto: only on a terminal,
+        /// unless NO_COLOR is set)
+        #[arg(long, value_enum, default_value_t)]
+        color: ColorChoice,
+    },
+    /// Show counts of open and resolved annotations per project and kind, the most
+    /// annotated files, their average age and the changes since the last scan
+    Stats {
+        /// Only show the statistics of this project
+        #[arg(short, long)]
+        project: Option<String>,
+        /// Number of files to show in the most annotated files
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: StatsFormat,
+    },
+    /// Open an annotation in $VISUAL or $EDITOR, at its line
+    Open {
+        /// Id of the annotation (see 'list --ids'), its location such as src/main.rs:12,
+        /// or a query matching a single open annotation
+        target: String,
+    },
+    /// Browse annotations in a full-screen terminal UI, with a project and file tree, a
+    /// filterable list and a preview of the source around the selected annotation
+    Tui,
     /// Manage global configuration settings
     Config {
         #[command(subcommand)]
@@ -165,6 +515,18 @@ enum Commands {
         /// Patterns to ignore when scanning files
         #[arg(short, long)]
         ignore: Vec<String>,
+        #[command(flatten)]
+        selection: FileSelection,
+        /// Only count annotations matching this query, e.g. 'kind:FIXME path:src/**'
+        #[arg(short, long)]
+        query: Option<Query>,
+        /// Print the found annotations in this format, with the summary on stderr
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Color annotations and link them to their files (auto: only on a terminal,
+        /// unless NO_COLOR is set)
+        #[arg(long, value_enum, default_value_t)]
+        color: ColorChoice,
     },
     /// Watch directory for changes and scan modified files in real-time
     Watch {
@@ -174,8 +536,11 @@ enum Commands {
         /// Patterns to ignore when watching files
         #[arg(short, long)]
         ignore: Vec<String>,
-        /// Debounce time in milliseconds to avoid duplicate events
-        #[arg(long, default_value = "2000")]
+        #[command(flatten)]
+        selection: FileSelection,
+        /// Debounce time in milliseconds to avoid duplicate events (defaults to the
+        /// configured debounce, or 2000)
+        #[arg(long)]
         debounce: Option<u64>,
     },
     /// Remove resolved annotations from the global database
@@ -186,14 +551,153 @@ enum Commands {
         /// Specific project to clean (if not specified, cleans all projects)
         #[arg(short, long)]
         project: Option<String>,
+        /// Only remove resolved annotations matching this query, e.g. 'resolved<2024-01-01'
+        #[arg(short, long)]
+        query: Option<Query>,
+    },
+    /// Render the open annotations as a Markdown or HTML report, grouped by project and file
+    Report {
+        #[command(flatten)]
+        filter: ListFilter,
+        /// Report format
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+        /// File to write the report to (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// URL template linking annotations to the repository, e.g.
+        /// 'https://github.com/org/repo/blob/main/{file}#L{line}'
+        #[arg(long)]
+        repository_url: Option<String>,
+    },
+    /// List the file types that can be used with --type and --type-not
+    Types,
+    /// Reconstruct annotation history from the local git repository
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
     },
 }
 
 #[derive(Subcommand)]
 enum ConfigAction {
-    Show,
-    SetPattern { pattern: String },
+    Show {
+        /// Show the effective settings for the current directory and where each came from
+        #[arg(long)]
+        origin: bool,
+    },
+    SetPattern {
+        pattern: String,
+    },
+    /// Reset the annotation pattern to the default, keeping the other settings
     Reset,
+    /// Print the value of a setting from the global configuration
+    Get {
+        /// Name of the setting, e.g. debounce
+        key: String,
+    },
+    /// Change a setting in the global configuration
+    Set {
+        /// Name of the setting, e.g. debounce
+        key: String,
+        /// New value; lists are comma separated, structured settings are JSON
+        value: String,
+    },
+    /// Restore the default value of a setting in the global configuration
+    Unset {
+        /// Name of the setting, e.g. debounce
+        key: String,
+    },
+    /// Open the global configuration in $VISUAL or $EDITOR and validate it afterwards
+    Edit,
+    /// Manage the ignore patterns stored for a project or globally
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreAction,
+    },
+    /// Try out a pattern on sample lines or a file before configuring it
+    Test {
+        /// Pattern to test (defaults to the configured pattern)
+        pattern: Option<String>,
+        /// Sample line to match against
+        #[arg(short, long)]
+        line: Vec<String>,
+        /// File whose lines are matched against
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Manage the annotation kinds and their severities
+    Kind {
+        #[command(subcommand)]
+        action: KindAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum KindAction {
+    /// List the configured annotation kinds
+    List,
+    /// Add an annotation kind, or update it if it already exists
+    Add {
+        /// Name of the kind, e.g. NOTE
+        name: String,
+        /// Keywords that identify the kind (defaults to the name)
+        #[arg(short, long)]
+        keyword: Vec<String>,
+        /// Regex that identifies the kind in addition to the keywords
+        #[arg(short, long)]
+        pattern: Option<String>,
+        /// Severity of the kind
+        #[arg(short, long, value_enum, default_value_t = Severity::Warning)]
+        severity: Severity,
+        /// Color used to display the kind
+        #[arg(short, long)]
+        color: Option<String>,
+    },
+    /// Remove an annotation kind
+    Remove {
+        /// Name of the kind to remove
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum IgnoreAction {
+    /// List the stored ignore patterns
+    List,
+    /// Store an ignore pattern
+    Add {
+        pattern: String,
+        #[command(flatten)]
+        target: IgnoreTarget,
+    },
+    /// Remove a stored ignore pattern
+    Remove {
+        pattern: String,
+        #[command(flatten)]
+        target: IgnoreTarget,
+    },
+}
+
+/// Where stored ignore patterns apply, defaulting to the project in the current directory
+#[derive(Args)]
+struct IgnoreTarget {
+    /// Apply to this project instead of the one in the current directory
+    #[arg(short, long, conflicts_with = "global")]
+    project: Option<String>,
+    /// Apply to every project
+    #[arg(short, long)]
+    global: bool,
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Date existing annotations and record removed ones by walking the git log
+    Backfill {
+        /// Directory of the project to backfill
+        #[arg(short, long, default_value = ".")]
+        directory: Option<PathBuf>,
+    },
 }
 
 fn initialize_codemarks() -> Result<()> {
@@ -207,17 +711,16 @@ fn initialize_codemarks() -> Result<()> {
         let default_config = CodemarksConfig::default();
         let config_json = serde_json::to_string_pretty(&default_config)?;
         std::fs::write(&config_path, config_json)?;
-        println!("Created default config file at {}", config_path.display());
+        // Notices go to stderr, so they never mix with machine readable output
+        eprintln!("Created default config file at {}", config_path.display());
     }
 
     let projects_path = config_dir.join("projects.json");
     if !projects_path.exists() {
-        let default_projects = ProjectsDatabase {
-            projects: HashMap::new(),
-        };
+        let default_projects = ProjectsDatabase::default();
         let projects_json = serde_json::to_string_pretty(&default_projects)?;
         std::fs::write(&projects_path, projects_json)?;
-        println!(
+        eprintln!(
             "Created default projects file at {}",
             projects_path.display()
         );
@@ -228,6 +731,9 @@ fn initialize_codemarks() -> Result<()> {
 
 fn main() {
     let cli = Cli::parse();
+    if let Some(profile) = &cli.profile {
+        config_layers::select_profile(profile);
+    }
 
     if !cli.ephemeral
         && let Err(e) = initialize_codemarks()
@@ -239,10 +745,30 @@ fn main() {
         Commands::Version => {
             println!("codemarks version {}", env!("CARGO_PKG_VERSION"));
         }
-        Commands::Scan { directory, ignore } => {
+        Commands::Scan {
+            directory,
+            ignore,
+            selection,
+            blame,
+            format,
+        } => {
             let dir = directory.as_deref().unwrap_or(Path::new("."));
-            match scan::scan_directory(dir, &ignore, cli.ephemeral) {
-                Ok(count) => {
+            let format = resolve_output_format(format, dir, cli.ephemeral);
+            match scan::scan_directory(dir, &ignore, &selection, blame, cli.ephemeral) {
+                Ok(summary) if format.is_machine_readable() => {
+                    let records: Vec<_> = summary
+                        .codemarks
+                        .iter()
+                        .map(|(project, codemark)| {
+                            output::Record::new(project, codemark, &summary.kinds)
+                        })
+                        .collect();
+                    if let Err(e) = output::print_records(format, &records) {
+                        eprintln!("Error writing output: {e}");
+                    }
+                }
+                Ok(summary) => {
+                    let count = summary.total;
                     if cli.ephemeral {
                         println!("Found {count} code annotations (ephemeral mode)");
                     } else {
@@ -254,49 +780,160 @@ fn main() {
                 Err(e) => eprintln!("Error scanning directory: {e}"),
             }
         }
-        Commands::List => {
-            list::list_codemarks(cli.ephemeral);
+        Commands::List {
+            filter,
+            layout,
+            format,
+            color,
+        } => {
+            let format = resolve_output_format(format, Path::new("."), cli.ephemeral);
+            if let Err(e) = list::list_codemarks(&filter, layout, format, color, cli.ephemeral) {
+                eprintln!("Error listing annotations: {e}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Query {
+            query,
+            limit,
+            layout,
+            format,
+            color,
+        } => {
+            let filter = ListFilter {
+                all_projects: true,
+                query: Some(query),
+                limit,
+                ..Default::default()
+            };
+            let format = resolve_output_format(format, Path::new("."), cli.ephemeral);
+            if let Err(e) = list::list_codemarks(&filter, layout, format, color, cli.ephemeral) {
+                eprintln!("Error listing annotations: {e}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Stats {
+            project,
+            top,
+            format,
+        } => {
+            stats::show_stats(project.as_deref(), top, format, cli.ephemeral);
+        }
+        Commands::Open { target } => {
+            if cli.ephemeral {
+                eprintln!("Open command is not available in ephemeral mode (--ephemeral)");
+                std::process::exit(1);
+            }
+            if let Err(e) = open::open_codemark(&target) {
+                eprintln!("Error opening annotation: {e}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Tui => {
+            if cli.ephemeral {
+                eprintln!("Tui command is not available in ephemeral mode (--ephemeral)");
+                std::process::exit(1);
+            }
+            if let Err(e) = tui::run_tui() {
+                eprintln!("Error running terminal UI: {e}");
+                std::process::exit(1);
+            }
         }
         Commands::Config { action } => {
             if cli.ephemeral {
                 eprintln!("Config management is not available in ephemeral mode (--ephemeral)");
                 std::process::exit(1);
             }
-            match config::handle_config(action) {
-                Ok(()) => {}
-                Err(e) => eprintln!("Error managing config: {e}"),
+            if let Err(e) = config::handle_config(action) {
+                eprintln!("Error managing config: {e}");
+                std::process::exit(1);
             }
         }
         Commands::Ci {
             directory,
             pattern,
             ignore,
+            selection,
+            query,
+            format,
+            color,
         } => {
             let dir = directory.as_deref().unwrap_or(Path::new("."));
-            // CI mode defaults to no-storage behavior (override the global flag)
-            ci::run_ci(dir, pattern, &ignore);
+            // CI never stores annotations, but reads the configuration like other commands
+            let format = resolve_output_format(format, dir, cli.ephemeral);
+            let overrides = ConfigOverrides {
+                pattern,
+                ignore,
+                ..Default::default()
+            };
+            ci::run_ci(
+                dir,
+                &overrides,
+                &selection,
+                query.as_ref(),
+                format,
+                color,
+                cli.ephemeral,
+            );
         }
         Commands::Watch {
             directory,
             ignore,
+            selection,
             debounce,
         } => {
             let dir = directory.as_deref().unwrap_or(Path::new("."));
-            match watch::watch_directory(dir, &ignore, debounce, cli.ephemeral) {
+            match watch::watch_directory(dir, &ignore, &selection, debounce, cli.ephemeral) {
                 Ok(()) => {}
                 Err(e) => eprintln!("Error watching directory: {e}"),
             }
         }
-        Commands::Clean { dry_run, project } => {
+        Commands::Clean {
+            dry_run,
+            project,
+            query,
+        } => {
             if cli.ephemeral {
                 eprintln!("Clean command is not available in ephemeral mode (--ephemeral)");
                 std::process::exit(1);
             }
-            match clean::clean_resolved(dry_run, project) {
+            match clean::clean_resolved(dry_run, project, query.as_ref()) {
                 Ok(()) => {}
                 Err(e) => eprintln!("Error cleaning resolved annotations: {e}"),
             }
         }
+        Commands::Report {
+            filter,
+            format,
+            output,
+            repository_url,
+        } => {
+            if cli.ephemeral {
+                eprintln!("Report command is not available in ephemeral mode (--ephemeral)");
+                std::process::exit(1);
+            }
+            match report::generate_report(&filter, format, output.as_deref(), repository_url) {
+                Ok(()) => {}
+                Err(e) => eprintln!("Error generating report: {e}"),
+            }
+        }
+        Commands::Types => {
+            filters::list_types();
+        }
+        Commands::History { action } => {
+            if cli.ephemeral {
+                eprintln!("History command is not available in ephemeral mode (--ephemeral)");
+                std::process::exit(1);
+            }
+            match action {
+                HistoryAction::Backfill { directory } => {
+                    let dir = directory.as_deref().unwrap_or(Path::new("."));
+                    match history::backfill_history(dir, cli.ephemeral) {
+                        Ok(()) => {}
+                        Err(e) => eprintln!("Error backfilling history: {e}"),
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -322,6 +959,7 @@ mod tests {
             line_number: 42,
             description: "This is a test TODO".to_string(),
             resolved: false,
+            ..Default::default()
         };
 
         assert_eq!(codemark.file, "test.rs");
@@ -337,6 +975,7 @@ mod tests {
             line_number: 42,
             description: "This is a test TODO".to_string(),
             resolved: false,
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&codemark).expect("Failed to serialize");
@@ -366,6 +1005,7 @@ mod tests {
     fn test_codemarks_config_serialization() {
         let config = CodemarksConfig {
             annotation_pattern: "CUSTOM_PATTERN".to_string(),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&config).expect("Failed to serialize config");
@@ -389,6 +1029,7 @@ mod tests {
             line_number: 1,
             description: "Test annotation".to_string(),
             resolved: false,
+            ..Default::default()
         };
 
         // Add a project with codemarks
@@ -439,7 +1080,7 @@ mod tests {
         let _temp_home = setup_temp_home();
 
         // Load config when no file exists should return default
-        let config = load_global_config(false);
+        let config = read_global_config(false);
         assert_eq!(config.annotation_pattern, default_annotation_pattern());
     }
 
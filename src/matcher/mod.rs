@@ -0,0 +1,308 @@
+// src/matcher.rs
+// Matches annotation lines and classifies them into the configured kinds
+
+use anyhow::Result;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::comment_syntax::{builtin_keys, leaders_for, pattern_for_leaders};
+use crate::formats::SourceLine;
+use crate::{AnnotationKind, CodemarksConfig, Severity, default_annotation_pattern};
+
+/// An annotation kind with its regexes compiled
+struct CompiledKind {
+    name: String,
+    severity: Severity,
+    keywords: Regex,
+    pattern: Option<Regex>,
+}
+
+/// The result of matching a single line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// Name of the kind the line was classified as, if any kind applies
+    pub kind: Option<String>,
+    pub severity: Option<Severity>,
+    /// The `desc` group of the annotation pattern, its first unnamed group, or the whole line
+    pub description: String,
+    /// The `owner` group of the annotation pattern
+    pub owner: Option<String>,
+    /// The `issue` group of the annotation pattern
+    pub issue: Option<String>,
+    /// 1-based column, in characters, where the match starts
+    pub column: usize,
+}
+
+/// Named capture groups of an annotation pattern that populate the fields of a codemark
+pub const CAPTURE_GROUPS: &[&str] = &["kind", "owner", "issue", "desc"];
+
+/// Compiles an annotation pattern, rejecting named groups that do not map to a field
+pub fn compile_pattern(pattern: &str) -> Result<Regex> {
+    let regex = Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid regex pattern: {e}"))?;
+    if let Some(name) = regex
+        .capture_names()
+        .flatten()
+        .find(|name| !CAPTURE_GROUPS.contains(name))
+    {
+        return Err(anyhow::anyhow!(
+            "Unknown capture group '{name}' in pattern, expected one of: {}",
+            CAPTURE_GROUPS.join(", ")
+        ));
+    }
+    Ok(regex)
+}
+
+/// Comment directives that suppress annotations
+pub const IGNORE_LINE: &str = "codemarks-ignore-line";
+pub const IGNORE_NEXT_LINE: &str = "codemarks-ignore-next-line";
+pub const IGNORE_FILE: &str = "codemarks-ignore-file";
+/// Comment leaders of files without a known comment syntax, as in the default pattern
+const DEFAULT_LEADERS: &[&str] = &["//", "#", "<!--", "/*", "*"];
+
+/// The annotations found in the lines of one file
+#[derive(Debug, Default)]
+pub struct FileMatches {
+    pub matches: Vec<(SourceLine, LineMatch)>,
+    /// Number of annotations silenced by suppression directives
+    pub suppressed: usize,
+}
+
+/// Finds annotations in lines of source code
+pub struct Matcher {
+    pattern: Regex,
+    kinds: Vec<CompiledKind>,
+    /// Patterns for files with a known comment syntax, keyed by the comment leaders
+    syntax_patterns: HashMap<Vec<String>, Regex>,
+    comment_syntax: BTreeMap<String, Vec<String>>,
+}
+
+impl Matcher {
+    /// Creates a matcher from the effective pattern and kinds of a configuration. As long
+    /// as the pattern is derived from the kinds, files with a known comment syntax are
+    /// matched on the keywords after their own comment leaders.
+    pub fn new(config: &CodemarksConfig) -> Result<Self> {
+        let mut matcher = Self::with_pattern(&config.effective_pattern(), &config.kinds)?;
+        if config.annotation_pattern != default_annotation_pattern() || config.kinds.is_empty() {
+            return Ok(matcher);
+        }
+
+        let keywords: Vec<String> = config
+            .kinds
+            .iter()
+            .flat_map(AnnotationKind::keywords)
+            .collect();
+        let keys = builtin_keys()
+            .map(str::to_string)
+            .chain(config.comment_syntax.keys().cloned());
+        for key in keys {
+            let Some(leaders) = leaders_for(Path::new(&key), &config.comment_syntax) else {
+                continue;
+            };
+            if leaders.is_empty() || matcher.syntax_patterns.contains_key(&leaders) {
+                continue;
+            }
+            let pattern = Regex::new(&pattern_for_leaders(&leaders, &keywords))
+                .map_err(|e| anyhow::anyhow!("Invalid comment syntax for {key}: {e}"))?;
+            matcher.syntax_patterns.insert(leaders, pattern);
+        }
+        matcher.comment_syntax.clone_from(&config.comment_syntax);
+        Ok(matcher)
+    }
+
+    /// Creates a matcher for an explicit pattern, classifying matches into the given kinds
+    pub fn with_pattern(pattern: &str, kinds: &[AnnotationKind]) -> Result<Self> {
+        let pattern = compile_pattern(pattern)?;
+        let kinds = kinds
+            .iter()
+            .map(|kind| {
+                let keywords = kind
+                    .keywords()
+                    .iter()
+                    .map(|keyword| regex::escape(keyword))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                let kind_pattern = kind
+                    .pattern
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid pattern for kind {}: {e}", kind.name))?;
+                Ok(CompiledKind {
+                    name: kind.name.clone(),
+                    severity: kind.severity,
+                    keywords: Regex::new(&format!(r"(?i)\b(?:{keywords})\b"))?,
+                    pattern: kind_pattern,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            pattern,
+            kinds,
+            syntax_patterns: HashMap::new(),
+            comment_syntax: BTreeMap::new(),
+        })
+    }
+
+    /// Checks a line of a file for an annotation, using the comment syntax of the file
+    /// when it is known
+    pub fn match_file_line(&self, file: &Path, line: &str) -> Option<LineMatch> {
+        let pattern = if self.syntax_patterns.is_empty() {
+            None
+        } else {
+            leaders_for(file, &self.comment_syntax)
+                .and_then(|leaders| self.syntax_patterns.get(&leaders))
+        };
+        self.match_with(pattern.unwrap_or(&self.pattern), line)
+    }
+
+    /// Finds the annotations in the lines of a file, leaving out the ones that are
+    /// suppressed by a `codemarks-ignore-*` directive in a comment
+    pub fn match_file_lines(&self, file: &Path, lines: Vec<SourceLine>) -> FileMatches {
+        let leaders = leaders_for(file, &self.comment_syntax)
+            .unwrap_or_else(|| DEFAULT_LEADERS.iter().map(ToString::to_string).collect());
+        let directive = |line: &SourceLine, directive: &str| {
+            comment_start(&line.text, &leaders)
+                .is_some_and(|start| line.text[start..].contains(directive))
+        };
+        let ignore_file = lines.iter().any(|line| directive(line, IGNORE_FILE));
+        let mut result = FileMatches::default();
+        let mut previous: Option<(Option<usize>, usize)> = None;
+        let mut ignore_next = false;
+        for line in lines {
+            // The next line directive only applies to the line directly below it
+            let follows_directive =
+                ignore_next && previous == Some((line.cell, line.line_number.saturating_sub(1)));
+            ignore_next = directive(&line, IGNORE_NEXT_LINE);
+            previous = Some((line.cell, line.line_number));
+
+            let Some(line_match) = self.match_file_line(file, &line.text) else {
+                continue;
+            };
+            if ignore_file || follows_directive || directive(&line, IGNORE_LINE) {
+                result.suppressed += 1;
+            } else {
+                result.matches.push((line, line_match));
+            }
+        }
+        result
+    }
+
+    /// Checks a line for an annotation. A line is an annotation when it matches the
+    /// annotation pattern or the pattern of one of the kinds.
+    pub fn match_line(&self, line: &str) -> Option<LineMatch> {
+        self.match_with(&self.pattern, line)
+    }
+
+    fn match_with(&self, pattern: &Regex, line: &str) -> Option<LineMatch> {
+        if let Some(captures) = pattern.captures(line) {
+            let start = captures.get(0).map_or(0, |whole| whole.start());
+            let group = |name: &str| {
+                captures
+                    .name(name)
+                    .map(|value| value.as_str().trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            // Without a `desc` group the first unnamed group is the description
+            let description = group("desc").unwrap_or_else(|| {
+                pattern
+                    .capture_names()
+                    .enumerate()
+                    .skip(1)
+                    .find(|(_, name)| name.is_none())
+                    .and_then(|(index, _)| captures.get(index))
+                    .map_or(line, |description| description.as_str())
+                    .trim()
+                    .to_string()
+            });
+            // A `kind` group is classified on its own, and names the kind when none applies
+            let (kind, severity) = match group("kind") {
+                Some(name) => match self.classify(&name) {
+                    Some(kind) => (Some(kind.name.clone()), Some(kind.severity)),
+                    None => (Some(name), None),
+                },
+                None => {
+                    let kind = self.classify(line);
+                    (
+                        kind.map(|kind| kind.name.clone()),
+                        kind.map(|kind| kind.severity),
+                    )
+                }
+            };
+            return Some(LineMatch {
+                kind,
+                severity,
+                description,
+                owner: group("owner"),
+                issue: group("issue"),
+                column: column(line, start),
+            });
+        }
+
+        let (kind, found) = self.kinds.iter().find_map(|kind| {
+            kind.pattern
+                .as_ref()
+                .and_then(|pattern| pattern.find(line))
+                .map(|found| (kind, found))
+        })?;
+        Some(LineMatch {
+            kind: Some(kind.name.clone()),
+            severity: Some(kind.severity),
+            description: line.trim().to_string(),
+            owner: None,
+            issue: None,
+            column: column(line, found.start()),
+        })
+    }
+
+    /// Finds the kind of an annotation line. Kind patterns take precedence, then the
+    /// keyword that appears first in the line decides.
+    fn classify(&self, line: &str) -> Option<&CompiledKind> {
+        if let Some(kind) = self.kinds.iter().find(|kind| {
+            kind.pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(line))
+        }) {
+            return Some(kind);
+        }
+        self.kinds
+            .iter()
+            .filter_map(|kind| kind.keywords.find(line).map(|m| (m.start(), kind)))
+            .min_by_key(|(start, _)| *start)
+            .map(|(_, kind)| kind)
+    }
+}
+
+/// Finds where the comment of a line starts: the first comment leader outside a double
+/// quoted string literal
+fn comment_start(line: &str, leaders: &[String]) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if leaders
+            .iter()
+            .any(|leader| line[index..].starts_with(leader.as_str()))
+        {
+            return Some(index);
+        } else if c == '"' {
+            in_string = true;
+        }
+    }
+    None
+}
+
+/// Converts a byte offset into a line to a 1-based character column
+fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+#[cfg(test)]
+mod tests;
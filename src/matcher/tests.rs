@@ -0,0 +1,294 @@
+use super::*;
+use crate::default_kinds;
+
+fn default_matcher() -> Matcher {
+    Matcher::new(&CodemarksConfig::default()).unwrap()
+}
+
+#[test]
+fn test_match_line_classifies_default_kinds() {
+    let matcher = default_matcher();
+
+    let todo = matcher.match_line("// TODO: add tests").unwrap();
+    assert_eq!(todo.kind.as_deref(), Some("TODO"));
+    assert_eq!(todo.severity, Some(Severity::Warning));
+    assert_eq!(todo.description, "add tests");
+
+    let fixme = matcher.match_line("# fixme handle errors").unwrap();
+    assert_eq!(fixme.kind.as_deref(), Some("FIXME"));
+    assert_eq!(fixme.severity, Some(Severity::Error));
+
+    assert!(matcher.match_line("let todo_list = Vec::new();").is_none());
+}
+
+#[test]
+fn test_match_line_uses_first_keyword() {
+    let matcher = default_matcher();
+
+    let line_match = matcher.match_line("// HACK: works around a TODO").unwrap();
+    assert_eq!(line_match.kind.as_deref(), Some("HACK"));
+}
+
+#[test]
+fn test_match_line_column() {
+    let matcher = default_matcher();
+
+    assert_eq!(matcher.match_line("// TODO: at start").unwrap().column, 1);
+    assert_eq!(
+        matcher.match_line("    let x = 1; # FIXME").unwrap().column,
+        16
+    );
+    // Columns count characters, not bytes
+    assert_eq!(matcher.match_line("\"é\"; // HACK").unwrap().column, 6);
+}
+
+#[test]
+fn test_custom_kinds_with_keywords_and_pattern() {
+    let mut config = CodemarksConfig::default();
+    let mut note = AnnotationKind::new("NOTE", Severity::Info, "blue");
+    note.keywords = vec!["NOTE".to_string(), "NB".to_string()];
+    let mut ticket = AnnotationKind::new("TICKET", Severity::Info, "cyan");
+    ticket.pattern = Some(r"\bJIRA-\d+\b".to_string());
+    config.kinds = vec![note, ticket];
+    let matcher = Matcher::new(&config).unwrap();
+
+    let nb = matcher.match_line("// NB: this is slow").unwrap();
+    assert_eq!(nb.kind.as_deref(), Some("NOTE"));
+    assert_eq!(nb.description, "this is slow");
+
+    // Kind patterns match lines that do not contain any keyword
+    let ticket = matcher.match_line("retry(); // see JIRA-42").unwrap();
+    assert_eq!(ticket.kind.as_deref(), Some("TICKET"));
+    assert_eq!(ticket.description, "retry(); // see JIRA-42");
+    assert_eq!(ticket.column, 17);
+
+    assert!(matcher.match_line("// TODO: not configured").is_none());
+}
+
+#[test]
+fn test_custom_pattern_without_matching_kind() {
+    let matcher = Matcher::with_pattern(r"XXX\s*(.*)", &default_kinds()).unwrap();
+
+    let line_match = matcher.match_line("// XXX remove this").unwrap();
+    assert_eq!(line_match.kind, None);
+    assert_eq!(line_match.severity, None);
+    assert_eq!(line_match.description, "remove this");
+}
+
+#[test]
+fn test_invalid_patterns() {
+    assert!(Matcher::with_pattern("[invalid(", &default_kinds()).is_err());
+
+    let mut kind = AnnotationKind::new("BAD", Severity::Info, "red");
+    kind.pattern = Some("(unclosed".to_string());
+    assert!(Matcher::with_pattern(r"TODO", &[kind]).is_err());
+}
+
+#[test]
+fn test_match_file_line_uses_comment_syntax() {
+    let matcher = default_matcher();
+
+    let sql = matcher
+        .match_file_line(Path::new("schema.sql"), "-- TODO: add an index")
+        .unwrap();
+    assert_eq!(sql.kind.as_deref(), Some("TODO"));
+    assert_eq!(sql.description, "add an index");
+    assert!(
+        matcher
+            .match_file_line(Path::new("init.lisp"), ";; FIXME leaks memory")
+            .is_some()
+    );
+    assert!(
+        matcher
+            .match_file_line(Path::new("paper.tex"), "% TODO cite the paper")
+            .is_some()
+    );
+    assert!(
+        matcher
+            .match_file_line(Path::new("build.bat"), "REM HACK: hardcoded path")
+            .is_some()
+    );
+
+    // Leaders of other languages are not comments in Python
+    assert!(
+        matcher
+            .match_file_line(Path::new("main.py"), "x = a // TODO")
+            .is_none()
+    );
+    // Files without known syntax fall back to the generic pattern
+    assert!(
+        matcher
+            .match_file_line(Path::new("notes.txt"), "# TODO: generic")
+            .is_some()
+    );
+}
+
+#[test]
+fn test_configured_comment_syntax() {
+    let mut config = CodemarksConfig::default();
+    config
+        .comment_syntax
+        .insert("txt".to_string(), vec![">".to_string()]);
+    let matcher = Matcher::new(&config).unwrap();
+
+    assert!(
+        matcher
+            .match_file_line(Path::new("notes.txt"), "> TODO: quoted")
+            .is_some()
+    );
+    assert!(
+        matcher
+            .match_file_line(Path::new("notes.txt"), "# TODO: generic")
+            .is_none()
+    );
+
+    // A custom pattern is used for every file as is
+    config.annotation_pattern = r"XXX(.*)".to_string();
+    let matcher = Matcher::new(&config).unwrap();
+    assert!(
+        matcher
+            .match_file_line(Path::new("schema.sql"), "XXX anywhere")
+            .is_some()
+    );
+}
+
+fn source_lines(lines: &[&str]) -> Vec<SourceLine> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, text)| SourceLine {
+            line_number: index + 1,
+            cell: None,
+            text: (*text).to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_match_file_lines_suppressions() {
+    let matcher = default_matcher();
+    let lines = source_lines(&[
+        "// TODO: first",
+        "// TODO: documented convention // codemarks-ignore-line",
+        "// codemarks-ignore-next-line",
+        "// FIXME: intentional",
+        "// HACK: not suppressed",
+    ]);
+
+    let result = matcher.match_file_lines(Path::new("main.rs"), lines);
+    let found: Vec<usize> = result
+        .matches
+        .iter()
+        .map(|(line, _)| line.line_number)
+        .collect();
+    assert_eq!(found, vec![1, 5]);
+    assert_eq!(result.suppressed, 2);
+}
+
+#[test]
+fn test_match_file_lines_ignore_file() {
+    let matcher = default_matcher();
+    let lines = source_lines(&["// codemarks-ignore-file", "// TODO: a", "# FIXME: b"]);
+
+    let result = matcher.match_file_lines(Path::new("notes.txt"), lines);
+    assert!(result.matches.is_empty());
+    assert_eq!(result.suppressed, 2);
+}
+
+#[test]
+fn test_directives_only_apply_in_comments() {
+    let matcher = default_matcher();
+    let lines = source_lines(&[
+        r#"const DIRECTIVE: &str = "codemarks-ignore-file";"#,
+        r#"let doc = "see // codemarks-ignore-next-line";"#,
+        "// TODO: still reported",
+        r#"println!("codemarks-ignore-line"); // FIXME: also reported"#,
+        r#"let s = "a \" quote"; // HACK: suppressed // codemarks-ignore-line"#,
+    ]);
+
+    let result = matcher.match_file_lines(Path::new("main.rs"), lines);
+    let found: Vec<usize> = result
+        .matches
+        .iter()
+        .map(|(line, _)| line.line_number)
+        .collect();
+    assert_eq!(found, vec![3, 4]);
+    assert_eq!(result.suppressed, 1);
+
+    // Files without comments cannot carry directives
+    let lines = source_lines(&["codemarks-ignore-file", "# TODO: reported"]);
+    let result = matcher.match_file_lines(Path::new("notes.txt"), lines);
+    assert_eq!(result.matches.len(), 1);
+}
+
+#[test]
+fn test_ignore_next_line_stays_within_cell() {
+    let matcher = default_matcher();
+    let lines = vec![
+        SourceLine {
+            line_number: 1,
+            cell: Some(1),
+            text: "# codemarks-ignore-next-line".to_string(),
+        },
+        SourceLine {
+            line_number: 1,
+            cell: Some(2),
+            text: "# TODO: next cell".to_string(),
+        },
+    ];
+
+    let result = matcher.match_file_lines(Path::new("notebook.ipynb"), lines);
+    assert_eq!(result.matches.len(), 1);
+    assert_eq!(result.suppressed, 0);
+}
+
+#[test]
+fn test_named_groups() {
+    let matcher = Matcher::with_pattern(
+        r"(?P<kind>TODO|XXX)\((?P<owner>[^)]*)\)(?:\s*\[(?P<issue>[A-Z]+-\d+)\])?:\s*(?P<desc>.*)",
+        &default_kinds(),
+    )
+    .unwrap();
+
+    let line_match = matcher
+        .match_line("// TODO(bob) [ABC-12]: split this up")
+        .unwrap();
+    assert_eq!(line_match.kind.as_deref(), Some("TODO"));
+    assert_eq!(line_match.severity, Some(Severity::Warning));
+    assert_eq!(line_match.owner.as_deref(), Some("bob"));
+    assert_eq!(line_match.issue.as_deref(), Some("ABC-12"));
+    assert_eq!(line_match.description, "split this up");
+
+    // A kind group that matches no configured kind names the kind itself
+    let line_match = matcher.match_line("# XXX(): odd").unwrap();
+    assert_eq!(line_match.kind.as_deref(), Some("XXX"));
+    assert_eq!(line_match.severity, None);
+    assert_eq!(line_match.owner, None);
+    assert_eq!(line_match.issue, None);
+}
+
+#[test]
+fn test_description_fallbacks() {
+    // Without a desc group, the first unnamed group is the description
+    let matcher =
+        Matcher::with_pattern(r"TODO\((?P<owner>\w+)\):\s*(.*)", &default_kinds()).unwrap();
+    let line_match = matcher.match_line("// TODO(amy): rename").unwrap();
+    assert_eq!(line_match.description, "rename");
+    assert_eq!(line_match.owner.as_deref(), Some("amy"));
+
+    // Without any unnamed group, the whole line is the description
+    let matcher = Matcher::with_pattern(r"TODO\((?P<owner>\w+)\)", &default_kinds()).unwrap();
+    let line_match = matcher.match_line("  // TODO(amy) rename  ").unwrap();
+    assert_eq!(line_match.description, "// TODO(amy) rename");
+}
+
+#[test]
+fn test_unknown_capture_group() {
+    let error = compile_pattern(r"TODO\((?P<assignee>\w+)\)")
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Unknown capture group 'assignee'"));
+    assert!(Matcher::with_pattern(r"(?P<who>\w+)", &default_kinds()).is_err());
+    assert!(compile_pattern(r"(?P<kind>TODO)(?P<desc>.*)").is_ok());
+}
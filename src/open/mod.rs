@@ -0,0 +1,185 @@
+// src/open.rs
+// Handles the open command for codemarks
+
+use anyhow::{Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config_layers::ConfigOverrides;
+use crate::list::{ListFilter, SortKey, Status, current_timestamp, select_codemarks};
+use crate::query::Query;
+use crate::{Codemark, ProjectsDatabase, load_global_config, load_global_projects};
+
+/// Most annotations listed when a target is ambiguous
+const MAX_CANDIDATES: usize = 10;
+
+/// Opens the open annotation identified by `target` in the configured editor
+pub fn open_codemark(target: &str) -> Result<()> {
+    let layered = load_global_config(Path::new("."), &ConfigOverrides::default(), false)?;
+    let projects_db = load_global_projects(false);
+    let filter = ListFilter {
+        status: Some(Status::Open),
+        ..Default::default()
+    };
+    let (candidates, _) = select_codemarks(
+        &projects_db,
+        &filter,
+        None,
+        SortKey::Project,
+        current_timestamp(),
+    );
+
+    let matches = find_codemarks(target, &candidates)?;
+    let (project, codemark) = match matches.as_slice() {
+        [] => bail!("No open annotation matches '{target}'"),
+        [single] => *single,
+        _ => {
+            let mut message = format!(
+                "'{target}' matches {} annotations, open one by its id:",
+                matches.len()
+            );
+            for (project, codemark) in matches.iter().take(MAX_CANDIDATES) {
+                message.push_str(&format!(
+                    "\n  {} {project}: {} {}",
+                    codemark.id(project),
+                    codemark.location(),
+                    codemark.description
+                ));
+            }
+            bail!(message);
+        }
+    };
+
+    let (path, line) = codemark_path(&projects_db, project, codemark)?;
+    launch_editor(layered.config.editor.as_deref(), &path, line)
+}
+
+/// Resolves the absolute path of an annotation and the line to open it at
+pub fn codemark_path(
+    projects_db: &ProjectsDatabase,
+    project: &str,
+    codemark: &Codemark,
+) -> Result<(PathBuf, usize)> {
+    let root = projects_db.roots.get(project).ok_or_else(|| {
+        anyhow!("The location of project '{project}' is unknown, scan it again to record it")
+    })?;
+    let path = root.join(&codemark.file);
+    if !path.exists() {
+        bail!("{} does not exist anymore", path.display());
+    }
+    // Lines inside notebook cells are not lines of the file
+    Ok((path, codemark.file_line().unwrap_or(1)))
+}
+
+/// Opens a file at a line in the editor, using the `editor` template when configured
+pub fn launch_editor(template: Option<&str>, path: &Path, line: usize) -> Result<()> {
+    run_editor(&editor_command(template, &user_editor(), path, line))
+}
+
+/// Opens a file in the editor without a line, e.g. to edit a configuration file
+pub fn edit_file(path: &Path) -> Result<()> {
+    let mut command: Vec<String> = user_editor()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    command.push(path.to_string_lossy().to_string());
+    run_editor(&command)
+}
+
+/// The editor of the user: `$VISUAL`, then `$EDITOR`, then `vi`
+fn user_editor() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string())
+}
+
+/// Runs an editor command and waits for the editor to exit
+fn run_editor(command: &[String]) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("No editor configured, set $VISUAL or $EDITOR"))?;
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| anyhow!("Failed to start editor '{program}': {e}"))?;
+    if !status.success() {
+        bail!("Editor '{program}' exited with {status}");
+    }
+    Ok(())
+}
+
+/// Finds the annotations identified by a target: an id or id prefix of at least four
+/// characters, a location as shown by `list`, or else a query
+fn find_codemarks<'a>(
+    target: &str,
+    candidates: &[(&'a str, &'a Codemark)],
+) -> Result<Vec<(&'a str, &'a Codemark)>> {
+    let select = |predicate: &dyn Fn(&str, &Codemark) -> bool| -> Vec<(&'a str, &'a Codemark)> {
+        candidates
+            .iter()
+            .filter(|(project, codemark)| predicate(project, codemark))
+            .copied()
+            .collect()
+    };
+
+    if target.len() >= 4 && target.chars().all(|c| c.is_ascii_hexdigit()) {
+        let target = target.to_ascii_lowercase();
+        let matches = select(&|project, codemark| codemark.id(project).starts_with(&target));
+        if !matches.is_empty() {
+            return Ok(matches);
+        }
+    }
+    let location = target.strip_prefix("./").unwrap_or(target);
+    let matches = select(&|_, codemark| codemark.location().trim_start_matches("./") == location);
+    if !matches.is_empty() {
+        return Ok(matches);
+    }
+    let query: Query = target.parse()?;
+    let now = current_timestamp();
+    Ok(select(&|project, codemark| {
+        query.matches(project, codemark, now)
+    }))
+}
+
+/// Builds the command that opens a file at a line. A template replaces `{file}` and
+/// `{line}` in its words, and gets the file appended when it has no `{file}`. Without
+/// one, the arguments follow the conventions of the editor.
+fn editor_command(template: Option<&str>, editor: &str, path: &Path, line: usize) -> Vec<String> {
+    let file = path.to_string_lossy();
+    let line = line.to_string();
+    if let Some(template) = template {
+        let mut command: Vec<String> = template
+            .split_whitespace()
+            .map(|word| word.replace("{file}", &file).replace("{line}", &line))
+            .collect();
+        if !template.contains("{file}") {
+            command.push(file.to_string());
+        }
+        return command;
+    }
+
+    let mut command: Vec<String> = editor.split_whitespace().map(str::to_string).collect();
+    let program = command
+        .first()
+        .and_then(|program| Path::new(program).file_stem())
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match program.as_str() {
+        "vi" | "vim" | "nvim" | "gvim" | "mvim" | "nano" | "pico" | "micro" | "emacs"
+        | "emacsclient" | "kak" | "joe" | "mg" => {
+            command.push(format!("+{line}"));
+            command.push(file.to_string());
+        }
+        "code" | "code-insiders" | "codium" | "cursor" | "windsurf" => {
+            command.push("--goto".to_string());
+            command.push(format!("{file}:{line}"));
+        }
+        "hx" | "helix" | "subl" | "zed" | "mate" => command.push(format!("{file}:{line}")),
+        // Editors without a known line syntax still get the file
+        _ => command.push(file.to_string()),
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,125 @@
+use super::*;
+
+fn codemarks() -> Vec<Codemark> {
+    vec![
+        Codemark {
+            file: "src/db.rs".to_string(),
+            line_number: 3,
+            description: "add an index".to_string(),
+            kind: Some("TODO".to_string()),
+            ..Default::default()
+        },
+        Codemark {
+            file: "./src/db.rs".to_string(),
+            line_number: 9,
+            description: "retry on timeout".to_string(),
+            kind: Some("FIXME".to_string()),
+            ..Default::default()
+        },
+    ]
+}
+
+fn found(target: &str, codemarks: &[Codemark]) -> Vec<String> {
+    let candidates: Vec<(&str, &Codemark)> =
+        codemarks.iter().map(|codemark| ("api", codemark)).collect();
+    find_codemarks(target, &candidates)
+        .unwrap()
+        .iter()
+        .map(|(_, codemark)| codemark.description.clone())
+        .collect()
+}
+
+#[test]
+fn test_codemark_id() {
+    let codemarks = codemarks();
+    let id = codemarks[0].id("api");
+    assert_eq!(id.len(), 7);
+    assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    // Ids are stable, also when the annotation moves
+    let moved = Codemark {
+        line_number: 30,
+        ..codemarks[0].clone()
+    };
+    assert_eq!(moved.id("api"), id);
+    assert_ne!(codemarks[0].id("web"), id);
+    assert_ne!(codemarks[1].id("api"), id);
+}
+
+#[test]
+fn test_find_codemarks() {
+    let codemarks = codemarks();
+    let id = codemarks[1].id("api");
+
+    assert_eq!(found(&id, &codemarks), ["retry on timeout"]);
+    assert_eq!(
+        found(&id[..4].to_uppercase(), &codemarks),
+        ["retry on timeout"]
+    );
+    assert_eq!(found("src/db.rs:3", &codemarks), ["add an index"]);
+    assert_eq!(found("./src/db.rs:9", &codemarks), ["retry on timeout"]);
+    assert_eq!(found("kind:FIXME", &codemarks), ["retry on timeout"]);
+    assert_eq!(found("path:src/*", &codemarks), ["add an index"]);
+    assert_eq!(found("db", &codemarks).len(), 0);
+    assert_eq!(found("project:api", &codemarks).len(), 2);
+    assert!(find_codemarks("color:red", &[]).is_err());
+}
+
+#[test]
+fn test_editor_command() {
+    let path = Path::new("/work/src/db.rs");
+    let command = |editor: &str| editor_command(None, editor, path, 12).join(" ");
+
+    assert_eq!(command("vim"), "vim +12 /work/src/db.rs");
+    assert_eq!(
+        command("/usr/bin/nvim -p"),
+        "/usr/bin/nvim -p +12 /work/src/db.rs"
+    );
+    assert_eq!(
+        command("emacsclient -t"),
+        "emacsclient -t +12 /work/src/db.rs"
+    );
+    assert_eq!(command("nano"), "nano +12 /work/src/db.rs");
+    assert_eq!(
+        command("code --wait"),
+        "code --wait --goto /work/src/db.rs:12"
+    );
+    assert_eq!(command("hx"), "hx /work/src/db.rs:12");
+    assert_eq!(command("gedit"), "gedit /work/src/db.rs");
+}
+
+#[test]
+fn test_editor_template() {
+    let path = Path::new("/work/src/db.rs");
+    assert_eq!(
+        editor_command(Some("idea --line {line} {file}"), "vim", path, 7),
+        ["idea", "--line", "7", "/work/src/db.rs"]
+    );
+    // The file is appended when the template does not place it
+    assert_eq!(
+        editor_command(Some("open -a TextEdit"), "vim", path, 7),
+        ["open", "-a", "TextEdit", "/work/src/db.rs"]
+    );
+}
+
+#[test]
+fn test_codemark_path_lines() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::write(dir.path().join("README.md"), "").unwrap();
+    std::fs::write(dir.path().join("notes.ipynb"), "").unwrap();
+    let mut projects_db = ProjectsDatabase::default();
+    projects_db
+        .roots
+        .insert("api".to_string(), dir.path().to_path_buf());
+    let codemark = |file: &str| Codemark {
+        file: file.to_string(),
+        line_number: 7,
+        cell: Some(2),
+        ..Default::default()
+    };
+
+    // Markdown code blocks keep the lines of the file, notebook cells open at the top
+    let (path, line) = codemark_path(&projects_db, "api", &codemark("README.md")).unwrap();
+    assert_eq!((path, line), (dir.path().join("README.md"), 7));
+    let (_, line) = codemark_path(&projects_db, "api", &codemark("notes.ipynb")).unwrap();
+    assert_eq!(line, 1);
+}
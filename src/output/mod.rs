@@ -0,0 +1,233 @@
+// src/output.rs
+// Machine readable output of codemarks, shared by list, scan and ci
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{self, Write};
+
+use crate::formats::{file_line, format_location};
+use crate::{AnnotationKind, Codemark, OutputFormat, Severity};
+
+/// Fields of a record, in the order of the CSV and TSV columns. New fields are only
+/// ever added at the end, so scripts can rely on the existing ones.
+pub const COLUMNS: &[&str] = &[
+    "project",
+    "file",
+    "line",
+    "cell",
+    "kind",
+    "status",
+    "description",
+    "owner",
+    "issue",
+    "author",
+    "created_at",
+    "resolved_at",
+    "severity",
+    "column",
+];
+
+/// A codemark as it appears in machine readable output
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Record<'a> {
+    pub project: &'a str,
+    pub file: &'a str,
+    pub line: usize,
+    /// Notebook cell or Markdown code block containing the annotation (1-based)
+    pub cell: Option<usize>,
+    pub kind: Option<&'a str>,
+    /// Either `open` or `resolved`
+    pub status: &'static str,
+    pub description: &'a str,
+    pub owner: Option<&'a str>,
+    pub issue: Option<&'a str>,
+    pub author: Option<&'a str>,
+    /// Unix timestamp of when the annotation was introduced
+    pub created_at: Option<i64>,
+    /// Unix timestamp of when the annotation was resolved
+    pub resolved_at: Option<i64>,
+    /// Severity of the kind of the annotation, when the kind is configured
+    pub severity: Option<Severity>,
+    /// 1-based column where the annotation comment starts
+    pub column: Option<usize>,
+}
+
+impl<'a> Record<'a> {
+    #[must_use]
+    pub fn new(project: &'a str, codemark: &'a Codemark, kinds: &[AnnotationKind]) -> Self {
+        Self {
+            project,
+            file: &codemark.file,
+            line: codemark.line_number,
+            cell: codemark.cell,
+            kind: codemark.kind.as_deref(),
+            status: if codemark.resolved {
+                "resolved"
+            } else {
+                "open"
+            },
+            description: &codemark.description,
+            owner: codemark.owner.as_deref(),
+            issue: codemark.issue.as_deref(),
+            author: codemark.author.as_deref(),
+            created_at: codemark.created_at,
+            resolved_at: codemark.resolved_at,
+            severity: AnnotationKind::find(kinds, codemark.kind.as_deref())
+                .map(|kind| kind.severity),
+            column: codemark.column,
+        }
+    }
+
+    /// Returns the values of the record in the order of `COLUMNS`, empty when absent
+    fn values(&self) -> Vec<String> {
+        let text = |value: Option<&str>| value.unwrap_or_default().to_string();
+        let number = |value: Option<i64>| value.map(|value| value.to_string()).unwrap_or_default();
+        vec![
+            self.project.to_string(),
+            self.file.to_string(),
+            self.line.to_string(),
+            self.cell.map(|cell| cell.to_string()).unwrap_or_default(),
+            text(self.kind),
+            self.status.to_string(),
+            self.description.to_string(),
+            text(self.owner),
+            text(self.issue),
+            text(self.author),
+            number(self.created_at),
+            number(self.resolved_at),
+            self.severity
+                .map(|severity| severity.to_string())
+                .unwrap_or_default(),
+            self.column
+                .map(|column| column.to_string())
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+impl OutputFormat {
+    /// Whether the format is meant for scripts rather than people
+    #[must_use]
+    pub fn is_machine_readable(self) -> bool {
+        self != Self::Text
+    }
+
+    /// Whether the format is a compiler diagnostic, whose paths editors resolve against
+    /// the current directory
+    #[must_use]
+    pub fn is_diagnostic(self) -> bool {
+        matches!(self, Self::Quickfix | Self::Gcc | Self::Msvc)
+    }
+}
+
+/// Writes records in the given format. JSON is written as a single array, so an empty
+/// result is still valid JSON; CSV and TSV always start with a header row.
+pub fn write_records(format: OutputFormat, records: &[Record], out: &mut impl Write) -> Result<()> {
+    match format {
+        OutputFormat::Text => {
+            for record in records {
+                let location = format_location(record.file, record.line, record.cell);
+                writeln!(
+                    out,
+                    "{}: {location}: {}",
+                    record.project, record.description
+                )?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "{}", COLUMNS.join(","))?;
+            for record in records {
+                let values: Vec<String> = record.values().iter().map(|v| csv_field(v)).collect();
+                writeln!(out, "{}", values.join(","))?;
+            }
+        }
+        OutputFormat::Tsv => {
+            writeln!(out, "{}", COLUMNS.join("\t"))?;
+            for record in records {
+                let values: Vec<String> = record.values().iter().map(|v| tsv_field(v)).collect();
+                writeln!(out, "{}", values.join("\t"))?;
+            }
+        }
+        OutputFormat::Quickfix | OutputFormat::Gcc | OutputFormat::Msvc => {
+            for record in records {
+                writeln!(out, "{}", diagnostic(format, record))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders a record as a compiler diagnostic, so editors and problem matchers can load
+/// it. Annotations without a known kind are reported as warnings. Lines inside notebook
+/// cells are not lines of the file, so those point at the start of the file and name the
+/// cell in the message.
+fn diagnostic(format: OutputFormat, record: &Record) -> String {
+    let in_notebook_cell = file_line(record.file, record.line, record.cell).is_none();
+    let (line, column, message) = match record.cell {
+        Some(cell) if in_notebook_cell => (
+            1,
+            1,
+            format!("{} (cell {cell}, line {})", record.description, record.line),
+        ),
+        _ => (
+            record.line,
+            record.column.unwrap_or(1),
+            record.description.to_string(),
+        ),
+    };
+    let severity = record.severity.unwrap_or_default();
+    let file = record.file;
+    match (format, record.kind) {
+        (OutputFormat::Gcc, kind) => {
+            // GCC and Clang call informational diagnostics notes
+            let severity = match severity {
+                Severity::Info => "note".to_string(),
+                severity => severity.to_string(),
+            };
+            let kind = kind.map(|kind| format!(" [{kind}]")).unwrap_or_default();
+            format!("{file}:{line}:{column}: {severity}: {message}{kind}")
+        }
+        (OutputFormat::Msvc, Some(kind)) => {
+            format!("{file}({line},{column}): {severity} {kind}: {message}")
+        }
+        (OutputFormat::Msvc, None) => format!("{file}({line},{column}): {severity}: {message}"),
+        (_, Some(kind)) => format!("{file}:{line}:{column}: {severity}: {kind}: {message}"),
+        (_, None) => format!("{file}:{line}:{column}: {severity}: {message}"),
+    }
+}
+
+/// Prints records to standard output in the given format
+pub fn print_records(format: OutputFormat, records: &[Record]) -> Result<()> {
+    write_records(format, records, &mut io::stdout().lock())
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes tabs, line breaks and backslashes in a TSV field
+fn tsv_field(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests;
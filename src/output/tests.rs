@@ -0,0 +1,187 @@
+use super::*;
+use crate::default_kinds;
+
+fn codemarks() -> Vec<Codemark> {
+    vec![
+        Codemark {
+            file: "src/lib.rs".to_string(),
+            line_number: 3,
+            column: Some(5),
+            description: "handle \"quoted\", commas".to_string(),
+            kind: Some("TODO".to_string()),
+            owner: Some("alice".to_string()),
+            created_at: Some(100),
+            ..Default::default()
+        },
+        Codemark {
+            file: "notes.ipynb".to_string(),
+            line_number: 2,
+            cell: Some(4),
+            description: "tab\there".to_string(),
+            resolved: true,
+            resolved_at: Some(200),
+            ..Default::default()
+        },
+    ]
+}
+
+fn render(format: OutputFormat) -> String {
+    let codemarks = codemarks();
+    let records: Vec<Record> = codemarks
+        .iter()
+        .map(|codemark| Record::new("api", codemark, &default_kinds()))
+        .collect();
+    let mut out = Vec::new();
+    write_records(format, &records, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_json() {
+    let value: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+    assert_eq!(value[0]["project"], "api");
+    assert_eq!(value[0]["file"], "src/lib.rs");
+    assert_eq!(value[0]["line"], 3);
+    assert_eq!(value[0]["kind"], "TODO");
+    assert_eq!(value[0]["status"], "open");
+    assert_eq!(value[0]["owner"], "alice");
+    assert_eq!(value[0]["created_at"], 100);
+    // Absent fields are kept as null, so every record has the same keys
+    assert!(value[0]["cell"].is_null());
+    assert_eq!(value[1]["status"], "resolved");
+    assert_eq!(value[1]["cell"], 4);
+    assert_eq!(value[1].as_object().unwrap().len(), COLUMNS.len());
+}
+
+#[test]
+fn test_ndjson() {
+    let output = render(OutputFormat::Ndjson);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let record: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+    assert_eq!(record["description"], "tab\there");
+}
+
+#[test]
+fn test_csv() {
+    let output = render(OutputFormat::Csv);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[0],
+        "project,file,line,cell,kind,status,description,owner,issue,author,created_at,resolved_at,severity,column"
+    );
+    assert_eq!(
+        lines[1],
+        r#"api,src/lib.rs,3,,TODO,open,"handle ""quoted"", commas",alice,,,100,,warning,5"#
+    );
+    assert_eq!(
+        lines[2],
+        "api,notes.ipynb,2,4,,resolved,tab\there,,,,,200,,"
+    );
+}
+
+#[test]
+fn test_tsv() {
+    let output = render(OutputFormat::Tsv);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], COLUMNS.join("\t"));
+    assert_eq!(lines[2].split('\t').nth(6), Some(r"tab\there"));
+}
+
+#[test]
+fn test_empty_output() {
+    let mut out = Vec::new();
+    write_records(OutputFormat::Json, &[], &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "[]\n");
+}
+
+#[test]
+fn test_diagnostics() {
+    let output = render(OutputFormat::Quickfix);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[0],
+        r#"src/lib.rs:3:5: warning: TODO: handle "quoted", commas"#
+    );
+    // Cells are not lines of the file, and annotations without a kind are warnings
+    assert_eq!(
+        lines[1],
+        "notes.ipynb:1:1: warning: tab\there (cell 4, line 2)"
+    );
+
+    let output = render(OutputFormat::Gcc);
+    assert_eq!(
+        output.lines().next(),
+        Some(r#"src/lib.rs:3:5: warning: handle "quoted", commas [TODO]"#)
+    );
+    let output = render(OutputFormat::Msvc);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[0],
+        r#"src/lib.rs(3,5): warning TODO: handle "quoted", commas"#
+    );
+    assert_eq!(
+        lines[1],
+        "notes.ipynb(1,1): warning: tab\there (cell 4, line 2)"
+    );
+}
+
+#[test]
+fn test_diagnostic_in_markdown_code_block() {
+    // Code blocks keep the lines of the file, unlike notebook cells
+    let codemark = Codemark {
+        file: "web/README.md".to_string(),
+        line_number: 7,
+        cell: Some(1),
+        column: Some(4),
+        description: "in block".to_string(),
+        kind: Some("TODO".to_string()),
+        ..Default::default()
+    };
+    let record = Record::new("web", &codemark, &default_kinds());
+    assert_eq!(
+        diagnostic(OutputFormat::Quickfix, &record),
+        "web/README.md:7:4: warning: TODO: in block"
+    );
+}
+
+#[test]
+fn test_diagnostic_severity() {
+    let mut kinds = default_kinds();
+    kinds.push(AnnotationKind::new("NOTE", Severity::Info, "blue"));
+    let codemarks = [
+        Codemark {
+            file: "a.rs".to_string(),
+            line_number: 1,
+            column: Some(1),
+            kind: Some("fixme".to_string()),
+            description: "crash".to_string(),
+            ..Default::default()
+        },
+        Codemark {
+            file: "a.rs".to_string(),
+            line_number: 2,
+            kind: Some("NOTE".to_string()),
+            description: "why".to_string(),
+            ..Default::default()
+        },
+    ];
+    let records: Vec<Record> = codemarks
+        .iter()
+        .map(|codemark| Record::new("api", codemark, &kinds))
+        .collect();
+    let render = |format| {
+        let mut out = Vec::new();
+        write_records(format, &records, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(
+        render(OutputFormat::Quickfix),
+        "a.rs:1:1: error: fixme: crash\na.rs:2:1: info: NOTE: why\n"
+    );
+    // GCC calls informational diagnostics notes
+    assert_eq!(
+        render(OutputFormat::Gcc),
+        "a.rs:1:1: error: crash [fixme]\na.rs:2:1: note: why [NOTE]\n"
+    );
+}
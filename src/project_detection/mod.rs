@@ -1,5 +1,78 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+
+/// Configuration files that mark the root of a project
+const PROJECT_MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "build.sbt",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "mix.exs",
+    "pyproject.toml",
+    "setup.py",
+];
+
+/// Checks whether a directory contains one of the known project configuration files
+pub fn is_project_root(directory: &Path) -> bool {
+    PROJECT_MANIFESTS
+        .iter()
+        .any(|manifest| directory.join(manifest).is_file())
+}
+
+/// Finds the nearest project root enclosing `path`, stopping at `top`.
+/// Returns `top` itself when no nested project root is found.
+pub fn find_project_root(path: &Path, top: &Path) -> std::path::PathBuf {
+    path.ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.starts_with(top) && *ancestor != top)
+        .find(|ancestor| is_project_root(ancestor))
+        .unwrap_or(top)
+        .to_path_buf()
+}
+
+/// Picks the name under which the project at `root` is stored, given the roots already
+/// known by name. A project keeps the name it was stored under before; a `detected` name
+/// already claimed by a different root gets the trailing components of `root` appended,
+/// e.g. `app (services/app)`, so the two projects are not merged.
+pub fn project_name_for_root(
+    roots: &HashMap<String, PathBuf>,
+    root: &Path,
+    detected: String,
+) -> String {
+    if roots.get(&detected).is_none_or(|known| known == root) {
+        return detected;
+    }
+    if let Some(name) = roots
+        .iter()
+        .filter(|(_, known)| *known == root)
+        .map(|(name, _)| name)
+        .min()
+    {
+        return name.clone();
+    }
+    let components: Vec<_> = root
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    for count in 1..=components.len() {
+        let suffix = components[components.len() - count..].join("/");
+        if suffix == detected {
+            continue;
+        }
+        let name = format!("{detected} ({suffix})");
+        if roots.get(&name).is_none_or(|known| known == root) {
+            return name;
+        }
+    }
+    format!("{detected} ({})", root.display())
+}
 
 /// Intelligently determine the project name based on language-specific configuration files
 pub fn detect_project_name(directory: &Path) -> String {
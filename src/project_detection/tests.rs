@@ -268,3 +268,60 @@ fn test_detect_project_name_invalid_directory() {
     let project_name = detect_project_name(non_existent_path);
     assert_eq!(project_name, "exist");
 }
+
+#[test]
+fn test_is_project_root() {
+    let temp_dir = setup_temp_dir();
+    assert!(!is_project_root(temp_dir.path()));
+
+    std::fs::write(temp_dir.path().join("go.mod"), "module example.com/app\n").unwrap();
+    assert!(is_project_root(temp_dir.path()));
+}
+
+#[test]
+fn test_find_project_root_nested() {
+    let temp_dir = setup_temp_dir();
+    let service_dir = temp_dir.path().join("services").join("a");
+    let source_dir = service_dir.join("src");
+    std::fs::create_dir_all(&source_dir).unwrap();
+    std::fs::write(
+        service_dir.join("Cargo.toml"),
+        "[package]\nname = \"service-a\"\n",
+    )
+    .unwrap();
+    let other_dir = temp_dir.path().join("docs");
+    std::fs::create_dir_all(&other_dir).unwrap();
+
+    let nested = find_project_root(&source_dir.join("main.rs"), temp_dir.path());
+    assert_eq!(nested, service_dir);
+
+    let top = find_project_root(&other_dir.join("guide.md"), temp_dir.path());
+    assert_eq!(top, temp_dir.path());
+}
+
+#[test]
+fn test_project_name_for_root_keeps_colliding_projects_apart() {
+    let mut roots = HashMap::new();
+    let first = Path::new("/work/services/app");
+    let second = Path::new("/work/web/app");
+
+    assert_eq!(
+        project_name_for_root(&roots, first, "app".to_string()),
+        "app"
+    );
+    roots.insert("app".to_string(), first.to_path_buf());
+    assert_eq!(
+        project_name_for_root(&roots, first, "app".to_string()),
+        "app"
+    );
+
+    // A second project with the same manifest name gets a path suffix
+    let name = project_name_for_root(&roots, second, "app".to_string());
+    assert_eq!(name, "app (web/app)");
+    roots.insert(name, second.to_path_buf());
+    // and keeps it on later scans
+    assert_eq!(
+        project_name_for_root(&roots, second, "app".to_string()),
+        "app (web/app)"
+    );
+}
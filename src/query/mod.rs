@@ -0,0 +1,444 @@
+// src/query.rs
+// Parses and evaluates queries such as `kind:FIXME owner:alice age>30d -resolved`
+
+use anyhow::Result;
+use globset::{Glob, GlobMatcher};
+use std::str::FromStr;
+
+use crate::Codemark;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Fields that can be used in a predicate, e.g. `kind:FIXME` or `age>30d`
+pub const FIELDS: &[&str] = &[
+    "kind", "owner", "issue", "author", "project", "path", "desc", "status", "age", "created",
+    "resolved", "line",
+];
+
+/// A comparison operator of a predicate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, left: i64, right: i64) -> bool {
+        match self {
+            Self::Less => left < right,
+            Self::LessOrEqual => left <= right,
+            Self::Equal => left == right,
+            Self::GreaterOrEqual => left >= right,
+            Self::Greater => left > right,
+        }
+    }
+}
+
+/// A single condition on a codemark
+#[derive(Debug, Clone)]
+enum Predicate {
+    /// The description contains the text, case insensitive
+    Text(String),
+    Kind(String),
+    Owner(String),
+    Issue(String),
+    /// The author name or email contains the text, case insensitive
+    Author(String),
+    Project(String),
+    Path(GlobMatcher),
+    Resolved(bool),
+    /// Age in seconds, from the creation date or else the date of the last commit
+    Age(Comparison, i64),
+    /// Day of creation, counted from the Unix epoch
+    Created(Comparison, i64),
+    /// Day of resolution, counted from the Unix epoch
+    ResolvedOn(Comparison, i64),
+    Line(Comparison, i64),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Predicate(Predicate),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A parsed query. Predicates next to each other must all hold, `OR` combines
+/// alternatives, and `-` or `NOT` negates a predicate or a parenthesized group.
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            anyhow::bail!("Query is empty");
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            anyhow::bail!("Unexpected {token} in query");
+        }
+        Ok(Self { expr })
+    }
+}
+
+impl Query {
+    /// Checks whether a codemark of a project matches the query at the given time
+    #[must_use]
+    pub fn matches(&self, project: &str, codemark: &Codemark, now: i64) -> bool {
+        evaluate(&self.expr, project, codemark, now)
+    }
+
+    /// Whether the query compares the age of annotations, which needs their dates
+    #[must_use]
+    pub fn uses_age(&self) -> bool {
+        fn any_age(expr: &Expr) -> bool {
+            match expr {
+                Expr::Predicate(predicate) => matches!(predicate, Predicate::Age(..)),
+                Expr::Not(expr) => any_age(expr),
+                Expr::And(left, right) | Expr::Or(left, right) => any_age(left) || any_age(right),
+            }
+        }
+        any_age(&self.expr)
+    }
+}
+
+fn evaluate(expr: &Expr, project: &str, codemark: &Codemark, now: i64) -> bool {
+    match expr {
+        Expr::Predicate(predicate) => predicate.holds(project, codemark, now),
+        Expr::Not(expr) => !evaluate(expr, project, codemark, now),
+        Expr::And(left, right) => {
+            evaluate(left, project, codemark, now) && evaluate(right, project, codemark, now)
+        }
+        Expr::Or(left, right) => {
+            evaluate(left, project, codemark, now) || evaluate(right, project, codemark, now)
+        }
+    }
+}
+
+impl Predicate {
+    fn holds(&self, project: &str, codemark: &Codemark, now: i64) -> bool {
+        let equals = |value: Option<&String>, expected: &str| {
+            value.is_some_and(|value| value.eq_ignore_ascii_case(expected))
+        };
+        match self {
+            Self::Text(text) => codemark.description.to_lowercase().contains(text),
+            Self::Kind(kind) => equals(codemark.kind.as_ref(), kind),
+            Self::Owner(owner) => equals(codemark.owner.as_ref(), owner),
+            Self::Issue(issue) => equals(codemark.issue.as_ref(), issue),
+            Self::Author(author) => codemark
+                .author
+                .iter()
+                .chain(codemark.author_email.iter())
+                .any(|value| value.to_lowercase().contains(author)),
+            Self::Project(name) => project.eq_ignore_ascii_case(name),
+            Self::Path(glob) => glob.is_match(&codemark.file),
+            Self::Resolved(resolved) => codemark.resolved == *resolved,
+            Self::Age(comparison, seconds) => codemark
+                .introduced_at()
+                .is_some_and(|date| comparison.holds(now - date, *seconds)),
+            Self::Created(comparison, day) => codemark
+                .created_at
+                .is_some_and(|date| comparison.holds(date.div_euclid(SECONDS_PER_DAY), *day)),
+            Self::ResolvedOn(comparison, day) => codemark
+                .resolved_at
+                .is_some_and(|date| comparison.holds(date.div_euclid(SECONDS_PER_DAY), *day)),
+            Self::Line(comparison, line) => comparison.holds(
+                i64::try_from(codemark.line_number).unwrap_or(i64::MAX),
+                *line,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Word { text: String, quoted: bool },
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Open => write!(f, "'('"),
+            Self::Close => write!(f, "')'"),
+            Self::Word { text, .. } => write!(f, "'{text}'"),
+        }
+    }
+}
+
+/// Splits a query into words and parentheses. Double quotes group words with spaces,
+/// also in values such as `desc:"add an index"`.
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else {
+            let mut text = String::new();
+            let mut quoted = false;
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                chars.next();
+                if c == '"' {
+                    // Only a word that starts with a quote is searched for as a whole
+                    quoted |= text.is_empty();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => text.push(c),
+                            None => anyhow::bail!("Unterminated quote in query"),
+                        }
+                    }
+                } else {
+                    text.push(c);
+                }
+            }
+            tokens.push(Token::Word { text, quoted });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word { text, quoted: false }) if text == keyword)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek_keyword("OR") {
+            self.position += 1;
+            let right = self.parse_and()?;
+            expr = Expr::Or(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            if self.peek_keyword("AND") {
+                self.position += 1;
+            } else if self.peek().is_none_or(|token| *token == Token::Close)
+                || self.peek_keyword("OR")
+            {
+                return Ok(expr);
+            }
+            let right = self.parse_unary()?;
+            expr = Expr::And(Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let Some(token) = self.peek().cloned() else {
+            anyhow::bail!("Query ends unexpectedly");
+        };
+        self.position += 1;
+        match token {
+            Token::Open => {
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    anyhow::bail!("Missing ')' in query");
+                }
+                self.position += 1;
+                Ok(expr)
+            }
+            Token::Close => anyhow::bail!("Unexpected ')' in query"),
+            Token::Word { text, quoted: true } => {
+                Ok(Expr::Predicate(Predicate::Text(text.to_lowercase())))
+            }
+            Token::Word { text, .. } if text == "NOT" => {
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Token::Word { text, .. } if text == "AND" || text == "OR" => {
+                anyhow::bail!("Expected a predicate before '{text}'")
+            }
+            Token::Word { text, .. } => {
+                if let Some(rest) = text.strip_prefix('-') {
+                    // A lone `-` negates the following group, e.g. `-(kind:TODO owner:bob)`
+                    if rest.is_empty() {
+                        return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+                    }
+                    return Ok(Expr::Not(Box::new(Expr::Predicate(parse_predicate(rest)?))));
+                }
+                Ok(Expr::Predicate(parse_predicate(&text)?))
+            }
+        }
+    }
+}
+
+/// Parses a single word into a predicate. Words without an operator search the
+/// description, except `open` and `resolved`, which select by status.
+fn parse_predicate(word: &str) -> Result<Predicate> {
+    let Some(split) = word.find([':', '<', '>', '=']) else {
+        return Ok(match word {
+            "open" => Predicate::Resolved(false),
+            "resolved" => Predicate::Resolved(true),
+            _ => Predicate::Text(word.to_lowercase()),
+        });
+    };
+    let field = &word[..split];
+    let (operator, value) = match &word[split..] {
+        rest if rest.starts_with(">=") => (">=", &rest[2..]),
+        rest if rest.starts_with("<=") => ("<=", &rest[2..]),
+        rest => rest.split_at(1),
+    };
+    if !FIELDS.contains(&field) {
+        anyhow::bail!(
+            "Unknown field '{field}' in query, expected one of: {}",
+            FIELDS.join(", ")
+        );
+    }
+    if value.is_empty() {
+        anyhow::bail!("Missing value for '{field}' in query");
+    }
+
+    let comparison = match operator {
+        "<" => Some(Comparison::Less),
+        "<=" => Some(Comparison::LessOrEqual),
+        ">=" => Some(Comparison::GreaterOrEqual),
+        ">" => Some(Comparison::Greater),
+        _ => None,
+    };
+    match (field, comparison) {
+        ("age", Some(comparison)) => Ok(Predicate::Age(comparison, parse_duration(value)?)),
+        ("created", Some(comparison)) => Ok(Predicate::Created(comparison, parse_date(value)?)),
+        ("resolved", Some(comparison)) => Ok(Predicate::ResolvedOn(comparison, parse_date(value)?)),
+        ("line", comparison) => {
+            let line = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid line number '{value}' in query"))?;
+            Ok(Predicate::Line(
+                comparison.unwrap_or(Comparison::Equal),
+                line,
+            ))
+        }
+        ("age" | "created" | "resolved", None) => {
+            anyhow::bail!("'{field}' needs a comparison such as {field}>{value}")
+        }
+        (_, Some(_)) => anyhow::bail!("'{field}' cannot be compared with '{operator}'"),
+        ("kind", None) => Ok(Predicate::Kind(value.to_string())),
+        ("owner", None) => Ok(Predicate::Owner(value.to_string())),
+        ("issue", None) => Ok(Predicate::Issue(value.to_string())),
+        ("author", None) => Ok(Predicate::Author(value.to_lowercase())),
+        ("project", None) => Ok(Predicate::Project(value.to_string())),
+        ("desc", None) => Ok(Predicate::Text(value.to_lowercase())),
+        ("path", None) => Glob::new(value)
+            .map(|glob| Predicate::Path(glob.compile_matcher()))
+            .map_err(|e| anyhow::anyhow!("Invalid path pattern '{value}': {e}")),
+        ("status", None) => match value {
+            "open" => Ok(Predicate::Resolved(false)),
+            "resolved" => Ok(Predicate::Resolved(true)),
+            _ => anyhow::bail!("Unknown status '{value}', expected open or resolved"),
+        },
+        _ => unreachable!("all fields are handled"),
+    }
+}
+
+/// Parses a duration such as `12h`, `30d`, `2w`, `6mo` or `1y` into seconds
+fn parse_duration(value: &str) -> Result<i64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let seconds = match unit {
+        "h" => 3_600,
+        "d" => SECONDS_PER_DAY,
+        "w" => 7 * SECONDS_PER_DAY,
+        "mo" => 30 * SECONDS_PER_DAY,
+        "y" => 365 * SECONDS_PER_DAY,
+        _ => anyhow::bail!("Invalid duration '{value}', expected e.g. 12h, 30d, 2w, 6mo or 1y"),
+    };
+    amount
+        .parse::<i64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(seconds))
+        .ok_or_else(|| anyhow::anyhow!("Invalid duration '{value}'"))
+}
+
+/// Parses a `YYYY-MM-DD` date into the number of days since the Unix epoch
+fn parse_date(value: &str) -> Result<i64> {
+    let invalid = || anyhow::anyhow!("Invalid date '{value}', expected YYYY-MM-DD");
+    let mut parts = value.splitn(3, '-');
+    let mut next = || -> Result<i64> {
+        parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let (year, month, day) = (next()?, next()?, next()?);
+    if !(0..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month, day))
+}
+
+/// Formats a Unix timestamp as a `YYYY-MM-DD` date in UTC
+pub fn format_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Conversions between days since the Unix epoch and proleptic Gregorian dates, see
+// http://howardhinnant.github.io/date_algorithms.html
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,149 @@
+use super::*;
+
+const NOW: i64 = 1_700_000_000;
+
+fn codemark() -> Codemark {
+    Codemark {
+        file: "src/db/query.rs".to_string(),
+        line_number: 42,
+        description: "Add an index".to_string(),
+        kind: Some("FIXME".to_string()),
+        owner: Some("alice".to_string()),
+        issue: Some("ABC-7".to_string()),
+        author: Some("Alice Smith".to_string()),
+        created_at: Some(NOW - 40 * SECONDS_PER_DAY),
+        ..Default::default()
+    }
+}
+
+fn matches(query: &str) -> bool {
+    query
+        .parse::<Query>()
+        .unwrap()
+        .matches("api", &codemark(), NOW)
+}
+
+#[test]
+fn test_field_predicates() {
+    assert!(matches(
+        "kind:fixme owner:alice age>30d path:src/** -resolved"
+    ));
+    assert!(matches("issue:ABC-7 author:smith project:api desc:index"));
+    assert!(matches("status:open line>=42 line<100"));
+    assert!(!matches("kind:TODO"));
+    assert!(!matches("owner:bob"));
+    assert!(!matches("path:tests/**"));
+    assert!(!matches("age>60d"));
+    assert!(!matches("resolved"));
+    assert!(!matches("line:41"));
+}
+
+#[test]
+fn test_age_uses_earliest_known_date() {
+    // Scanned today, but blamed to a commit from 90 days ago
+    let codemark = Codemark {
+        created_at: Some(NOW),
+        commit_date: Some(NOW - 90 * SECONDS_PER_DAY),
+        ..codemark()
+    };
+    let query: Query = "age>60d".parse().unwrap();
+    assert!(query.matches("api", &codemark, NOW));
+    let query: Query = "age<1d".parse().unwrap();
+    assert!(!query.matches("api", &codemark, NOW));
+
+    assert!(query.uses_age());
+    assert!(
+        "kind:TODO OR -(age>1y)"
+            .parse::<Query>()
+            .unwrap()
+            .uses_age()
+    );
+    assert!(
+        !"kind:TODO created>2024-01-01"
+            .parse::<Query>()
+            .unwrap()
+            .uses_age()
+    );
+}
+
+#[test]
+fn test_text_search() {
+    assert!(matches("INDEX"));
+    assert!(matches(r#""an index""#));
+    assert!(matches(r#"desc:"an index""#));
+    assert!(!matches(r#""index an""#));
+    // Quoted keywords are searched for rather than interpreted
+    assert!(!matches(r#"index "OR" cache"#));
+}
+
+#[test]
+fn test_boolean_operators() {
+    assert!(matches("kind:TODO OR owner:alice"));
+    assert!(matches("kind:FIXME AND owner:alice"));
+    assert!(!matches("kind:FIXME owner:bob"));
+    assert!(matches("NOT kind:TODO"));
+    assert!(matches("-(kind:TODO OR owner:bob)"));
+    assert!(!matches("-(kind:FIXME owner:alice)"));
+    // AND binds tighter than OR
+    assert!(matches("owner:bob kind:TODO OR project:api"));
+    assert!(!matches("owner:bob (kind:TODO OR project:api)"));
+}
+
+#[test]
+fn test_dates() {
+    assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+    assert_eq!(parse_date("2000-03-01").unwrap(), 11_017);
+    assert_eq!(parse_date("2023-11-14").unwrap(), NOW / SECONDS_PER_DAY);
+    assert_eq!(format_date(0), "1970-01-01");
+    assert_eq!(format_date(951_782_400), "2000-02-29");
+    assert_eq!(format_date(NOW), "2023-11-14");
+    // Years outside the four digit range are rejected instead of overflowing
+    assert!(parse_date("99999999999999999-01-01").is_err());
+    assert!(parse_date("10000-01-01").is_err());
+    assert!("created>99999999999999999-01-01".parse::<Query>().is_err());
+
+    // The codemark was created 40 days before NOW, on 2023-10-05
+    assert!(matches("created>=2023-10-05 created<2023-10-06"));
+    assert!(!matches("created>2023-10-05"));
+    let mut resolved = codemark();
+    resolved.resolved = true;
+    resolved.resolved_at = Some(NOW);
+    let query: Query = "resolved resolved>2023-11-01".parse().unwrap();
+    assert!(query.matches("api", &resolved, NOW));
+    assert!(!matches("resolved>2023-11-01"));
+}
+
+#[test]
+fn test_durations() {
+    assert_eq!(parse_duration("12h").unwrap(), 12 * 3_600);
+    assert_eq!(parse_duration("2w").unwrap(), 14 * SECONDS_PER_DAY);
+    assert_eq!(parse_duration("6mo").unwrap(), 180 * SECONDS_PER_DAY);
+    assert_eq!(parse_duration("1y").unwrap(), 365 * SECONDS_PER_DAY);
+    assert!(parse_duration("30").is_err());
+    assert!(parse_duration("d").is_err());
+    // Durations that do not fit are rejected instead of overflowing
+    assert!(parse_duration("999999999999999999y").is_err());
+    assert!("age>999999999999999999y".parse::<Query>().is_err());
+}
+
+#[test]
+fn test_invalid_queries() {
+    for query in [
+        "",
+        "color:red",
+        "kind:",
+        "age:30d",
+        "kind>FIXME",
+        "status:closed",
+        "created>yesterday",
+        "(kind:FIXME",
+        "kind:FIXME)",
+        "OR kind:FIXME",
+        "kind:FIXME AND",
+        r#""unterminated"#,
+    ] {
+        assert!(query.parse::<Query>().is_err(), "{query} should not parse");
+    }
+    let error = "color:red".parse::<Query>().unwrap_err().to_string();
+    assert!(error.contains("Unknown field 'color'"));
+}
@@ -0,0 +1,181 @@
+// src/repo_config.rs
+// Per-repository configuration read from .codemarks.toml
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::project_detection::is_project_root;
+use crate::{AnnotationKind, CodemarksConfig, Severity, default_annotation_pattern};
+
+pub const REPO_CONFIG_FILE: &str = ".codemarks.toml";
+
+/// Settings a repository can define in its `.codemarks.toml`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Regex pattern for annotations, overriding the global pattern
+    pub pattern: Option<String>,
+    /// Annotation kinds to match when no explicit pattern is set
+    pub kinds: Vec<KindSpec>,
+    /// Comment leaders per file extension or file name
+    pub comment_syntax: BTreeMap<String, Vec<String>>,
+    /// Patterns to ignore in addition to the ones given on the command line
+    pub ignore: Vec<String>,
+    /// Project name, overriding the name detected from project files
+    pub project: Option<String>,
+    /// Link to an annotation in reports, with `{file}` and `{line}` placeholders
+    pub repository_url: Option<String>,
+    pub ci: CiPolicy,
+    /// Named profiles, selected with `--profile` or `CODEMARKS_PROFILE`
+    pub profile: BTreeMap<String, Profile>,
+    /// Location of the file this configuration was loaded from
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// An annotation kind in the repository config, either just a keyword such as
+/// `"TODO"` or a full kind definition table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KindSpec {
+    Keyword(String),
+    Kind(AnnotationKind),
+}
+
+impl KindSpec {
+    /// Resolves the spec into a kind. Plain keywords reuse the definition of a known
+    /// kind with the same name, so they keep its severity and color.
+    fn resolve(&self, known: &[AnnotationKind]) -> AnnotationKind {
+        match self {
+            KindSpec::Kind(kind) => kind.clone(),
+            KindSpec::Keyword(name) => known
+                .iter()
+                .find(|kind| kind.name.eq_ignore_ascii_case(name))
+                .cloned()
+                .unwrap_or_else(|| AnnotationKind {
+                    name: name.clone(),
+                    keywords: Vec::new(),
+                    pattern: None,
+                    severity: Severity::default(),
+                    color: None,
+                }),
+        }
+    }
+}
+
+/// Controls when `codemarks ci` fails
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CiPolicy {
+    /// Number of annotations that are allowed before CI fails (defaults to 0)
+    pub max_annotations: Option<usize>,
+}
+
+/// Settings that a named profile overrides when it is selected
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub pattern: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<KindSpec>,
+    /// Patterns to ignore instead of the ones from the configuration files
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    pub ci: CiPolicy,
+}
+
+impl Profile {
+    /// Applies the pattern and kinds of the profile to a configuration
+    pub fn apply_to(&self, config: &mut CodemarksConfig) {
+        apply_pattern_and_kinds(self.pattern.as_ref(), &self.kinds, config);
+    }
+}
+
+/// Applies kinds and a pattern on top of a configuration. Kinds reset the pattern, so
+/// they decide what is matched unless a pattern is given as well.
+fn apply_pattern_and_kinds(
+    pattern: Option<&String>,
+    kinds: &[KindSpec],
+    config: &mut CodemarksConfig,
+) {
+    if !kinds.is_empty() {
+        config.kinds = kinds
+            .iter()
+            .map(|spec| spec.resolve(&config.kinds))
+            .collect();
+        config.annotation_pattern = default_annotation_pattern();
+    }
+    if let Some(pattern) = pattern {
+        config.annotation_pattern = pattern.clone();
+    }
+}
+
+impl RepoConfig {
+    /// Applies the repository settings on top of the global configuration
+    pub fn apply_to(&self, config: &mut CodemarksConfig) {
+        apply_pattern_and_kinds(self.pattern.as_ref(), &self.kinds, config);
+        config.comment_syntax.extend(
+            self.comment_syntax
+                .iter()
+                .map(|(key, leaders)| (key.clone(), leaders.clone())),
+        );
+        if self.repository_url.is_some() {
+            config.repository_url.clone_from(&self.repository_url);
+        }
+    }
+
+    /// Returns the configured project name if it applies to `directory`. The name belongs
+    /// to the directory holding the config file and to any directory below it that is
+    /// not a project root of its own.
+    pub fn project_name_for(&self, directory: &Path) -> Option<String> {
+        let project = self.project.as_ref()?;
+        let config_dir = self.path.as_ref()?.parent()?;
+        let directory = directory
+            .canonicalize()
+            .unwrap_or_else(|_| directory.to_path_buf());
+        if directory == config_dir || !is_project_root(&directory) {
+            Some(project.clone())
+        } else {
+            None
+        }
+    }
+}
+
+/// Searches for a `.codemarks.toml` from `start` upward to the root of the git
+/// repository containing it. Outside a repository only `start` itself is checked.
+pub fn find_repo_config(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+    let in_repository = start
+        .ancestors()
+        .any(|ancestor| ancestor.join(".git").exists());
+
+    for directory in start.ancestors() {
+        let candidate = directory.join(REPO_CONFIG_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if !in_repository || directory.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Loads the repository configuration that applies to a directory. Returns an empty
+/// configuration when there is no `.codemarks.toml`.
+pub fn load_repo_config(start: &Path) -> Result<RepoConfig> {
+    let Some(path) = find_repo_config(start) else {
+        return Ok(RepoConfig::default());
+    };
+    let content = fs::read_to_string(&path)?;
+    let mut config: RepoConfig =
+        toml::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid {}: {e}", path.display()))?;
+    config.path = Some(path);
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests;
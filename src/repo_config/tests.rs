@@ -0,0 +1,148 @@
+use super::*;
+use crate::default_annotation_pattern;
+use tempfile::tempdir;
+
+#[test]
+fn test_load_repo_config_missing() {
+    let temp_dir = tempdir().unwrap();
+    let config = load_repo_config(temp_dir.path()).unwrap();
+    assert!(config.path.is_none());
+    assert!(config.pattern.is_none());
+    assert!(config.ignore.is_empty());
+}
+
+#[test]
+fn test_load_repo_config() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        r#"
+project = "my-service"
+kinds = ["TODO", "XXX"]
+ignore = ["vendor/", "*.min.js"]
+
+[ci]
+max_annotations = 5
+"#,
+    )
+    .unwrap();
+
+    let config = load_repo_config(temp_dir.path()).unwrap();
+    assert_eq!(config.project.as_deref(), Some("my-service"));
+    assert_eq!(
+        config.kinds,
+        vec![
+            KindSpec::Keyword("TODO".to_string()),
+            KindSpec::Keyword("XXX".to_string())
+        ]
+    );
+    assert_eq!(config.ignore, vec!["vendor/", "*.min.js"]);
+    assert_eq!(config.ci.max_annotations, Some(5));
+    assert!(config.path.is_some());
+}
+
+#[test]
+fn test_load_repo_config_invalid() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        "unknown_setting = 1\n",
+    )
+    .unwrap();
+    assert!(load_repo_config(temp_dir.path()).is_err());
+}
+
+#[test]
+fn test_find_repo_config_stops_at_repository_root() {
+    let temp_dir = tempdir().unwrap();
+    let repo_dir = temp_dir.path().join("repo");
+    let nested_dir = repo_dir.join("src").join("module");
+    fs::create_dir_all(&nested_dir).unwrap();
+    fs::create_dir_all(repo_dir.join(".git")).unwrap();
+
+    // A config above the repository root is not picked up
+    fs::write(temp_dir.path().join(REPO_CONFIG_FILE), "").unwrap();
+    assert!(find_repo_config(&nested_dir).is_none());
+
+    fs::write(repo_dir.join(REPO_CONFIG_FILE), "").unwrap();
+    let found = find_repo_config(&nested_dir).unwrap();
+    assert_eq!(
+        found,
+        repo_dir.canonicalize().unwrap().join(REPO_CONFIG_FILE)
+    );
+}
+
+#[test]
+fn test_apply_to() {
+    let mut config = CodemarksConfig::default();
+    RepoConfig::default().apply_to(&mut config);
+    assert_eq!(config.annotation_pattern, default_annotation_pattern());
+
+    let repo_config = RepoConfig {
+        kinds: vec![KindSpec::Keyword("XXX".to_string())],
+        ..Default::default()
+    };
+    repo_config.apply_to(&mut config);
+    let regex = regex::Regex::new(&config.effective_pattern()).unwrap();
+    assert!(regex.is_match("// XXX: Custom keyword"));
+    assert!(!regex.is_match("// TODO: Not configured"));
+
+    let repo_config = RepoConfig {
+        pattern: Some("CUSTOM".to_string()),
+        kinds: vec![KindSpec::Keyword("XXX".to_string())],
+        ..Default::default()
+    };
+    repo_config.apply_to(&mut config);
+    assert_eq!(config.annotation_pattern, "CUSTOM");
+}
+
+#[test]
+fn test_project_name_for() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let source_dir = root.join("src");
+    let service_dir = root.join("service");
+    fs::create_dir_all(&source_dir).unwrap();
+    fs::create_dir_all(&service_dir).unwrap();
+    fs::write(service_dir.join("package.json"), r#"{"name": "service"}"#).unwrap();
+
+    let repo_config = RepoConfig {
+        project: Some("configured".to_string()),
+        path: Some(root.join(REPO_CONFIG_FILE)),
+        ..Default::default()
+    };
+    assert_eq!(
+        repo_config.project_name_for(&root).as_deref(),
+        Some("configured")
+    );
+    assert_eq!(
+        repo_config.project_name_for(&source_dir).as_deref(),
+        Some("configured")
+    );
+    assert_eq!(repo_config.project_name_for(&service_dir), None);
+}
+
+#[test]
+fn test_kind_specs() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(REPO_CONFIG_FILE),
+        r#"
+kinds = [
+    "FIXME",
+    { name = "SECURITY", keywords = ["SEC", "SECURITY"], severity = "error", color = "red" },
+]
+"#,
+    )
+    .unwrap();
+
+    let repo_config = load_repo_config(temp_dir.path()).unwrap();
+    let mut config = CodemarksConfig::default();
+    repo_config.apply_to(&mut config);
+
+    assert_eq!(config.kinds.len(), 2);
+    // Plain keywords keep the definition of the known kind
+    assert_eq!(config.kinds[0].severity, Severity::Error);
+    assert_eq!(config.kinds[1].name, "SECURITY");
+    assert_eq!(config.kinds[1].keywords, vec!["SEC", "SECURITY"]);
+}
@@ -0,0 +1,333 @@
+// src/report.rs
+// Renders Markdown and HTML reports of the annotations, grouped by project and file
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config_layers::ConfigOverrides;
+use crate::list::{
+    ListFilter, SortKey, Status, current_timestamp, format_age, project_filter, select_codemarks,
+};
+use crate::query::format_date;
+use crate::{Codemark, load_global_config, load_global_projects};
+
+/// Format of a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    /// A single HTML page with inline styles
+    Html,
+}
+
+/// The annotations of one project in a report
+struct ProjectReport<'a> {
+    name: &'a str,
+    /// Number of annotations per kind, `(none)` for annotations without a kind
+    kinds: BTreeMap<&'a str, usize>,
+    /// Annotations per file, in file order and then in line order
+    files: BTreeMap<&'a str, Vec<&'a Codemark>>,
+}
+
+/// Everything that is rendered into a report
+struct Report<'a> {
+    generated_at: i64,
+    projects: Vec<ProjectReport<'a>>,
+    /// Template of links to annotations, with `{file}` and `{line}` placeholders
+    repository_url: Option<String>,
+    /// Directory of each project root within its git repository, e.g. `web/`
+    prefixes: HashMap<String, String>,
+}
+
+impl<'a> Report<'a> {
+    fn new(
+        selected: Vec<(&'a str, &'a Codemark)>,
+        repository_url: Option<String>,
+        prefixes: HashMap<String, String>,
+        generated_at: i64,
+    ) -> Self {
+        let mut projects: Vec<ProjectReport> = Vec::new();
+        // The codemarks are sorted by project, so each project is a consecutive run
+        for (name, codemark) in selected {
+            if projects.last().is_none_or(|project| project.name != name) {
+                projects.push(ProjectReport {
+                    name,
+                    kinds: BTreeMap::new(),
+                    files: BTreeMap::new(),
+                });
+            }
+            if let Some(project) = projects.last_mut() {
+                *project
+                    .kinds
+                    .entry(codemark.kind.as_deref().unwrap_or("(none)"))
+                    .or_default() += 1;
+                project
+                    .files
+                    .entry(codemark.file.as_str())
+                    .or_default()
+                    .push(codemark);
+            }
+        }
+        Self {
+            generated_at,
+            projects,
+            repository_url,
+            prefixes,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.projects
+            .iter()
+            .map(|project| project.kinds.values().sum::<usize>())
+            .sum()
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "Generated on {}: {} annotations in {} projects",
+            format_date(self.generated_at),
+            self.total(),
+            self.projects.len()
+        )
+    }
+
+    /// Links an annotation to its line in the repository, if a URL template is configured.
+    /// Stored paths are relative to the project root, links to the repository root.
+    fn link(&self, project: &str, codemark: &Codemark) -> Option<String> {
+        self.repository_url.as_ref().map(|template| {
+            let prefix = self.prefixes.get(project).map_or("", String::as_str);
+            let file = format!("{prefix}{}", codemark.file.trim_start_matches("./"));
+            template
+                .replace("{project}", project)
+                .replace("{file}", &file)
+                .replace("{line}", &codemark.line_number.to_string())
+        })
+    }
+
+    /// Describes how long ago an annotation was introduced, if that is known
+    fn age(&self, codemark: &Codemark) -> String {
+        codemark
+            .introduced_at()
+            .map(|date| format_age(self.generated_at - date))
+            .unwrap_or_default()
+    }
+
+    fn description(codemark: &Codemark) -> String {
+        if codemark.resolved {
+            format!("(resolved) {}", codemark.description)
+        } else {
+            codemark.description.clone()
+        }
+    }
+}
+
+/// Writes a report of the selected annotations to a file, or to stdout without one
+pub fn generate_report(
+    filter: &ListFilter,
+    format: ReportFormat,
+    output: Option<&Path>,
+    repository_url: Option<String>,
+) -> Result<()> {
+    let overrides = ConfigOverrides {
+        repository_url,
+        ..Default::default()
+    };
+    let layered = load_global_config(Path::new("."), &overrides, false)?;
+    let projects_db = load_global_projects(false);
+
+    // Reports are about the remaining work, unless asked otherwise
+    let mut filter = filter.clone();
+    filter.status.get_or_insert(Status::Open);
+    let project = project_filter(&filter)?;
+    let now = current_timestamp();
+    let (selected, _) = select_codemarks(
+        &projects_db,
+        &filter,
+        project.as_ref().map(|(project, _)| project.as_str()),
+        SortKey::Project,
+        now,
+    );
+    let mut prefixes = HashMap::new();
+    if layered.config.repository_url.is_some() {
+        for (name, _) in &selected {
+            if !prefixes.contains_key(*name)
+                && let Some(root) = projects_db.roots.get(*name)
+            {
+                prefixes.insert((*name).to_string(), repository_prefix(root));
+            }
+        }
+    }
+    let report = Report::new(selected, layered.config.repository_url, prefixes, now);
+    let content = match format {
+        ReportFormat::Markdown => render_markdown(&report),
+        ReportFormat::Html => render_html(&report),
+    };
+
+    match output {
+        Some(path) => {
+            fs::write(path, content)?;
+            println!(
+                "Report of {} annotations written to {}",
+                report.total(),
+                path.display()
+            );
+        }
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+fn render_markdown(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Codemarks Report\n\n{}", report.summary());
+    for project in &report.projects {
+        let _ = writeln!(out, "\n## {}\n", markdown_escape(project.name));
+        let _ = writeln!(out, "| Kind | Count |\n|------|------:|");
+        for (kind, count) in &project.kinds {
+            let _ = writeln!(out, "| {} | {count} |", markdown_escape(kind));
+        }
+        for (file, codemarks) in &project.files {
+            let _ = writeln!(out, "\n### {}\n", markdown_escape(file));
+            let _ = writeln!(
+                out,
+                "| Line | Kind | Description | Owner | Age |\n|-----:|------|-------------|-------|-----|"
+            );
+            for codemark in codemarks {
+                let location = codemark.location();
+                let line = location.strip_prefix(*file).unwrap_or(&location);
+                let line = line.trim_start_matches(':');
+                let line = match report.link(project.name, codemark) {
+                    Some(url) => {
+                        format!("[{}]({})", markdown_escape(line), url.replace(' ', "%20"))
+                    }
+                    None => markdown_escape(line),
+                };
+                let _ = writeln!(
+                    out,
+                    "| {line} | {} | {} | {} | {} |",
+                    markdown_escape(codemark.kind.as_deref().unwrap_or_default()),
+                    markdown_escape(&Report::description(codemark)),
+                    markdown_escape(codemark.owner.as_deref().unwrap_or_default()),
+                    report.age(codemark)
+                );
+            }
+        }
+    }
+    if report.projects.is_empty() {
+        let _ = writeln!(out, "\nNo annotations found.");
+    }
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem auto;max-width:60rem;\
+padding:0 1rem;color:#222}table{border-collapse:collapse;margin:.5rem 0 1rem}th,td{border:1px \
+solid #ddd;padding:.25rem .6rem;text-align:left;vertical-align:top}th{background:#f5f5f5}\
+td.number{text-align:right}h2{border-bottom:1px solid #ddd;padding-bottom:.25rem}h3{font-family:\
+monospace;font-size:1rem}.summary{color:#666}";
+
+fn render_html(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Codemarks Report</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <h1>Codemarks Report</h1>\n<p class=\"summary\">{}</p>",
+        html_escape(&report.summary())
+    );
+    for project in &report.projects {
+        let _ = writeln!(out, "<h2>{}</h2>", html_escape(project.name));
+        let _ = writeln!(out, "<table>\n<tr><th>Kind</th><th>Count</th></tr>");
+        for (kind, count) in &project.kinds {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td class=\"number\">{count}</td></tr>",
+                html_escape(kind)
+            );
+        }
+        let _ = writeln!(out, "</table>");
+        for (file, codemarks) in &project.files {
+            let _ = writeln!(out, "<h3>{}</h3>", html_escape(file));
+            let _ = writeln!(
+                out,
+                "<table>\n<tr><th>Line</th><th>Kind</th><th>Description</th><th>Owner</th><th>Age</th></tr>"
+            );
+            for codemark in codemarks {
+                let location = codemark.location();
+                let line = location.strip_prefix(*file).unwrap_or(&location);
+                let line = html_escape(line.trim_start_matches(':'));
+                let line = match report.link(project.name, codemark) {
+                    Some(url) => format!("<a href=\"{}\">{line}</a>", html_escape(&url)),
+                    None => line,
+                };
+                let _ = writeln!(
+                    out,
+                    "<tr><td class=\"number\">{line}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(codemark.kind.as_deref().unwrap_or_default()),
+                    html_escape(&Report::description(codemark)),
+                    html_escape(codemark.owner.as_deref().unwrap_or_default()),
+                    report.age(codemark)
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+    }
+    if report.projects.is_empty() {
+        let _ = writeln!(out, "<p>No annotations found.</p>");
+    }
+    let _ = writeln!(out, "</body>\n</html>");
+    out
+}
+
+/// Escapes text for a Markdown table cell
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' | '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns the directory of a project root within its git repository, with a trailing
+/// slash, or nothing when the root is the top level or not in a repository
+fn repository_prefix(root: &Path) -> String {
+    Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--show-prefix"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,124 @@
+use super::*;
+
+const NOW: i64 = 1_700_000_000;
+
+fn codemarks() -> Vec<Codemark> {
+    vec![
+        Codemark {
+            file: "src/lib.rs".to_string(),
+            line_number: 12,
+            description: "Split <this> | that".to_string(),
+            kind: Some("TODO".to_string()),
+            owner: Some("alice".to_string()),
+            created_at: Some(NOW - 40 * 86_400),
+            ..Default::default()
+        },
+        Codemark {
+            file: "src/lib.rs".to_string(),
+            line_number: 3,
+            description: "Handle errors".to_string(),
+            kind: Some("FIXME".to_string()),
+            ..Default::default()
+        },
+        Codemark {
+            file: "README.md".to_string(),
+            line_number: 7,
+            description: "Document & link".to_string(),
+            kind: Some("TODO".to_string()),
+            ..Default::default()
+        },
+    ]
+}
+
+fn report<'a>(codemarks: &'a [Codemark], repository_url: Option<&str>) -> Report<'a> {
+    let mut selected: Vec<_> = codemarks.iter().map(|codemark| ("api", codemark)).collect();
+    selected.sort_by_key(|(_, codemark)| (&codemark.file, codemark.line_number));
+    Report::new(
+        selected,
+        repository_url.map(str::to_string),
+        HashMap::new(),
+        NOW,
+    )
+}
+
+#[test]
+fn test_render_markdown() {
+    let codemarks = codemarks();
+    let markdown = render_markdown(&report(
+        &codemarks,
+        Some("https://example.com/{project}/blob/main/{file}#L{line}"),
+    ));
+
+    assert!(markdown.contains("Generated on 2023-11-14: 3 annotations in 1 projects"));
+    assert!(markdown.contains("## api"));
+    assert!(markdown.contains("| FIXME | 1 |\n| TODO | 2 |"));
+    // Files are listed in order, with their annotations in line order
+    let readme = markdown.find("### README.md").unwrap();
+    let lib = markdown.find("### src/lib.rs").unwrap();
+    assert!(readme < lib);
+    let first = markdown
+        .find("| [3](https://example.com/api/blob/main/src/lib.rs#L3) | FIXME")
+        .unwrap();
+    let second = markdown
+        .find("| [12](https://example.com/api/blob/main/src/lib.rs#L12) | TODO | Split \\<this\\> \\| that | alice | 1mo |")
+        .unwrap();
+    assert!(first < second);
+}
+
+#[test]
+fn test_render_html() {
+    let codemarks = codemarks();
+    let html = render_html(&report(&codemarks, None));
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    assert!(html.contains("<td>Split &lt;this&gt; | that</td>"));
+    assert!(html.contains("<td>Document &amp; link</td>"));
+    // Without a repository URL template, lines are not linked
+    assert!(html.contains("<td class=\"number\">7</td>"));
+    assert!(!html.contains("<a href"));
+}
+
+#[test]
+fn test_empty_report() {
+    let report = Report::new(Vec::new(), None, HashMap::new(), NOW);
+    assert!(render_markdown(&report).contains("No annotations found."));
+    assert!(render_html(&report).contains("<p>No annotations found.</p>"));
+}
+
+#[test]
+fn test_link_relative_to_repository() {
+    let mut codemarks = codemarks();
+    codemarks[2].file = "./README.md".to_string();
+    let mut selected: Vec<_> = codemarks.iter().map(|codemark| ("api", codemark)).collect();
+    selected.sort_by_key(|(_, codemark)| (&codemark.file, codemark.line_number));
+    let report = Report::new(
+        selected,
+        Some("https://example.com/blob/main/{file}#L{line}".to_string()),
+        HashMap::from([("api".to_string(), "services/api/".to_string())]),
+        NOW,
+    );
+
+    let markdown = render_markdown(&report);
+    assert!(markdown.contains("(https://example.com/blob/main/services/api/src/lib.rs#L3)"));
+    assert!(markdown.contains("(https://example.com/blob/main/services/api/README.md#L7)"));
+}
+
+#[test]
+fn test_repository_prefix() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let nested = dir.path().join("services/api");
+    fs::create_dir_all(&nested).unwrap();
+    assert_eq!(repository_prefix(&nested), "");
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir.path())
+        .arg("init")
+        .output()
+        .expect("Failed to run git");
+    assert!(status.status.success());
+    assert_eq!(repository_prefix(&nested), "services/api/");
+    assert_eq!(repository_prefix(dir.path()), "");
+}
@@ -2,102 +2,165 @@
 // Handles the scan command for codemarks
 
 use anyhow::Result;
-use ignore::{WalkBuilder, overrides::OverrideBuilder};
-use regex::Regex;
-use std::fs;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::blame::blame_file;
+use crate::config_layers::ConfigOverrides;
+use crate::filters::FileSelection;
+use crate::formats::read_source_lines;
+use crate::matcher::Matcher;
+use crate::project_detection::{is_project_root, project_name_for_root};
 use crate::{
-    Codemark, detect_project_name, load_global_config, load_global_projects, save_global_projects,
+    AnnotationKind, Codemark, detect_project_name, load_global_config, load_global_projects,
+    save_global_projects,
 };
 
+/// The outcome of a scan
+pub struct ScanSummary {
+    /// Number of open annotations in the database after the scan
+    pub total: usize,
+    /// Open annotations of the scanned projects, with their project name
+    pub codemarks: Vec<(String, Codemark)>,
+    /// Annotation kinds of the configuration the scan used
+    pub kinds: Vec<AnnotationKind>,
+}
+
 pub fn scan_directory(
     directory: &Path,
     ignore_patterns: &[String],
+    selection: &FileSelection,
+    blame: bool,
     ephemeral: bool,
-) -> Result<usize> {
-    let config = load_global_config(ephemeral);
+) -> Result<ScanSummary> {
+    let overrides = ConfigOverrides {
+        ignore: ignore_patterns.to_vec(),
+        ..Default::default()
+    };
+    let layered = load_global_config(directory, &overrides, ephemeral)?;
+    let ignore_patterns = &layered.ignore;
     let mut projects_db = load_global_projects(ephemeral);
-    // Use the original pattern for matching only
-    let codemark_regex = Regex::new(&config.annotation_pattern)?;
-    let project_name = detect_project_name(directory);
+    let matcher = Matcher::new(&layered.config)?;
+    let project_name = layered
+        .project_name_for(directory)
+        .unwrap_or_else(|| detect_project_name(directory));
     let canonical_dir = directory.canonicalize()?;
-    if let Some(existing_codemarks) = projects_db.projects.get_mut(&project_name) {
-        for codemark in existing_codemarks.iter_mut() {
-            codemark.resolved = true;
-        }
-    }
-    let mut current_codemarks = Vec::new();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs().cast_signed());
 
-    let mut builder = WalkBuilder::new(directory);
+    // Remember where each project lives, so annotations can be opened from anywhere.
+    // Nested roots are added as the walk finds them.
+    projects_db
+        .roots
+        .insert(project_name.clone(), canonical_dir.clone());
 
-    // Add custom ignore patterns using overrides
-    if !ignore_patterns.is_empty() {
-        let mut override_builder = OverrideBuilder::new(directory);
-        for pattern in ignore_patterns {
-            // Add as negative override (ignore pattern)
-            if let Err(e) = override_builder.add(&format!("!{pattern}")) {
-                eprintln!("Warning: Invalid ignore pattern '{pattern}': {e}");
-            }
-        }
-        if let Ok(overrides) = override_builder.build() {
-            builder.overrides(overrides);
-        }
-    }
+    // Nested project roots found during the walk, keyed by their path
+    let mut project_roots: HashMap<PathBuf, String> = HashMap::new();
+    let mut current_codemarks: HashMap<String, Vec<Found>> = HashMap::new();
+    current_codemarks.insert(project_name.clone(), Vec::new());
+
+    let builder = selection.walker(directory, ignore_patterns)?;
 
     for result in builder.build() {
         let Ok(entry) = result else { continue };
         let file_path = entry.path();
-        if entry.file_type().is_some_and(|ft| ft.is_file())
-            && let Ok(file) = fs::File::open(file_path)
+        if entry.depth() > 0
+            && entry.file_type().is_some_and(|ft| ft.is_dir())
+            && is_project_root(file_path)
         {
-            let reader = BufReader::new(file);
-            for (line_number, line) in reader.lines().enumerate() {
-                if let Ok(line_content) = line {
-                    // Use the pattern only to match, but always store the entire line
-                    if codemark_regex.is_match(&line_content) {
-                        let description = line_content.clone();
-                        let relative_path =
-                            if let Ok(stripped) = file_path.strip_prefix(&canonical_dir) {
-                                stripped.to_string_lossy().to_string()
-                            } else {
-                                file_path.to_string_lossy().to_string()
-                            };
-                        let codemark = Codemark {
-                            file: relative_path,
-                            line_number: line_number + 1,
-                            description,
-                            resolved: false,
-                        };
-                        current_codemarks.push(codemark);
-                    }
-                }
-            }
+            // Projects sharing a manifest name are stored under distinct names
+            let root = file_path
+                .canonicalize()
+                .unwrap_or_else(|_| file_path.to_path_buf());
+            let nested_name =
+                project_name_for_root(&projects_db.roots, &root, detect_project_name(file_path));
+            projects_db.roots.insert(nested_name.clone(), root);
+            current_codemarks.entry(nested_name.clone()).or_default();
+            project_roots.insert(file_path.to_path_buf(), nested_name);
+            continue;
         }
-    }
-    if let Some(existing_codemarks) = projects_db.projects.get_mut(&project_name) {
-        for current_codemark in current_codemarks {
-            let mut found = false;
-            for existing_codemark in existing_codemarks.iter_mut() {
-                if existing_codemark.file == current_codemark.file
-                    && existing_codemark.description == current_codemark.description
-                {
-                    existing_codemark.resolved = false;
-                    existing_codemark.line_number = current_codemark.line_number;
-                    found = true;
-                    break;
-                }
+        if entry.file_type().is_some_and(|ft| ft.is_file())
+            && let Ok(lines) = read_source_lines(file_path)
+        {
+            // Attribute the file to its nearest enclosing project
+            let nested_root = file_path
+                .ancestors()
+                .find_map(|ancestor| project_roots.get_key_value(ancestor));
+            let (owner, relative_path) = match nested_root {
+                Some((root, name)) => (
+                    name.clone(),
+                    file_path
+                        .strip_prefix(root)
+                        .unwrap_or(file_path)
+                        .to_string_lossy()
+                        .to_string(),
+                ),
+                // The walker yields paths under `directory` as given, e.g. ./src/main.rs
+                None => (
+                    project_name.clone(),
+                    file_path
+                        .strip_prefix(directory)
+                        .or_else(|_| file_path.strip_prefix(&canonical_dir))
+                        .unwrap_or(file_path)
+                        .to_string_lossy()
+                        .to_string(),
+                ),
+            };
+
+            let project_codemarks = current_codemarks.entry(owner).or_default();
+            let first_in_file = project_codemarks.len();
+            for (line, line_match) in matcher.match_file_lines(file_path, lines).matches {
+                let codemark = Codemark {
+                    file: relative_path.clone(),
+                    line_number: line.line_number,
+                    cell: line.cell,
+                    column: Some(line_match.column),
+                    description: line_match.description,
+                    resolved: false,
+                    kind: line_match.kind,
+                    owner: line_match.owner,
+                    issue: line_match.issue,
+                    created_at: Some(now),
+                    ..Default::default()
+                };
+                project_codemarks.push(Found {
+                    codemark,
+                    line: line.text,
+                });
             }
-            if !found {
-                existing_codemarks.push(current_codemark);
+            // Blame line numbers only exist for plain files, not notebook cells
+            if blame
+                && project_codemarks.len() > first_in_file
+                && file_path.extension().is_none_or(|ext| ext != "ipynb")
+            {
+                attribute_codemarks(
+                    file_path,
+                    project_codemarks[first_in_file..]
+                        .iter_mut()
+                        .map(|found| &mut found.codemark),
+                );
             }
         }
-    } else {
-        projects_db
-            .projects
-            .insert(project_name.clone(), current_codemarks);
     }
+
+    let mut scanned_projects: Vec<String> = current_codemarks.keys().cloned().collect();
+    scanned_projects.sort();
+    for (name, codemarks) in current_codemarks {
+        projects_db.scanned_at.insert(name.clone(), now);
+        let existing_codemarks = projects_db.projects.entry(name).or_default();
+        reconcile_codemarks(existing_codemarks, codemarks, now);
+    }
+    let codemarks = scanned_projects
+        .iter()
+        .flat_map(|name| {
+            projects_db.projects[name]
+                .iter()
+                .filter(|codemark| !codemark.resolved)
+                .map(|codemark| (name.clone(), codemark.clone()))
+        })
+        .collect();
     let total_count = projects_db
         .projects
         .values()
@@ -105,7 +168,93 @@ pub fn scan_directory(
         .filter(|codemark| !codemark.resolved)
         .count();
     save_global_projects(&projects_db, ephemeral)?;
-    Ok(total_count)
+    Ok(ScanSummary {
+        total: total_count,
+        codemarks,
+        kinds: layered.config.kinds,
+    })
+}
+
+/// A codemark found by a scan, with the text of its line
+struct Found {
+    codemark: Codemark,
+    line: String,
+}
+
+/// Merges the codemarks found by a scan into the stored codemarks of a project.
+/// Stored codemarks that were not found again are marked as resolved.
+fn reconcile_codemarks(existing_codemarks: &mut Vec<Codemark>, current: Vec<Found>, now: i64) {
+    for codemark in existing_codemarks.iter_mut() {
+        if !codemark.resolved {
+            codemark.resolved_at = Some(now);
+        }
+        codemark.resolved = true;
+    }
+    for Found {
+        codemark: current_codemark,
+        line,
+    } in current
+    {
+        // Only match codemarks that were not revived yet, so duplicate lines stay distinct
+        let same_file = |existing_codemark: &Codemark| {
+            existing_codemark.resolved
+                && existing_codemark.file.trim_start_matches("./") == current_codemark.file
+        };
+        let index = existing_codemarks
+            .iter()
+            .position(|existing_codemark| {
+                same_file(existing_codemark)
+                    && existing_codemark.description == current_codemark.description
+            })
+            // Older versions stored the whole line as the description, and scans of `.`
+            // kept a ./ prefix on the file
+            .or_else(|| {
+                existing_codemarks.iter().position(|existing_codemark| {
+                    same_file(existing_codemark)
+                        && existing_codemark.description.trim() == line.trim()
+                })
+            });
+        if let Some(index) = index {
+            let existing_codemark = &mut existing_codemarks[index];
+            existing_codemark.file = current_codemark.file;
+            existing_codemark.description = current_codemark.description;
+            existing_codemark.resolved = false;
+            existing_codemark.resolved_at = None;
+            existing_codemark.line_number = current_codemark.line_number;
+            existing_codemark.cell = current_codemark.cell;
+            existing_codemark.column = current_codemark.column;
+            existing_codemark.kind = current_codemark.kind;
+            existing_codemark.owner = current_codemark.owner;
+            existing_codemark.issue = current_codemark.issue;
+            if current_codemark.commit.is_some() {
+                existing_codemark.author = current_codemark.author;
+                existing_codemark.author_email = current_codemark.author_email;
+                existing_codemark.commit = current_codemark.commit;
+                existing_codemark.commit_date = current_codemark.commit_date;
+            }
+        } else {
+            existing_codemarks.push(current_codemark);
+        }
+    }
+}
+
+/// Fills in the author fields of the codemarks found in one file using git blame
+pub fn attribute_codemarks<'a>(
+    file_path: &Path,
+    codemarks: impl IntoIterator<Item = &'a mut Codemark>,
+) {
+    // Untracked files and directories outside a repository simply stay unattributed
+    let Ok(blame) = blame_file(file_path) else {
+        return;
+    };
+    for codemark in codemarks {
+        if let Some(info) = blame.get(&codemark.line_number) {
+            codemark.author = Some(info.author.clone());
+            codemark.author_email = Some(info.author_email.clone());
+            codemark.commit = Some(info.commit.clone());
+            codemark.commit_date = Some(info.commit_date);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,263 @@
+// src/stats.rs
+// Handles the stats command for codemarks
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::list::{current_timestamp, format_age};
+use crate::{ProjectsDatabase, load_global_projects};
+
+/// Format of the statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum StatsFormat {
+    #[default]
+    Text,
+    /// A single JSON object, e.g. for dashboards
+    Json,
+}
+
+/// Number of open and resolved annotations
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Counts {
+    pub open: usize,
+    pub resolved: usize,
+}
+
+impl Counts {
+    fn add(&mut self, resolved: bool) {
+        if resolved {
+            self.resolved += 1;
+        } else {
+            self.open += 1;
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ProjectStats {
+    pub project: String,
+    #[serde(flatten)]
+    pub counts: Counts,
+    /// Unix timestamp of the last scan of the project, if it was ever scanned
+    pub last_scan: Option<i64>,
+    /// Annotations the last scan found for the first time
+    pub added_by_last_scan: usize,
+    /// Annotations the last scan no longer found
+    pub resolved_by_last_scan: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct KindStats {
+    /// Kind of the annotations, `null` for annotations without a kind
+    pub kind: Option<String>,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct FileStats {
+    pub project: String,
+    pub file: String,
+    pub open: usize,
+}
+
+/// Statistics of the annotations in the database
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Stats {
+    pub generated_at: i64,
+    #[serde(flatten)]
+    pub counts: Counts,
+    /// Average age of the open annotations whose introduction date is known, in seconds
+    pub average_age: Option<i64>,
+    pub added_by_last_scan: usize,
+    pub resolved_by_last_scan: usize,
+    pub projects: Vec<ProjectStats>,
+    pub kinds: Vec<KindStats>,
+    /// Files with the most open annotations, most first
+    pub top_files: Vec<FileStats>,
+}
+
+/// Computes the statistics of all projects, or only of the given one
+#[must_use]
+pub fn compute_stats(
+    projects_db: &ProjectsDatabase,
+    project: Option<&str>,
+    top: usize,
+    now: i64,
+) -> Stats {
+    let mut counts = Counts::default();
+    let mut ages = Vec::new();
+    let mut projects = Vec::new();
+    let mut kinds: BTreeMap<Option<&str>, Counts> = BTreeMap::new();
+    let mut top_files = Vec::new();
+
+    let mut project_names: Vec<&String> = projects_db.projects.keys().collect();
+    project_names.sort();
+    for name in project_names {
+        if project.is_some_and(|project| project != name) {
+            continue;
+        }
+        let last_scan = projects_db.scanned_at.get(name).copied();
+        // A scan stamps the annotations it adds and resolves with its own time
+        let by_last_scan =
+            |date: Option<i64>| last_scan.is_some_and(|last_scan| date == Some(last_scan));
+        let mut project_stats = ProjectStats {
+            project: name.clone(),
+            counts: Counts::default(),
+            last_scan,
+            added_by_last_scan: 0,
+            resolved_by_last_scan: 0,
+        };
+        let mut files: BTreeMap<&str, usize> = BTreeMap::new();
+        for codemark in &projects_db.projects[name] {
+            counts.add(codemark.resolved);
+            project_stats.counts.add(codemark.resolved);
+            kinds
+                .entry(codemark.kind.as_deref())
+                .or_default()
+                .add(codemark.resolved);
+            if by_last_scan(codemark.created_at) {
+                project_stats.added_by_last_scan += 1;
+            }
+            if codemark.resolved {
+                if by_last_scan(codemark.resolved_at) {
+                    project_stats.resolved_by_last_scan += 1;
+                }
+                continue;
+            }
+            *files.entry(&codemark.file).or_default() += 1;
+            if let Some(date) = codemark.introduced_at() {
+                ages.push((now - date).max(0));
+            }
+        }
+        top_files.extend(files.into_iter().map(|(file, open)| FileStats {
+            project: name.clone(),
+            file: file.to_string(),
+            open,
+        }));
+        projects.push(project_stats);
+    }
+
+    // Most annotated files first, ties in project and file order
+    top_files.sort_by_key(|file| std::cmp::Reverse(file.open));
+    top_files.truncate(top);
+    // Annotations without a kind come last
+    let mut kinds: Vec<KindStats> = kinds
+        .into_iter()
+        .map(|(kind, counts)| KindStats {
+            kind: kind.map(str::to_string),
+            counts,
+        })
+        .collect();
+    kinds.sort_by_key(|kind| kind.kind.is_none());
+
+    Stats {
+        generated_at: now,
+        counts,
+        average_age: (!ages.is_empty()).then(|| ages.iter().sum::<i64>() / ages.len() as i64),
+        added_by_last_scan: projects.iter().map(|p| p.added_by_last_scan).sum(),
+        resolved_by_last_scan: projects.iter().map(|p| p.resolved_by_last_scan).sum(),
+        projects,
+        kinds,
+        top_files,
+    }
+}
+
+/// Renders the statistics as aligned tables
+#[must_use]
+pub fn render_text(stats: &Stats) -> String {
+    let mut lines = vec![format!(
+        "Annotations: {} open, {} resolved",
+        stats.counts.open, stats.counts.resolved
+    )];
+    if let Some(age) = stats.average_age {
+        lines.push(format!(
+            "Average age of open annotations: {}",
+            format_age(age)
+        ));
+    }
+    lines.push(format!(
+        "Last scan: {} added, {} resolved",
+        stats.added_by_last_scan, stats.resolved_by_last_scan
+    ));
+
+    lines.push(String::new());
+    let width = stats
+        .projects
+        .iter()
+        .map(|project| project.project.len())
+        .chain(["Project".len()])
+        .max()
+        .unwrap_or_default();
+    // Changes are the annotations added and resolved by the last scan of the project
+    lines.push(format!(
+        "{:width$}  {:>6}  {:>8}  {:>7}  Last scan",
+        "Project", "Open", "Resolved", "Changes"
+    ));
+    for project in &stats.projects {
+        let last_scan = project.last_scan.map_or_else(
+            || "never".to_string(),
+            |date| match format_age(stats.generated_at - date).as_str() {
+                "today" => "today".to_string(),
+                age => format!("{age} ago"),
+            },
+        );
+        lines.push(format!(
+            "{:width$}  {:>6}  {:>8}  {:>7}  {last_scan}",
+            project.project,
+            project.counts.open,
+            project.counts.resolved,
+            format!(
+                "+{} -{}",
+                project.added_by_last_scan, project.resolved_by_last_scan
+            ),
+        ));
+    }
+
+    lines.push(String::new());
+    let kind_name = |kind: &KindStats| kind.kind.clone().unwrap_or_else(|| "(no kind)".to_string());
+    let width = stats
+        .kinds
+        .iter()
+        .map(|kind| kind_name(kind).len())
+        .chain(["Kind".len()])
+        .max()
+        .unwrap_or_default();
+    lines.push(format!(
+        "{:width$}  {:>6}  {:>8}",
+        "Kind", "Open", "Resolved"
+    ));
+    for kind in &stats.kinds {
+        lines.push(format!(
+            "{:width$}  {:>6}  {:>8}",
+            kind_name(kind),
+            kind.counts.open,
+            kind.counts.resolved
+        ));
+    }
+
+    if !stats.top_files.is_empty() {
+        lines.push(String::new());
+        lines.push("Files with the most open annotations".to_string());
+        for file in &stats.top_files {
+            lines.push(format!("{:>6}  {}: {}", file.open, file.project, file.file));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn show_stats(project: Option<&str>, top: usize, format: StatsFormat, ephemeral: bool) {
+    let projects_db = load_global_projects(ephemeral);
+    let stats = compute_stats(&projects_db, project, top, current_timestamp());
+    match format {
+        StatsFormat::Text => print!("{}", render_text(&stats)),
+        StatsFormat::Json => match serde_json::to_string_pretty(&stats) {
+            Ok(json) => println!("{json}"),
+            Err(e) => eprintln!("Error writing output: {e}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,151 @@
+use super::*;
+use crate::Codemark;
+
+const NOW: i64 = 1_700_000_000;
+const DAY: i64 = 86_400;
+
+fn database() -> ProjectsDatabase {
+    let codemark = |file: &str, kind: Option<&str>, created_at| Codemark {
+        file: file.to_string(),
+        line_number: 1,
+        description: "task".to_string(),
+        kind: kind.map(str::to_string),
+        created_at,
+        ..Default::default()
+    };
+    let mut projects_db = ProjectsDatabase::default();
+    projects_db.projects.insert(
+        "api".to_string(),
+        vec![
+            codemark("src/db.rs", Some("TODO"), Some(NOW - 10 * DAY)),
+            codemark("src/db.rs", Some("FIXME"), Some(NOW - 2 * DAY)),
+            codemark("src/main.rs", Some("TODO"), Some(NOW - 30 * DAY)),
+            Codemark {
+                resolved: true,
+                resolved_at: Some(NOW - 2 * DAY),
+                ..codemark("src/old.rs", Some("TODO"), Some(NOW - 40 * DAY))
+            },
+            Codemark {
+                resolved: true,
+                resolved_at: Some(NOW - 20 * DAY),
+                ..codemark("src/old.rs", Some("HACK"), None)
+            },
+        ],
+    );
+    projects_db
+        .projects
+        .insert("web".to_string(), vec![codemark("src/app.js", None, None)]);
+    projects_db
+        .scanned_at
+        .insert("api".to_string(), NOW - 2 * DAY);
+    projects_db
+}
+
+#[test]
+fn test_compute_stats() {
+    let stats = compute_stats(&database(), None, 2, NOW);
+
+    assert_eq!(
+        stats.counts,
+        Counts {
+            open: 4,
+            resolved: 2
+        }
+    );
+    // Only the open annotations with a known date count towards the age
+    assert_eq!(stats.average_age, Some(14 * DAY));
+    assert_eq!(stats.added_by_last_scan, 1);
+    assert_eq!(stats.resolved_by_last_scan, 1);
+
+    let api = &stats.projects[0];
+    assert_eq!(api.project, "api");
+    assert_eq!(
+        api.counts,
+        Counts {
+            open: 3,
+            resolved: 2
+        }
+    );
+    assert_eq!(api.last_scan, Some(NOW - 2 * DAY));
+    let web = &stats.projects[1];
+    assert_eq!(web.last_scan, None);
+    assert_eq!(web.added_by_last_scan, 0);
+
+    let kinds: Vec<(Option<&str>, usize, usize)> = stats
+        .kinds
+        .iter()
+        .map(|kind| (kind.kind.as_deref(), kind.counts.open, kind.counts.resolved))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (Some("FIXME"), 1, 0),
+            (Some("HACK"), 0, 1),
+            (Some("TODO"), 2, 1),
+            (None, 1, 0)
+        ]
+    );
+
+    let files: Vec<(&str, usize)> = stats
+        .top_files
+        .iter()
+        .map(|file| (file.file.as_str(), file.open))
+        .collect();
+    assert_eq!(files, [("src/db.rs", 2), ("src/main.rs", 1)]);
+}
+
+#[test]
+fn test_compute_stats_of_project() {
+    let stats = compute_stats(&database(), Some("web"), 10, NOW);
+    assert_eq!(
+        stats.counts,
+        Counts {
+            open: 1,
+            resolved: 0
+        }
+    );
+    assert_eq!(stats.average_age, None);
+    assert_eq!(stats.projects.len(), 1);
+    assert_eq!(stats.kinds.len(), 1);
+}
+
+#[test]
+fn test_render_text() {
+    let text = render_text(&compute_stats(&database(), None, 10, NOW));
+    assert!(text.starts_with(
+        "Annotations: 4 open, 2 resolved\n\
+         Average age of open annotations: 14d\n\
+         Last scan: 1 added, 1 resolved\n"
+    ));
+    assert!(text.contains("Project    Open  Resolved  Changes  Last scan\n"));
+    assert!(text.contains("api           3         2    +1 -1  2d ago\n"));
+    assert!(text.contains("web           1         0    +0 -0  never\n"));
+    assert!(text.contains("(no kind)       1         0\n"));
+    assert!(text.contains("     2  api: src/db.rs\n"));
+}
+
+#[test]
+fn test_stats_json() {
+    let json = serde_json::to_value(compute_stats(&database(), Some("api"), 1, NOW)).unwrap();
+    assert_eq!(json["open"], 3);
+    assert_eq!(json["resolved"], 2);
+    assert_eq!(json["average_age"], 14 * DAY);
+    assert_eq!(json["projects"][0]["project"], "api");
+    assert_eq!(json["projects"][0]["open"], 3);
+    assert_eq!(json["kinds"][0]["kind"], "FIXME");
+    assert_eq!(json["top_files"][0]["file"], "src/db.rs");
+}
+
+#[test]
+fn test_changes_only_count_the_last_scan() {
+    let mut projects_db = database();
+    let api = projects_db.projects.get_mut("api").unwrap();
+    // Resolved by hand after the scan, and added by an earlier scan
+    api[0].resolved = true;
+    api[0].resolved_at = Some(NOW - DAY);
+    api[2].created_at = Some(NOW - 3 * DAY);
+
+    let stats = compute_stats(&projects_db, Some("api"), 10, NOW);
+    assert_eq!(stats.added_by_last_scan, 1);
+    assert_eq!(stats.resolved_by_last_scan, 1);
+}
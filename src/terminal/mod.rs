@@ -0,0 +1,167 @@
+// src/terminal.rs
+// Colors and hyperlinks for output on a terminal
+
+use clap::ValueEnum;
+use std::env;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::{AnnotationKind, Severity};
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Only on a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether to style standard output
+    #[must_use]
+    pub fn enabled(self) -> bool {
+        should_style(
+            self,
+            std::io::stdout().is_terminal(),
+            env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            env::var("TERM").is_ok_and(|term| term == "dumb"),
+        )
+    }
+}
+
+fn should_style(choice: ColorChoice, is_terminal: bool, no_color: bool, dumb: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => is_terminal && !no_color && !dumb,
+    }
+}
+
+/// Styles text with colors by annotation kind and OSC 8 hyperlinks to files.
+/// When styling is disabled, every method returns the text unchanged.
+pub struct Styler {
+    enabled: bool,
+    kinds: Vec<AnnotationKind>,
+}
+
+impl Styler {
+    #[must_use]
+    pub fn new(choice: ColorChoice, kinds: &[AnnotationKind]) -> Self {
+        Self::with_enabled(choice.enabled(), kinds)
+    }
+
+    #[must_use]
+    pub fn with_enabled(enabled: bool, kinds: &[AnnotationKind]) -> Self {
+        Self {
+            enabled,
+            kinds: kinds.to_vec(),
+        }
+    }
+
+    fn paint(&self, text: &str, code: &str) -> String {
+        if self.enabled && !text.is_empty() {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Colors text with the color of a kind, or the color of its severity
+    #[must_use]
+    pub fn kind(&self, kind: Option<&str>, text: &str) -> String {
+        match kind_color(&self.kinds, kind).and_then(color_code) {
+            Some(code) => self.paint(text, code),
+            None => text.to_string(),
+        }
+    }
+
+    #[must_use]
+    pub fn bold(&self, text: &str) -> String {
+        self.paint(text, "1")
+    }
+
+    /// Dims secondary text, such as resolved annotations
+    #[must_use]
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(text, "2")
+    }
+
+    #[must_use]
+    pub fn success(&self, text: &str) -> String {
+        self.paint(text, "32")
+    }
+
+    #[must_use]
+    pub fn error(&self, text: &str) -> String {
+        self.paint(text, "1;31")
+    }
+
+    /// Makes text a hyperlink to a line of a file, which must exist
+    #[must_use]
+    pub fn link(&self, text: &str, path: &Path, line: Option<usize>) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        match path.canonicalize() {
+            Ok(path) => format!(
+                "\x1b]8;;{}\x1b\\{text}\x1b]8;;\x1b\\",
+                file_url(&path, line)
+            ),
+            Err(_) => text.to_string(),
+        }
+    }
+}
+
+/// Returns the name of the color of a kind: its configured color, or the color of its
+/// severity. Unknown kinds have no color.
+#[must_use]
+pub fn kind_color<'a>(kinds: &'a [AnnotationKind], kind: Option<&str>) -> Option<&'a str> {
+    let kind = AnnotationKind::find(kinds, kind)?;
+    Some(kind.color.as_deref().unwrap_or(match kind.severity {
+        Severity::Error => "red",
+        Severity::Warning => "yellow",
+        Severity::Info => "blue",
+    }))
+}
+
+fn color_code(color: &str) -> Option<&'static str> {
+    Some(match color {
+        "black" => "30",
+        "red" => "31",
+        "green" => "32",
+        "yellow" => "33",
+        "blue" => "34",
+        "magenta" => "35",
+        "cyan" => "36",
+        "white" => "37",
+        _ => return None,
+    })
+}
+
+/// Builds a `file://` URL of an absolute path, with the line as fragment
+fn file_url(path: &Path, line: Option<usize>) -> String {
+    let mut url = String::from("file://");
+    let path = path.to_string_lossy();
+    // Windows paths become file:///C:/...
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'\\' => url.push('/'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                url.push(char::from(byte));
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    if let Some(line) = line {
+        url.push_str(&format!("#{line}"));
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests;
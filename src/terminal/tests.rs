@@ -0,0 +1,73 @@
+use super::*;
+use crate::default_kinds;
+
+#[test]
+fn test_should_style() {
+    assert!(should_style(ColorChoice::Auto, true, false, false));
+    assert!(!should_style(ColorChoice::Auto, false, false, false));
+    assert!(!should_style(ColorChoice::Auto, true, true, false));
+    assert!(!should_style(ColorChoice::Auto, true, false, true));
+    // An explicit choice wins over the environment
+    assert!(should_style(ColorChoice::Always, false, true, true));
+    assert!(!should_style(ColorChoice::Never, true, false, false));
+}
+
+#[test]
+fn test_kind_colors() {
+    let mut kinds = default_kinds();
+    kinds.push(AnnotationKind {
+        color: None,
+        ..AnnotationKind::new("NOTE", Severity::Info, "")
+    });
+    let styler = Styler::with_enabled(true, &kinds);
+
+    assert_eq!(styler.kind(Some("FIXME"), "fix"), "\x1b[31mfix\x1b[0m");
+    assert_eq!(styler.kind(Some("hack"), "hack"), "\x1b[35mhack\x1b[0m");
+    // Without a color, the severity decides
+    assert_eq!(styler.kind(Some("NOTE"), "note"), "\x1b[34mnote\x1b[0m");
+    assert_eq!(styler.kind(Some("XXX"), "other"), "other");
+    assert_eq!(styler.kind(None, "none"), "none");
+    assert_eq!(styler.dim("old"), "\x1b[2mold\x1b[0m");
+    assert_eq!(styler.dim(""), "");
+
+    let plain = Styler::with_enabled(false, &kinds);
+    assert_eq!(plain.kind(Some("FIXME"), "fix"), "fix");
+    assert_eq!(plain.error("failed"), "failed");
+}
+
+#[test]
+fn test_links() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let file = dir.path().join("my file.rs");
+    std::fs::write(&file, "// TODO: link").expect("Failed to write file");
+    let url = file_url(&file.canonicalize().unwrap(), Some(3));
+
+    let styler = Styler::with_enabled(true, &[]);
+    assert_eq!(
+        styler.link("my file.rs:3", &file, Some(3)),
+        format!("\x1b]8;;{url}\x1b\\my file.rs:3\x1b]8;;\x1b\\")
+    );
+    assert!(url.starts_with("file:///"));
+    assert!(url.ends_with("/my%20file.rs#3"));
+    // Missing files and disabled styling are not linked
+    assert_eq!(
+        styler.link("gone.rs:1", &dir.path().join("gone.rs"), Some(1)),
+        "gone.rs:1"
+    );
+    assert_eq!(
+        Styler::with_enabled(false, &[]).link("my file.rs:3", &file, Some(3)),
+        "my file.rs:3"
+    );
+}
+
+#[test]
+fn test_file_url() {
+    assert_eq!(
+        file_url(Path::new("/home/ana/src/main.rs"), Some(12)),
+        "file:///home/ana/src/main.rs#12"
+    );
+    assert_eq!(
+        file_url(Path::new("/tmp/notes#1.md"), None),
+        "file:///tmp/notes%231.md"
+    );
+}
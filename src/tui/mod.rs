@@ -0,0 +1,655 @@
+// src/tui.rs
+// Full-screen terminal UI to browse and triage annotations
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config_layers::ConfigOverrides;
+use crate::formats::read_source_lines;
+use crate::list::current_timestamp;
+use crate::open::{codemark_path, launch_editor};
+use crate::query::Query;
+use crate::terminal::kind_color;
+use crate::{
+    AnnotationKind, Codemark, ProjectsDatabase, load_global_config, load_global_projects,
+    save_global_projects,
+};
+
+/// Number of source lines shown above and below the annotation in the preview
+const PREVIEW_CONTEXT: usize = 5;
+/// Number of rows moved by page up and page down
+const PAGE: usize = 10;
+const HELP: &str =
+    "q quit  tab switch pane  / filter  o open  x resolve  y copy location  a show resolved";
+
+/// The pane that receives the navigation keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Tree,
+    List,
+}
+
+/// What the event loop has to do after a key press
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+    /// Open a file at a line in the editor
+    Open(PathBuf, usize),
+    /// Copy text to the clipboard
+    Copy(String),
+    /// Save the modified projects database
+    Save,
+}
+
+/// An entry of the project and file tree, `None` standing for all projects or files
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeNode {
+    project: Option<String>,
+    file: Option<String>,
+    count: usize,
+}
+
+/// State of the terminal UI
+pub struct App {
+    pub projects_db: ProjectsDatabase,
+    kinds: Vec<AnnotationKind>,
+    tree: Vec<TreeNode>,
+    tree_state: ListState,
+    /// Annotations in the list, as project name and index into its codemarks
+    visible: Vec<(String, usize)>,
+    list_state: ListState,
+    focus: Focus,
+    /// Filter being typed, while the filter has the keyboard
+    input: Option<String>,
+    filter: String,
+    query: Option<Query>,
+    show_resolved: bool,
+    /// Message shown in the status bar until the next key press
+    pub status: String,
+    now: i64,
+    /// Annotations toggled since the last save, as project name and toggled codemark
+    toggled: Vec<(String, Codemark)>,
+}
+
+impl App {
+    #[must_use]
+    pub fn new(projects_db: ProjectsDatabase, kinds: Vec<AnnotationKind>, now: i64) -> Self {
+        let mut app = Self {
+            projects_db,
+            kinds,
+            tree: Vec::new(),
+            tree_state: ListState::default().with_selected(Some(0)),
+            visible: Vec::new(),
+            list_state: ListState::default(),
+            focus: Focus::List,
+            input: None,
+            filter: String::new(),
+            query: None,
+            show_resolved: false,
+            status: String::new(),
+            now,
+            toggled: Vec::new(),
+        };
+        app.refresh();
+        app
+    }
+
+    fn matches(&self, project: &str, codemark: &Codemark) -> bool {
+        (self.show_resolved || !codemark.resolved)
+            && self
+                .query
+                .as_ref()
+                .is_none_or(|query| query.matches(project, codemark, self.now))
+    }
+
+    /// Rebuilds the tree and the list after the annotations or the filter changed,
+    /// keeping the selections where possible
+    fn refresh(&mut self) {
+        let selected_node = self.selected_node().cloned();
+        let selected_codemark = self.selected_index().cloned();
+
+        let mut projects: Vec<&String> = self.projects_db.projects.keys().collect();
+        projects.sort();
+        let mut tree = vec![TreeNode {
+            project: None,
+            file: None,
+            count: 0,
+        }];
+        for project in projects {
+            let mut files: Vec<&str> = self.projects_db.projects[project]
+                .iter()
+                .filter(|codemark| self.matches(project, codemark))
+                .map(|codemark| codemark.file.as_str())
+                .collect();
+            if files.is_empty() {
+                continue;
+            }
+            files.sort_unstable();
+            tree[0].count += files.len();
+            tree.push(TreeNode {
+                project: Some(project.clone()),
+                file: None,
+                count: files.len(),
+            });
+            for chunk in files.chunk_by(|a, b| a == b) {
+                tree.push(TreeNode {
+                    project: Some(project.clone()),
+                    file: Some(chunk[0].to_string()),
+                    count: chunk.len(),
+                });
+            }
+        }
+        self.tree = tree;
+        let node_index = selected_node
+            .and_then(|selected| {
+                self.tree
+                    .iter()
+                    .position(|node| node.project == selected.project && node.file == selected.file)
+            })
+            .unwrap_or(0);
+        self.tree_state.select(Some(node_index));
+
+        let node = &self.tree[node_index];
+        let mut visible = Vec::new();
+        for (project, codemarks) in &self.projects_db.projects {
+            if node
+                .project
+                .as_ref()
+                .is_some_and(|selected| selected != project)
+            {
+                continue;
+            }
+            for (index, codemark) in codemarks.iter().enumerate() {
+                if node.file.as_ref().is_none_or(|file| *file == codemark.file)
+                    && self.matches(project, codemark)
+                {
+                    visible.push((project.clone(), index));
+                }
+            }
+        }
+        visible.sort_by(|(project_a, a), (project_b, b)| {
+            let a = &self.projects_db.projects[project_a][*a];
+            let b = &self.projects_db.projects[project_b][*b];
+            (project_a, &a.file, a.cell, a.line_number).cmp(&(
+                project_b,
+                &b.file,
+                b.cell,
+                b.line_number,
+            ))
+        });
+        let list_index = selected_codemark
+            .and_then(|selected| visible.iter().position(|entry| *entry == selected))
+            .or_else(|| {
+                // The selected annotation disappeared, stay at the same row
+                let row = self.list_state.selected().unwrap_or(0);
+                (!visible.is_empty()).then(|| row.min(visible.len() - 1))
+            });
+        self.visible = visible;
+        self.list_state.select(list_index);
+    }
+
+    fn selected_node(&self) -> Option<&TreeNode> {
+        self.tree_state
+            .selected()
+            .and_then(|index| self.tree.get(index))
+    }
+
+    fn selected_index(&self) -> Option<&(String, usize)> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.visible.get(index))
+    }
+
+    /// Returns the selected annotation with its project
+    #[must_use]
+    pub fn selected(&self) -> Option<(&str, &Codemark)> {
+        self.selected_index().map(|(project, index)| {
+            (
+                project.as_str(),
+                &self.projects_db.projects[project][*index],
+            )
+        })
+    }
+
+    /// Moves the selection of the focused pane by `delta` rows
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Focus::Tree => (&mut self.tree_state, self.tree.len()),
+            Focus::List => (&mut self.list_state, self.visible.len()),
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0);
+        state.select(Some(current.saturating_add_signed(delta).min(len - 1)));
+        if self.focus == Focus::Tree {
+            // Show the annotations of the new tree node from the top
+            self.list_state.select(Some(0));
+            self.refresh();
+        }
+    }
+
+    fn apply_filter(&mut self, filter: &str) {
+        if filter.trim().is_empty() {
+            self.query = None;
+            self.status.clear();
+        } else {
+            match filter.parse::<Query>() {
+                Ok(query) => {
+                    self.query = Some(query);
+                    self.status.clear();
+                }
+                // Keep the last valid query while the filter is being typed
+                Err(e) => self.status = e.to_string(),
+            }
+        }
+        self.refresh();
+    }
+
+    /// Handles a key press and returns what the event loop has to do
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+        if let Some(input) = &mut self.input {
+            match key.code {
+                // An invalid filter keeps the keyboard until it is fixed or cancelled
+                KeyCode::Enter if self.status.is_empty() => {
+                    self.filter = input.trim().to_string();
+                    self.input = None;
+                }
+                KeyCode::Esc => {
+                    self.input = None;
+                    let filter = self.filter.clone();
+                    self.apply_filter(&filter);
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                    let input = input.clone();
+                    self.apply_filter(&input);
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    let input = input.clone();
+                    self.apply_filter(&input);
+                }
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        self.status.clear();
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.apply_filter("");
+            }
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Tree => Focus::List,
+                    Focus::List => Focus::Tree,
+                };
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Tree,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::List,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(PAGE.cast_signed()),
+            KeyCode::PageUp => self.move_selection(-PAGE.cast_signed()),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Enter if self.focus == Focus::Tree => self.focus = Focus::List,
+            KeyCode::Enter | KeyCode::Char('o') => return self.open(),
+            KeyCode::Char('x') => return self.toggle_resolved(),
+            KeyCode::Char('y' | 'c') => return self.copy(),
+            KeyCode::Char('/') => self.input = Some(self.filter.clone()),
+            KeyCode::Char('a') => {
+                self.show_resolved = !self.show_resolved;
+                self.refresh();
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// The text of the filter being typed, or of the applied filter
+    fn filter_text(&self) -> &str {
+        self.input.as_deref().unwrap_or(&self.filter)
+    }
+
+    fn open(&mut self) -> Action {
+        let Some((project, codemark)) = self.selected() else {
+            return Action::None;
+        };
+        match codemark_path(&self.projects_db, project, codemark) {
+            Ok((path, line)) => Action::Open(path, line),
+            Err(e) => {
+                self.status = e.to_string();
+                Action::None
+            }
+        }
+    }
+
+    fn toggle_resolved(&mut self) -> Action {
+        let Some((project, index)) = self.selected_index().cloned() else {
+            return Action::None;
+        };
+        let now = self.now;
+        let codemark = &mut self
+            .projects_db
+            .projects
+            .get_mut(&project)
+            .expect("listed project")[index];
+        codemark.resolved = !codemark.resolved;
+        codemark.resolved_at = codemark.resolved.then_some(now);
+        self.toggled.push((project, codemark.clone()));
+        self.status = format!(
+            "Marked {} as {}",
+            codemark.location(),
+            if codemark.resolved {
+                "resolved"
+            } else {
+                "open"
+            }
+        );
+        self.refresh();
+        Action::Save
+    }
+
+    /// Applies the annotations toggled since the last save to a freshly loaded database
+    /// and shows it, so that saving keeps what a scan or watch stored in the meantime.
+    /// Toggled annotations that are gone from the database are dropped.
+    pub fn merge_toggles(&mut self, mut projects_db: ProjectsDatabase) {
+        for (project, toggled) in self.toggled.drain(..) {
+            let Some(codemarks) = projects_db.projects.get_mut(&project) else {
+                continue;
+            };
+            let same = |codemark: &Codemark| {
+                codemark.file.trim_start_matches("./") == toggled.file.trim_start_matches("./")
+                    && codemark.description == toggled.description
+            };
+            // Prefer the annotation at the same line, in case a description repeats
+            let index = codemarks
+                .iter()
+                .position(|codemark| same(codemark) && codemark.line_number == toggled.line_number)
+                .or_else(|| {
+                    codemarks.iter().position(|codemark| {
+                        same(codemark) && codemark.resolved != toggled.resolved
+                    })
+                });
+            if let Some(index) = index {
+                codemarks[index].resolved = toggled.resolved;
+                codemarks[index].resolved_at = toggled.resolved_at;
+            }
+        }
+        self.projects_db = projects_db;
+        self.refresh();
+    }
+
+    fn copy(&mut self) -> Action {
+        let Some((project, codemark)) = self.selected() else {
+            return Action::None;
+        };
+        let location = match codemark_path(&self.projects_db, project, codemark) {
+            Ok((path, line)) => format!("{}:{line}", path.display()),
+            Err(_) => codemark.location(),
+        };
+        self.status = format!("Copied {location}");
+        Action::Copy(location)
+    }
+
+    fn kind_style(&self, kind: Option<&str>) -> Style {
+        kind_color(&self.kinds, kind)
+            .and_then(color)
+            .map_or_else(Style::default, |color| Style::default().fg(color))
+    }
+
+    /// Draws the tree, the list, the preview and the status bar
+    pub fn render(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [tree, right] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(main);
+        let [list, preview] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+
+        self.render_tree(frame, tree);
+        self.render_list(frame, list);
+        self.render_preview(frame, preview);
+
+        let status_line = if let Some(input) = &self.input {
+            let mut line = Line::from(vec![Span::raw("/"), Span::raw(input.clone())]);
+            if !self.status.is_empty() {
+                line.push_span(Span::styled(
+                    format!("  {}", self.status),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            line
+        } else if self.status.is_empty() {
+            Line::styled(HELP, Style::default().add_modifier(Modifier::DIM))
+        } else {
+            Line::raw(self.status.clone())
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn pane(&self, title: String, focus: Focus) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.focus == focus {
+            block.border_style(Style::default().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+
+    fn render_tree(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .tree
+            .iter()
+            .map(|node| {
+                let text = match (&node.project, &node.file) {
+                    (None, _) => format!("All projects ({})", node.count),
+                    (Some(project), None) => format!("{project} ({})", node.count),
+                    (Some(_), Some(file)) => format!("  {file} ({})", node.count),
+                };
+                ListItem::new(text)
+            })
+            .collect();
+        let tree = List::new(items)
+            .block(self.pane("Projects".to_string(), Focus::Tree))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(tree, area, &mut self.tree_state);
+    }
+
+    fn render_list(&mut self, frame: &mut Frame, area: Rect) {
+        let several_projects = self
+            .selected_node()
+            .is_some_and(|node| node.project.is_none())
+            && self.tree.iter().filter(|node| node.file.is_none()).count() > 2;
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|(project, index)| {
+                let codemark = &self.projects_db.projects[project][*index];
+                let mut spans = Vec::new();
+                if codemark.resolved {
+                    spans.push(Span::styled("✓ ", Style::default().fg(Color::Green)));
+                }
+                spans.push(Span::styled(
+                    format!("{:<6} ", codemark.kind.as_deref().unwrap_or("")),
+                    self.kind_style(codemark.kind.as_deref()),
+                ));
+                if several_projects {
+                    spans.push(Span::raw(format!("{project}: ")));
+                }
+                spans.push(Span::styled(
+                    codemark.location(),
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+                spans.push(Span::raw(format!(" {}", codemark.description)));
+                let line = Line::from(spans);
+                if codemark.resolved {
+                    ListItem::new(line.style(Style::default().add_modifier(Modifier::DIM)))
+                } else {
+                    ListItem::new(line)
+                }
+            })
+            .collect();
+        let mut title = format!("Annotations ({})", self.visible.len());
+        if !self.filter_text().is_empty() {
+            title.push_str(&format!(" - filter: {}", self.filter_text()));
+        }
+        if self.show_resolved {
+            title.push_str(" - including resolved");
+        }
+        let list = List::new(items)
+            .block(self.pane(title, Focus::List))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list_state);
+    }
+
+    fn render_preview(&self, frame: &mut Frame, area: Rect) {
+        let Some((project, codemark)) = self.selected() else {
+            frame.render_widget(
+                Paragraph::new("No annotation selected").block(Block::bordered().title("Preview")),
+                area,
+            );
+            return;
+        };
+        let title = format!("{project}: {}", codemark.location());
+        let lines = match self.projects_db.roots.get(project) {
+            Some(root) => preview_lines(&root.join(&codemark.file), codemark),
+            None => vec![Line::raw(
+                "The location of the project is unknown, scan it again to record it",
+            )],
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// Reads the source lines around an annotation, within its notebook cell or code block
+fn preview_lines(path: &Path, codemark: &Codemark) -> Vec<Line<'static>> {
+    let Ok(source) = read_source_lines(path) else {
+        return vec![Line::raw(format!("Cannot read {}", path.display()))];
+    };
+    let first = codemark.line_number.saturating_sub(PREVIEW_CONTEXT);
+    let last = codemark.line_number + PREVIEW_CONTEXT;
+    source
+        .into_iter()
+        .filter(|line| line.cell == codemark.cell && (first..=last).contains(&line.line_number))
+        .map(|line| {
+            let text = format!("{:>5} │ {}", line.line_number, line.text.trim_end());
+            if line.line_number == codemark.line_number {
+                Line::styled(
+                    text,
+                    Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                )
+            } else {
+                Line::raw(text)
+            }
+        })
+        .collect()
+}
+
+fn color(name: &str) -> Option<Color> {
+    Some(match name {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        _ => return None,
+    })
+}
+
+/// Copies text to the clipboard of the terminal with an OSC 52 sequence
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(group >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Runs the terminal UI until it is closed
+pub fn run_tui() -> Result<()> {
+    let layered = load_global_config(Path::new("."), &ConfigOverrides::default(), false)?;
+    let projects_db = load_global_projects(false);
+    let mut app = App::new(
+        projects_db,
+        layered.config.kinds.clone(),
+        current_timestamp(),
+    );
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, layered.config.editor.as_deref());
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App, editor: Option<&str>) -> Result<()> {
+    loop {
+        terminal.draw(|frame| app.render(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Open(path, line) => {
+                // Hand the terminal to the editor until it exits
+                ratatui::restore();
+                let result = launch_editor(editor, &path, line);
+                *terminal = ratatui::init();
+                if let Err(e) = result {
+                    app.status = e.to_string();
+                }
+            }
+            Action::Copy(text) => copy_to_clipboard(&text)?,
+            Action::Save => {
+                app.merge_toggles(load_global_projects(false));
+                save_global_projects(&app.projects_db, false)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
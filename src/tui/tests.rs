@@ -0,0 +1,247 @@
+use super::*;
+use crate::default_kinds;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::collections::HashMap;
+
+fn codemark(file: &str, line_number: usize, kind: &str, description: &str) -> Codemark {
+    Codemark {
+        file: file.to_string(),
+        line_number,
+        kind: Some(kind.to_string()),
+        description: description.to_string(),
+        ..Default::default()
+    }
+}
+
+fn app(root: Option<&Path>) -> App {
+    let mut projects_db = ProjectsDatabase {
+        projects: HashMap::from([
+            (
+                "api".to_string(),
+                vec![
+                    codemark("src/db.rs", 3, "TODO", "add an index"),
+                    codemark("src/db.rs", 9, "FIXME", "retry on timeout"),
+                    codemark("src/main.rs", 1, "HACK", "hardcoded port"),
+                ],
+            ),
+            (
+                "web".to_string(),
+                vec![codemark("app.js", 2, "TODO", "debounce input")],
+            ),
+        ]),
+        ..Default::default()
+    };
+    if let Some(root) = root {
+        projects_db
+            .roots
+            .insert("api".to_string(), root.to_path_buf());
+    }
+    App::new(projects_db, default_kinds(), 0)
+}
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn press(app: &mut App, keys: &str) {
+    for c in keys.chars() {
+        app.handle_key(key(KeyCode::Char(c)));
+    }
+}
+
+fn screen(app: &mut App) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+    terminal.draw(|frame| app.render(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn selected_description(app: &App) -> Option<String> {
+    app.selected()
+        .map(|(_, codemark)| codemark.description.clone())
+}
+
+#[test]
+fn test_render() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/db.rs"),
+        "use std::fs;\n\n// TODO: add an index\nfn query() {}\n",
+    )
+    .unwrap();
+    let mut app = app(Some(dir.path()));
+    let screen = screen(&mut app);
+
+    assert!(screen.contains("All projects (4)"));
+    assert!(screen.contains("api (3)"));
+    assert!(screen.contains("  src/db.rs (2)"));
+    assert!(screen.contains("web (1)"));
+    assert!(screen.contains("Annotations (4)"));
+    assert!(screen.contains("api: src/db.rs:3 add an index"));
+    assert!(screen.contains("web: app.js:2 debounce input"));
+    // The preview shows the source around the selected annotation
+    assert!(screen.contains("api: src/db.rs:3"));
+    assert!(screen.contains("    3 │ // TODO: add an index"));
+    assert!(screen.contains("    4 │ fn query() {}"));
+    assert!(screen.contains("q quit"));
+}
+
+#[test]
+fn test_preview_without_root() {
+    let mut app = app(None);
+    assert!(screen(&mut app).contains("The location of the project is unknown"));
+}
+
+#[test]
+fn test_navigation() {
+    let mut app = app(None);
+    assert_eq!(selected_description(&app).unwrap(), "add an index");
+    press(&mut app, "jj");
+    assert_eq!(selected_description(&app).unwrap(), "hardcoded port");
+    press(&mut app, "G");
+    assert_eq!(selected_description(&app).unwrap(), "debounce input");
+    press(&mut app, "g");
+    assert_eq!(selected_description(&app).unwrap(), "add an index");
+
+    // Selecting a tree node narrows the list
+    app.handle_key(key(KeyCode::Tab));
+    press(&mut app, "jj");
+    assert!(screen(&mut app).contains("Annotations (2)"));
+    assert_eq!(selected_description(&app).unwrap(), "add an index");
+    press(&mut app, "jj");
+    let screen = screen(&mut app);
+    assert!(screen.contains("Annotations (1)"));
+    assert!(screen.contains("app.js:2 debounce input"));
+    assert_eq!(app.handle_key(key(KeyCode::Enter)), Action::None);
+    assert_eq!(app.handle_key(key(KeyCode::Char('q'))), Action::Quit);
+}
+
+#[test]
+fn test_filter() {
+    let mut app = app(None);
+    press(&mut app, "/debounce");
+    assert!(screen(&mut app).contains("Annotations (1) - filter: debounce"));
+    for _ in 0.."debounce".len() {
+        app.handle_key(key(KeyCode::Backspace));
+    }
+    press(&mut app, "kind:");
+    // The last valid query applies while the filter is incomplete, and Enter waits
+    // for a valid one
+    assert_eq!(app.status, "Missing value for 'kind' in query");
+    assert!(screen(&mut app).contains("Annotations (0) - filter: kind:"));
+    app.handle_key(key(KeyCode::Enter));
+    assert!(screen(&mut app).contains("/kind:"));
+    press(&mut app, "TODO");
+    assert!(app.status.is_empty());
+    app.handle_key(key(KeyCode::Enter));
+    let screen_text = screen(&mut app);
+    assert!(screen_text.contains("Annotations (2) - filter: kind:TODO"));
+    assert!(screen_text.contains("All projects (2)"));
+    assert!(!screen_text.contains("src/main.rs"));
+
+    // Esc while typing restores the applied filter, and then clears it
+    press(&mut app, "/ x");
+    app.handle_key(key(KeyCode::Esc));
+    assert!(screen(&mut app).contains("Annotations (2) - filter: kind:TODO"));
+    assert_eq!(app.handle_key(key(KeyCode::Esc)), Action::None);
+    assert!(screen(&mut app).contains("Annotations (4)"));
+    assert_eq!(app.handle_key(key(KeyCode::Esc)), Action::Quit);
+}
+
+#[test]
+fn test_toggle_resolved() {
+    let mut app = app(None);
+    app.now = 1_700_000_000;
+    assert_eq!(app.handle_key(key(KeyCode::Char('x'))), Action::Save);
+    let resolved = &app.projects_db.projects["api"][0];
+    assert!(resolved.resolved);
+    assert_eq!(resolved.resolved_at, Some(1_700_000_000));
+    assert_eq!(app.status, "Marked src/db.rs:3 as resolved");
+    // Resolved annotations are hidden until shown with 'a'
+    assert_eq!(selected_description(&app).unwrap(), "retry on timeout");
+    assert!(screen(&mut app).contains("Annotations (3)"));
+
+    press(&mut app, "a");
+    assert!(screen(&mut app).contains("Annotations (4) - including resolved"));
+    press(&mut app, "k");
+    assert_eq!(app.handle_key(key(KeyCode::Char('x'))), Action::Save);
+    let reopened = &app.projects_db.projects["api"][0];
+    assert!(!reopened.resolved);
+    assert_eq!(reopened.resolved_at, None);
+}
+
+#[test]
+fn test_merge_toggles_keeps_other_updates() {
+    let stored = || app(None).projects_db;
+    let mut app = app(None);
+    app.now = 1_700_000_000;
+    assert_eq!(app.handle_key(key(KeyCode::Char('x'))), Action::Save);
+
+    // Meanwhile a scan moved the annotation and found a new one
+    let mut latest = stored();
+    latest.projects.get_mut("api").unwrap()[0].line_number = 5;
+    latest
+        .projects
+        .get_mut("web")
+        .unwrap()
+        .push(codemark("app.js", 8, "FIXME", "handle errors"));
+    app.merge_toggles(latest);
+
+    let resolved = &app.projects_db.projects["api"][0];
+    assert!(resolved.resolved);
+    assert_eq!(resolved.resolved_at, Some(1_700_000_000));
+    assert_eq!(resolved.line_number, 5);
+    assert_eq!(app.projects_db.projects["web"].len(), 2);
+    assert!(screen(&mut app).contains("handle errors"));
+
+    // Toggles are only applied once
+    let mut latest = stored();
+    latest.projects.get_mut("api").unwrap()[0].resolved = false;
+    app.merge_toggles(latest);
+    assert!(!app.projects_db.projects["api"][0].resolved);
+}
+
+#[test]
+fn test_open_and_copy() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/db.rs"), "// TODO: add an index\n").unwrap();
+    let mut app = app(Some(dir.path()));
+    let path = dir.path().join("src/db.rs");
+
+    assert_eq!(
+        app.handle_key(key(KeyCode::Char('o'))),
+        Action::Open(path.clone(), 3)
+    );
+    assert_eq!(
+        app.handle_key(key(KeyCode::Char('y'))),
+        Action::Copy(format!("{}:3", path.display()))
+    );
+
+    // Without a root, the annotation cannot be opened and its location is copied as is
+    press(&mut app, "G");
+    assert_eq!(app.handle_key(key(KeyCode::Enter)), Action::None);
+    assert!(app.status.contains("scan it again"));
+    assert_eq!(
+        app.handle_key(key(KeyCode::Char('y'))),
+        Action::Copy("app.js:2".to_string())
+    );
+}
+
+#[test]
+fn test_base64() {
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foo"), "Zm9v");
+    assert_eq!(base64(b"src/db.rs:3"), "c3JjL2RiLnJzOjM=");
+}
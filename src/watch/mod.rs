@@ -1,49 +1,59 @@
+use crate::config_layers::ConfigOverrides;
+use crate::filters::FileSelection;
+use crate::formats::source_lines;
+use crate::matcher::Matcher;
+use crate::project_detection::{find_project_root, project_name_for_root};
 use crate::{
     Codemark, detect_project_name, load_global_config, load_global_projects, save_global_projects,
 };
 use anyhow::Result;
 use ignore::WalkBuilder;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use regex::Regex;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::time::{Duration, Instant};
 
-/// Scans a single file for code annotations and returns found codemarks
-fn scan_file(file_path: &Path, annotation_pattern: &Regex) -> Result<Vec<Codemark>> {
+/// Scans a single file for code annotations and returns found codemarks, with paths
+/// relative to `project_root` like `scan` stores them
+fn scan_file(file_path: &Path, project_root: &Path, matcher: &Matcher) -> Result<Vec<Codemark>> {
     let content = fs::read_to_string(file_path)?;
     let mut codemarks = Vec::new();
+    let relative_path = relative_path(file_path, project_root);
 
-    for (line_number, line) in content.lines().enumerate() {
-        if let Some(captures) = annotation_pattern.captures(line)
-            && let Some(description) = captures.get(1)
-        {
-            let codemark = Codemark {
-                file: file_path.to_string_lossy().to_string(),
-                line_number: line_number + 1,
-                description: description.as_str().trim().to_string(),
-                resolved: false,
-            };
-            codemarks.push(codemark);
-        }
+    let lines = source_lines(file_path, &content);
+    for (line, line_match) in matcher.match_file_lines(file_path, lines).matches {
+        let codemark = Codemark {
+            file: relative_path.clone(),
+            line_number: line.line_number,
+            cell: line.cell,
+            column: Some(line_match.column),
+            description: line_match.description,
+            resolved: false,
+            kind: line_match.kind,
+            owner: line_match.owner,
+            issue: line_match.issue,
+            ..Default::default()
+        };
+        codemarks.push(codemark);
     }
 
     Ok(codemarks)
 }
 
-/// Checks if a file should be ignored based on ignore patterns
-fn should_ignore_file(file_path: &Path, ignore_patterns: &[String]) -> bool {
-    let file_str = file_path.to_string_lossy();
-
-    for pattern in ignore_patterns {
-        if file_str.contains(pattern) {
-            return true;
-        }
-    }
+/// Returns the path of a file relative to the root of its project
+fn relative_path(file_path: &Path, project_root: &Path) -> String {
+    file_path
+        .strip_prefix(project_root)
+        .unwrap_or(file_path)
+        .to_string_lossy()
+        .to_string()
+}
 
-    // Skip common non-source file extensions
+/// Checks if a file is a common non-source file, such as an image or an archive. Ignore
+/// patterns are checked by the file matcher of the selection.
+fn should_ignore_file(file_path: &Path) -> bool {
     if let Some(extension) = file_path.extension() {
         let ext = extension.to_string_lossy().to_lowercase();
         matches!(
@@ -87,13 +97,13 @@ fn should_ignore_file(file_path: &Path, ignore_patterns: &[String]) -> bool {
 /// Processes a changed file by scanning it for annotations
 fn process_changed_file(
     file_path: &Path,
-    ignore_patterns: &[String],
-    annotation_pattern: &Regex,
+    project_root: &Path,
+    matcher: &Matcher,
     project_name: &str,
     ephemeral: bool,
 ) -> Result<usize> {
     // Check if file should be ignored
-    if should_ignore_file(file_path, ignore_patterns) {
+    if should_ignore_file(file_path) {
         return Ok(0);
     }
 
@@ -109,7 +119,11 @@ fn process_changed_file(
             // File is readable as text, proceed with scanning
             println!("Scanning changed file: {}", file_path.display());
 
-            match scan_file(file_path, annotation_pattern) {
+            // Older scans of `.` stored paths with a ./ prefix
+            let relative_path = relative_path(file_path, project_root);
+            let other_file =
+                |codemark: &Codemark| codemark.file.trim_start_matches("./") != relative_path;
+            match scan_file(file_path, project_root, matcher) {
                 Ok(codemarks) => {
                     if codemarks.is_empty() {
                         // No annotations found, but still need to clean up old ones
@@ -119,8 +133,7 @@ fn process_changed_file(
                                 projects_db.projects.get_mut(project_name)
                             {
                                 let old_count = project_codemarks.len();
-                                project_codemarks
-                                    .retain(|cm| cm.file != file_path.to_string_lossy());
+                                project_codemarks.retain(other_file);
                                 let new_count = project_codemarks.len();
                                 if old_count != new_count {
                                     save_global_projects(&projects_db, false)?;
@@ -137,8 +150,7 @@ fn process_changed_file(
                             if let Some(project_codemarks) =
                                 projects_db.projects.get_mut(project_name)
                             {
-                                project_codemarks
-                                    .retain(|cm| cm.file != file_path.to_string_lossy());
+                                project_codemarks.retain(other_file);
                             } else {
                                 projects_db
                                     .projects
@@ -157,10 +169,7 @@ fn process_changed_file(
 
                         println!("  Found {} annotations:", codemarks.len());
                         for codemark in &codemarks {
-                            println!(
-                                "    Line {}: {}",
-                                codemark.line_number, codemark.description
-                            );
+                            println!("    {}: {}", codemark.location(), codemark.description);
                         }
 
                         Ok(codemarks.len())
@@ -179,27 +188,42 @@ fn process_changed_file(
     }
 }
 
+/// Debounce time in milliseconds when neither the command line nor the config sets one
+pub const DEFAULT_DEBOUNCE_MS: u64 = 2000;
+
 /// Main watch function that monitors a directory for changes
 pub fn watch_directory(
     directory: &Path,
     ignore_patterns: &[String],
+    selection: &FileSelection,
     debounce_ms: Option<u64>,
     ephemeral: bool,
 ) -> Result<()> {
-    let config = load_global_config(ephemeral);
-    let annotation_pattern = Regex::new(&config.annotation_pattern)
-        .map_err(|e| anyhow::anyhow!("Invalid regex pattern: {e}"))?;
+    let overrides = ConfigOverrides {
+        ignore: ignore_patterns.to_vec(),
+        debounce: debounce_ms,
+        ..Default::default()
+    };
+    let layered = load_global_config(directory, &overrides, ephemeral)?;
+    let config = &layered.config;
+    let debounce_ms = config.debounce.unwrap_or(DEFAULT_DEBOUNCE_MS);
+    let matcher = Matcher::new(config)?;
+    let ignore_patterns = &layered.ignore;
 
     // Intelligently detect the project name from configuration files
-    let project_name = detect_project_name(directory);
+    let project_name = layered
+        .project_name_for(directory)
+        .unwrap_or_else(|| detect_project_name(directory));
+    let watch_root = directory.canonicalize()?;
+    let file_matcher = selection.matcher(&watch_root, ignore_patterns)?;
 
     println!("Watching directory: {}", directory.display());
     println!("Project name: {project_name}");
     if !ignore_patterns.is_empty() {
         println!("Ignore patterns: {ignore_patterns:?}");
     }
-    println!("Annotation pattern: {}", config.annotation_pattern);
-    println!("Debounce: {}ms", debounce_ms.unwrap_or(500));
+    println!("Annotation pattern: {}", config.effective_pattern());
+    println!("Debounce: {debounce_ms}ms");
     println!("Press Ctrl+C to stop watching...\n");
 
     // Create a channel to receive file system events
@@ -209,11 +233,11 @@ pub fn watch_directory(
     let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
 
     // Watch the directory recursively
-    watcher.watch(directory, RecursiveMode::Recursive)?;
+    watcher.watch(&watch_root, RecursiveMode::Recursive)?;
 
     // Track recent events to implement debouncing
     let mut recent_events: HashMap<PathBuf, Instant> = HashMap::new();
-    let debounce_duration = Duration::from_millis(debounce_ms.unwrap_or(500));
+    let debounce_duration = Duration::from_millis(debounce_ms);
 
     // Process events
     loop {
@@ -240,7 +264,7 @@ pub fn watch_directory(
                                             break;
                                         }
                                     }
-                                    if !should_process {
+                                    if !should_process || !file_matcher.is_selected(&path) {
                                         continue;
                                     }
 
@@ -253,12 +277,24 @@ pub fn watch_directory(
                                     }
                                     recent_events.insert(path.clone(), now);
 
+                                    // Attribute the file to its nearest enclosing project
+                                    let project_root = find_project_root(&path, &watch_root);
+                                    let file_project = if project_root == watch_root {
+                                        project_name.clone()
+                                    } else {
+                                        project_name_for_root(
+                                            &load_global_projects(ephemeral).roots,
+                                            &project_root,
+                                            detect_project_name(&project_root),
+                                        )
+                                    };
+
                                     // Process the file
                                     match process_changed_file(
                                         &path,
-                                        ignore_patterns,
-                                        &annotation_pattern,
-                                        &project_name,
+                                        &project_root,
+                                        &matcher,
+                                        &file_project,
                                         ephemeral,
                                     ) {
                                         Ok(count) => {
@@ -1,4 +1,5 @@
 use super::*;
+use crate::default_kinds;
 use std::env;
 use tempfile::tempdir;
 
@@ -21,8 +22,8 @@ fn test_scan_file_with_annotations() {
     )
     .unwrap();
 
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
-    let result = scan_file(&test_file, &pattern).unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
+    let result = scan_file(&test_file, temp_dir.path(), &pattern).unwrap();
 
     assert_eq!(result.len(), 2);
     assert_eq!(result[0].description, "Fix this");
@@ -40,57 +41,54 @@ fn test_scan_file_without_annotations() {
     )
     .unwrap();
 
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
-    let result = scan_file(&test_file, &pattern).unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
+    let result = scan_file(&test_file, temp_dir.path(), &pattern).unwrap();
 
     assert_eq!(result.len(), 0);
 }
 
 #[test]
-fn test_should_ignore_file_with_patterns() {
+fn test_ignore_patterns_with_glob() {
     setup_test_env();
-    let file_path = Path::new("/path/to/test.rs");
-    let ignore_patterns = vec!["test.rs".to_string()];
-
-    assert!(should_ignore_file(file_path, &ignore_patterns));
+    let temp_dir = tempdir().unwrap();
+    let ignore_patterns = vec!["*.min.js".to_string()];
+    let matcher = FileSelection::default()
+        .matcher(temp_dir.path(), &ignore_patterns)
+        .unwrap();
+
+    assert!(!matcher.is_selected(&temp_dir.path().join("dist/app.min.js")));
+    assert!(matcher.is_selected(&temp_dir.path().join("src/app.js")));
+    // Patterns are globs, not substrings of the path
+    assert!(matcher.is_selected(&temp_dir.path().join("src/min.js.rs")));
 }
 
 #[test]
 fn test_should_ignore_file_binary_extensions() {
     setup_test_env();
     let file_path = Path::new("/path/to/image.jpg");
-    let ignore_patterns = vec![];
 
-    assert!(should_ignore_file(file_path, &ignore_patterns));
+    assert!(should_ignore_file(file_path));
 }
 
 #[test]
 fn test_should_not_ignore_source_file() {
     setup_test_env();
     let file_path = Path::new("/path/to/source.rs");
-    let ignore_patterns = vec![];
 
-    assert!(!should_ignore_file(file_path, &ignore_patterns));
+    assert!(!should_ignore_file(file_path));
 }
 
 #[test]
 fn test_process_changed_file_ignored() {
     setup_test_env();
     let temp_dir = tempdir().unwrap();
-    let test_file = temp_dir.path().join("ignored.txt");
+    let test_file = temp_dir.path().join("ignored.lock");
     fs::write(&test_file, "// TODO: This should be ignored").unwrap();
 
-    let ignore_patterns = vec!["ignored.txt".to_string()];
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
 
-    let result = process_changed_file(
-        &test_file,
-        &ignore_patterns,
-        &pattern,
-        "test_project",
-        false,
-    )
-    .unwrap();
+    let result =
+        process_changed_file(&test_file, temp_dir.path(), &pattern, "test_project", false).unwrap();
     assert_eq!(result, 0);
 }
 
@@ -98,12 +96,11 @@ fn test_process_changed_file_ignored() {
 fn test_process_changed_file_nonexistent() {
     setup_test_env();
     let nonexistent_file = Path::new("/nonexistent/file.rs");
-    let ignore_patterns = vec![];
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
 
     let result = process_changed_file(
         nonexistent_file,
-        &ignore_patterns,
+        Path::new("/nonexistent"),
         &pattern,
         "test_project",
         false,
@@ -123,17 +120,10 @@ fn test_process_changed_file_with_annotations() {
     )
     .unwrap();
 
-    let ignore_patterns = vec![];
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
 
-    let result = process_changed_file(
-        &test_file,
-        &ignore_patterns,
-        &pattern,
-        "test_project",
-        false,
-    )
-    .unwrap();
+    let result =
+        process_changed_file(&test_file, temp_dir.path(), &pattern, "test_project", false).unwrap();
     assert_eq!(result, 2); // Should find 2 annotations
 }
 
@@ -144,17 +134,10 @@ fn test_process_changed_file_empty_file() {
     let test_file = temp_dir.path().join("empty.rs");
     fs::write(&test_file, "").unwrap();
 
-    let ignore_patterns = vec![];
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
 
-    let result = process_changed_file(
-        &test_file,
-        &ignore_patterns,
-        &pattern,
-        "test_project",
-        false,
-    )
-    .unwrap();
+    let result =
+        process_changed_file(&test_file, temp_dir.path(), &pattern, "test_project", false).unwrap();
     assert_eq!(result, 0);
 }
 
@@ -166,12 +149,11 @@ fn test_process_changed_file_binary_file() {
     // Write some binary data
     fs::write(&binary_file, b"\x00\x01\x02\x03\xFF").unwrap();
 
-    let ignore_patterns = vec![];
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
 
     let result = process_changed_file(
         &binary_file,
-        &ignore_patterns,
+        temp_dir.path(),
         &pattern,
         "test_project",
         false,
@@ -188,10 +170,10 @@ fn test_scan_file_invalid_utf8() {
     // Write invalid UTF-8 bytes
     fs::write(&test_file, b"\xFF\xFE// TODO: This has invalid UTF-8").unwrap();
 
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
 
     // This should handle the error gracefully
-    let result = scan_file(&test_file, &pattern);
+    let result = scan_file(&test_file, temp_dir.path(), &pattern);
     assert!(result.is_err());
 }
 
@@ -205,8 +187,8 @@ fn test_scan_file_different_annotation_types() {
         "// TODO: Task 1\n# FIXME: Bug in shell script\n<!-- HACK: Quick fix -->\n* NOTE: Important note\nlet x = 5;",
     ).unwrap();
 
-    let pattern = Regex::new(r"(?i)(?://|#|<!--|\*)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
-    let result = scan_file(&test_file, &pattern).unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--|\*)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
+    let result = scan_file(&test_file, temp_dir.path(), &pattern).unwrap();
 
     assert_eq!(result.len(), 4);
     assert_eq!(result[0].description, "Task 1");
@@ -216,29 +198,22 @@ fn test_scan_file_different_annotation_types() {
 }
 
 #[test]
-fn test_should_ignore_file_multiple_patterns() {
-    setup_test_env();
-    let file_path = Path::new("/path/to/build/output.js");
-    let ignore_patterns = vec![
-        "*.tmp".to_string(),
-        "build/".to_string(),
-        "node_modules/".to_string(),
-    ];
-
-    assert!(should_ignore_file(file_path, &ignore_patterns));
-}
-
-#[test]
-fn test_should_ignore_file_no_match() {
+fn test_ignore_patterns_multiple() {
     setup_test_env();
-    let file_path = Path::new("/src/main.rs");
+    let temp_dir = tempdir().unwrap();
     let ignore_patterns = vec![
         "*.tmp".to_string(),
         "build/".to_string(),
         "node_modules/".to_string(),
     ];
-
-    assert!(!should_ignore_file(file_path, &ignore_patterns));
+    let matcher = FileSelection::default()
+        .matcher(temp_dir.path(), &ignore_patterns)
+        .unwrap();
+
+    assert!(!matcher.is_selected(&temp_dir.path().join("build/output.js")));
+    assert!(!matcher.is_selected(&temp_dir.path().join("web/node_modules/lib/index.js")));
+    assert!(!matcher.is_selected(&temp_dir.path().join("notes.tmp")));
+    assert!(matcher.is_selected(&temp_dir.path().join("src/main.rs")));
 }
 
 #[test]
@@ -259,7 +234,7 @@ fn test_should_ignore_file_all_binary_extensions() {
 
     for ext in binary_extensions {
         let file_path = Path::new(ext);
-        assert!(should_ignore_file(file_path, &[]), "Should ignore {ext}");
+        assert!(should_ignore_file(file_path), "Should ignore {ext}");
     }
 }
 
@@ -279,10 +254,7 @@ fn test_should_not_ignore_source_extensions() {
 
     for ext in source_extensions {
         let file_path = Path::new(ext);
-        assert!(
-            !should_ignore_file(file_path, &[]),
-            "Should not ignore {ext}"
-        );
+        assert!(!should_ignore_file(file_path), "Should not ignore {ext}");
     }
 }
 
@@ -296,8 +268,8 @@ fn test_scan_file_line_numbers_correct() {
         "fn main() {\n    println!(\"Hello\");\n    // TODO: Line 3 task\n    let x = 5;\n    // FIXME: Line 5 bug\n}",
     ).unwrap();
 
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
-    let result = scan_file(&test_file, &pattern).unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
+    let result = scan_file(&test_file, temp_dir.path(), &pattern).unwrap();
 
     assert_eq!(result.len(), 2);
     assert_eq!(result[0].line_number, 3);
@@ -317,8 +289,8 @@ fn test_scan_file_with_complex_regex() {
     ).unwrap();
 
     // More complex regex that captures assignee in parentheses
-    let pattern = Regex::new(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)").unwrap();
-    let result = scan_file(&test_file, &pattern).unwrap();
+    let pattern = Matcher::with_pattern(r"(?i)(?://|#|<!--)\s*(?:TODO|FIXME|HACK|NOTE|BUG|OPTIMIZE|REVIEW)(?:\([^)]*\))?\s*:?\s*(.*)", &default_kinds()).unwrap();
+    let result = scan_file(&test_file, temp_dir.path(), &pattern).unwrap();
 
     assert_eq!(result.len(), 4);
     assert_eq!(result[0].description, "Assigned task");
@@ -334,3 +306,26 @@ fn setup_temp_home() -> tempfile::TempDir {
     }
     temp_dir
 }
+
+#[test]
+fn test_watch_after_scan_keeps_one_record() {
+    let _temp_home = setup_temp_home();
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    let test_file = root.join("src/main.rs");
+    fs::write(&test_file, "// TODO: Important task\nfn main() {}\n").unwrap();
+    crate::scan::scan_directory(&root, &[], &FileSelection::default(), false, false).unwrap();
+
+    fs::write(&test_file, "fn main() {}\n// TODO: Important task\n").unwrap();
+    let matcher = Matcher::new(&crate::CodemarksConfig::default()).unwrap();
+    let project_name = detect_project_name(&root);
+    let result = process_changed_file(&test_file, &root, &matcher, &project_name, false).unwrap();
+    assert_eq!(result, 1);
+
+    let projects_db = load_global_projects(false);
+    let codemarks = &projects_db.projects[&project_name];
+    assert_eq!(codemarks.len(), 1);
+    assert_eq!(codemarks[0].file, "src/main.rs");
+    assert_eq!(codemarks[0].line_number, 2);
+}
@@ -111,6 +111,305 @@ fn test_list_command_empty() {
         .stdout(predicate::str::contains("No code annotations found"));
 }
 
+fn write_projects(home: &std::path::Path, projects_json: &str) {
+    let config_dir = home.join(".codemarks");
+    fs::create_dir_all(&config_dir).expect("Failed to create config directory");
+    fs::write(config_dir.join("projects.json"), projects_json)
+        .expect("Failed to write projects file");
+}
+
+#[test]
+fn test_list_command_filters() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    write_projects(
+        temp_home.path(),
+        r#"{"projects": {
+            "api": [
+                {"file": "src/db.rs", "line_number": 3, "description": "add an index", "kind": "TODO"},
+                {"file": "src/db.rs", "line_number": 9, "description": "retry on timeout", "kind": "FIXME"},
+                {"file": "docs/guide.md", "line_number": 1, "description": "explain the index", "kind": "TODO"},
+                {"file": "src/old.rs", "line_number": 5, "description": "drop the index", "kind": "TODO", "resolved": true}
+            ],
+            "web": [
+                {"file": "src/app.js", "line_number": 2, "description": "index the routes", "kind": "TODO"}
+            ]
+        }}"#,
+    );
+    let list = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(temp_home.path())
+            .arg("list")
+            .args(args)
+            .assert()
+            .success()
+    };
+
+    list(&[
+        "--project",
+        "api",
+        "--file",
+        "src/**",
+        "--kind",
+        "todo",
+        "--status",
+        "open",
+        "--grep",
+        "INDEX",
+    ])
+    .stdout(predicate::str::contains("src/db.rs:3 add an index"))
+    .stdout(predicate::str::contains("retry on timeout").not())
+    .stdout(predicate::str::contains("explain the index").not())
+    .stdout(predicate::str::contains("drop the index").not())
+    .stdout(predicate::str::contains("index the routes").not());
+
+    list(&["--status", "resolved"])
+        .stdout(predicate::str::contains("drop the index"))
+        .stdout(predicate::str::contains("add an index").not());
+
+    list(&["--grep", "index", "--limit", "2"]).stdout(predicate::str::contains(
+        "Showing 2 of 4 matching annotations.",
+    ));
+
+    list(&["--kind", "HACK"]).stdout(predicate::str::contains(
+        "No code annotations match the given filters.",
+    ));
+}
+
+#[test]
+fn test_query_and_clean_with_query() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    write_projects(
+        temp_home.path(),
+        r#"{"projects": {
+            "api": [
+                {"file": "src/db.rs", "line_number": 3, "description": "add an index", "kind": "FIXME", "owner": "alice", "created_at": 1000},
+                {"file": "src/db.rs", "line_number": 9, "description": "retry", "kind": "FIXME", "owner": "bob", "created_at": 1000},
+                {"file": "src/old.rs", "line_number": 5, "description": "old cache", "kind": "FIXME", "owner": "alice", "resolved": true, "resolved_at": 1000},
+                {"file": "src/new.rs", "line_number": 5, "description": "new cache", "kind": "FIXME", "owner": "alice", "resolved": true, "resolved_at": 1900000000}
+            ]
+        }}"#,
+    );
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(temp_home.path());
+        cmd
+    };
+
+    codemarks()
+        .args([
+            "query",
+            "kind:FIXME owner:alice age>30d path:src/** -resolved",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("src/db.rs:3 add an index"))
+        .stdout(predicate::str::contains("retry").not())
+        .stdout(predicate::str::contains("cache").not());
+
+    codemarks()
+        .args(["query", "owner:"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Missing value for 'owner' in query",
+        ));
+
+    // Only the resolved annotations matching the query are removed
+    codemarks()
+        .args(["clean", "--query", "resolved<2020-01-01"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Successfully removed 1 resolved annotations",
+        ));
+    codemarks()
+        .args(["query", "cache"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("new cache"))
+        .stdout(predicate::str::contains("old cache").not());
+}
+
+#[test]
+fn test_ci_command_with_query() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    create_test_files(temp_dir.path());
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .arg("--query")
+        .arg("kind:FIXME path:*.rs")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Found 1 codemarks matching pattern.",
+        ));
+}
+
+#[test]
+fn test_ci_query_by_age_uses_commit_dates() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    fs::write(test_dir.path().join("old.rs"), "// TODO: old\n").expect("Failed to write file");
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .current_dir(test_dir.path())
+            .env("GIT_AUTHOR_DATE", "2020-01-01T00:00:00Z")
+            .env("GIT_COMMITTER_DATE", "2020-01-01T00:00:00Z")
+            .args([
+                "-c",
+                "user.name=Alice",
+                "-c",
+                "user.email=alice@example.com",
+            ])
+            .args(args)
+            .status()
+            .expect("Failed to run git");
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "Add annotation"]);
+    // Not committed yet, so it is as old as the run
+    fs::write(test_dir.path().join("new.rs"), "// TODO: new\n").expect("Failed to write file");
+
+    let ci = |query: &str| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .args(["ci", "--query", query, "--directory"])
+            .arg(test_dir.path())
+            .output()
+            .expect("Failed to run ci")
+    };
+
+    let output = ci("age>30d");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("old.rs"));
+    assert!(!stdout.contains("new.rs"));
+    assert!(stdout.contains("Found 1 codemarks matching pattern."));
+    assert!(String::from_utf8_lossy(&ci("age<1d").stdout).contains("new.rs"));
+}
+
+#[test]
+fn test_machine_readable_formats() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(temp_home.path());
+        cmd
+    };
+
+    let output = codemarks()
+        .args(["scan", "--format", "json", "--directory"])
+        .arg(test_dir.path())
+        .output()
+        .expect("Failed to run scan");
+    let records: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("scan did not print JSON");
+    let records = records.as_array().expect("Expected an array");
+    assert_eq!(records.len(), 5);
+    assert!(records.iter().all(|record| record["status"] == "open"));
+    assert!(
+        records
+            .iter()
+            .any(|record| record["file"] == "test.rs" && record["line"] == 2)
+    );
+
+    codemarks()
+        .args(["list", "--all-projects", "--kind", "HACK", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "project,file,line,cell,kind,status,description,owner,issue,author,created_at,resolved_at,severity,column\n",
+        ))
+        .stdout(predicate::str::contains(",test.rs,8,,HACK,open,Quick workaround,"))
+        .stdout(predicate::str::contains("Showing").not());
+
+    let mut ci = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    let output = ci
+        .args(["ci", "--format", "ndjson", "--directory"])
+        .arg(test_dir.path())
+        .output()
+        .expect("Failed to run ci");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 5);
+    for line in stdout.lines() {
+        let record: serde_json::Value = serde_json::from_str(line).expect("Invalid record");
+        assert!(record["kind"].is_string());
+    }
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Found 5 codemarks"));
+}
+
+#[test]
+fn test_ci_diagnostic_formats() {
+    let (_cmd, temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    let ci = |format: &str| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        let output = cmd
+            .env("HOME", temp_home.path())
+            .args(["ci", "--format", format, "--directory"])
+            .arg(test_dir.path())
+            .output()
+            .expect("Failed to run ci");
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Found 5 codemarks"));
+        String::from_utf8(output.stdout).expect("Output is not UTF-8")
+    };
+
+    let quickfix = ci("quickfix");
+    assert_eq!(quickfix.lines().count(), 5);
+    assert!(quickfix.contains("test.rs:4:5: error: FIXME: Add error handling\n"));
+    assert!(quickfix.contains("test.js:1:1: warning: TODO: Add validation\n"));
+    assert!(ci("gcc").contains("test.rs:8:1: warning: Quick workaround [HACK]\n"));
+    assert!(ci("msvc").contains("test.rs(4,5): error FIXME: Add error handling\n"));
+}
+
+#[test]
+fn test_ci_diagnostic_paths_relative_to_current_directory() {
+    let (_cmd, temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    let sub_dir = test_dir.path().join("sub");
+    fs::create_dir(&sub_dir).expect("Failed to create subdirectory");
+    create_test_files(&sub_dir);
+    let ci = |format: &str, directory: &str| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        let output = cmd
+            .env("HOME", temp_home.path())
+            .current_dir(test_dir.path())
+            .args(["ci", "--format", format, "--directory", directory])
+            .output()
+            .expect("Failed to run ci");
+        String::from_utf8(output.stdout).expect("Output is not UTF-8")
+    };
+
+    let has_line = |output: String, expected: &str| output.lines().any(|line| line == expected);
+    assert!(has_line(
+        ci("quickfix", "sub"),
+        "sub/test.rs:4:5: error: FIXME: Add error handling"
+    ));
+    assert!(has_line(
+        ci("gcc", "./sub"),
+        "sub/test.rs:8:1: warning: Quick workaround [HACK]"
+    ));
+    assert!(has_line(
+        ci("msvc", "sub"),
+        "sub/test.rs(4,5): error FIXME: Add error handling"
+    ));
+    // Other formats keep paths relative to the scanned directory
+    assert!(ci("csv", "sub").contains(",test.rs,4,"));
+}
+
 #[test]
 fn test_ci_command_with_annotations() {
     let (mut cmd, _temp_home) = cmd_with_temp_home();
@@ -216,6 +515,57 @@ fn test_config_set_pattern_command() {
     assert!(config_content.contains(custom_pattern));
 }
 
+#[test]
+fn test_config_set_pattern_rejects_unknown_groups() {
+    let (mut cmd, temp_home) = cmd_with_temp_home();
+
+    cmd.arg("config")
+        .arg("set-pattern")
+        .arg(r"TODO\((?P<assignee>\w+)\)")
+        .assert()
+        .stderr(predicate::str::contains(
+            "Unknown capture group 'assignee' in pattern, expected one of: kind, owner, issue, desc",
+        ));
+    let config_file = temp_home.path().join(".codemarks").join("config.json");
+    let config_content = fs::read_to_string(config_file).unwrap_or_default();
+    assert!(!config_content.contains("assignee"));
+}
+
+#[test]
+fn test_named_groups_populate_codemark_fields() {
+    let (mut cmd, temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(
+        test_dir.path().join("lib.rs"),
+        "// FIXME(alice) #42: handle timeouts\n",
+    )
+    .expect("Failed to write file");
+
+    cmd.arg("config")
+        .arg("set-pattern")
+        .arg(r"(?P<kind>TODO|FIXME)\((?P<owner>\w+)\)\s*(?:#(?P<issue>\d+))?:\s*(?P<desc>.*)")
+        .assert()
+        .success();
+
+    let mut scan = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    scan.env("HOME", temp_home.path())
+        .arg("scan")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .assert()
+        .success();
+
+    let projects_file = temp_home.path().join(".codemarks").join("projects.json");
+    let content = fs::read_to_string(projects_file).expect("Failed to read projects file");
+    let db: serde_json::Value = serde_json::from_str(&content).expect("Invalid projects file");
+    let projects = db["projects"].as_object().expect("No projects");
+    let codemark = &projects.values().next().expect("No project")[0];
+    assert_eq!(codemark["kind"], "FIXME");
+    assert_eq!(codemark["owner"], "alice");
+    assert_eq!(codemark["issue"], "42");
+    assert_eq!(codemark["description"], "handle timeouts");
+}
+
 #[test]
 fn test_config_reset_command() {
     let (mut cmd, _temp_home) = cmd_with_temp_home();
@@ -229,6 +579,25 @@ fn test_config_reset_command() {
         ));
 }
 
+#[test]
+fn test_config_reset_keeps_other_settings() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let codemarks = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .args(args)
+            .assert()
+            .success()
+    };
+
+    codemarks(&["config", "set-pattern", r"XXX\s*(.*)"]);
+    codemarks(&["config", "set", "editor", "hx"]);
+    codemarks(&["config", "reset"]);
+
+    codemarks(&["config", "get", "annotation_pattern"]).stdout(predicate::str::contains("TODO"));
+    codemarks(&["config", "get", "editor"]).stdout("hx\n");
+}
+
 #[test]
 fn test_watch_command_help() {
     let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
@@ -333,3 +702,908 @@ fn test_clean_command_with_project_filter() {
             "No resolved annotations found to clean",
         ));
 }
+
+#[test]
+fn test_scan_with_blame_outside_repository() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+
+    // Blame attribution is skipped silently when there is no git history
+    cmd.arg("scan")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .arg("--blame")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found"));
+}
+
+#[test]
+fn test_history_backfill_after_scan() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    fs::create_dir(test_dir.path().join("web")).expect("Failed to create web");
+    fs::write(test_dir.path().join("web/package.json"), "{}").expect("Failed to write file");
+    fs::write(test_dir.path().join("web/app.js"), "// TODO: nested\n")
+        .expect("Failed to write file");
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .current_dir(test_dir.path())
+            .args([
+                "-c",
+                "user.name=Alice",
+                "-c",
+                "user.email=alice@example.com",
+            ])
+            .args(args)
+            .status()
+            .expect("Failed to run git");
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "Add annotations"]);
+
+    let codemarks = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(test_dir.path())
+            .args(args)
+            .assert()
+            .success()
+    };
+    codemarks(&["scan", "--directory", "."]);
+    let backfill = codemarks(&["history", "backfill", "--directory", "."]);
+    let stdout = String::from_utf8(backfill.get_output().stdout.clone()).unwrap();
+    // Annotations of the nested project are dated against their own root
+    let web = stdout
+        .split("Backfilled history for project ")
+        .find(|section| section.starts_with("'web'"))
+        .expect("No history for the nested project");
+    assert!(web.contains("1 existing annotations dated"));
+    assert!(stdout.contains("5 existing annotations dated"));
+}
+
+#[test]
+fn test_history_backfill_outside_repository() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+
+    cmd.arg("history")
+        .arg("backfill")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Error backfilling history"));
+}
+
+#[test]
+fn test_ci_command_reports_notebook_cells() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    fs::write(
+        test_dir.path().join("analysis.ipynb"),
+        r##"{"cells": [{"cell_type": "code", "source": ["x = 1\n", "# FIXME: Wrong value\n"]}]}"##,
+    )
+    .expect("Failed to write notebook");
+
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "analysis.ipynb[cell 1]:2: # FIXME: Wrong value",
+        ));
+}
+
+#[test]
+fn test_scan_attributes_annotations_to_nested_projects() {
+    let (mut cmd, temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    fs::write(
+        test_dir.path().join("Cargo.toml"),
+        "[workspace]\nname = \"monorepo\"\n",
+    )
+    .expect("Failed to write manifest");
+    let service_dir = test_dir.path().join("services").join("a");
+    fs::create_dir_all(&service_dir).expect("Failed to create service directory");
+    fs::write(
+        service_dir.join("Cargo.toml"),
+        "[package]\nname = \"service-a\"\n",
+    )
+    .expect("Failed to write manifest");
+    fs::write(service_dir.join("lib.rs"), "// TODO: Service task\n").expect("Failed to write file");
+    let web_dir = test_dir.path().join("web");
+    fs::create_dir_all(&web_dir).expect("Failed to create web directory");
+    fs::write(web_dir.join("package.json"), r#"{"name": "web-app"}"#)
+        .expect("Failed to write manifest");
+    fs::write(web_dir.join("app.js"), "// FIXME: Web task\n").expect("Failed to write file");
+    fs::write(test_dir.path().join("root.rs"), "// HACK: Root task\n")
+        .expect("Failed to write file");
+
+    cmd.arg("scan")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found 3 code annotations"));
+
+    let projects_file = temp_home.path().join(".codemarks").join("projects.json");
+    let content = fs::read_to_string(projects_file).expect("Failed to read projects file");
+    let db: serde_json::Value = serde_json::from_str(&content).expect("Invalid projects file");
+    assert_eq!(db["projects"]["service-a"][0]["file"], "lib.rs");
+    assert_eq!(db["projects"]["web-app"][0]["file"], "app.js");
+    assert_eq!(db["projects"]["monorepo"][0]["file"], "root.rs");
+}
+
+#[test]
+fn test_scan_keeps_nested_projects_with_the_same_name_apart() {
+    let (_, temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    for (dir, task) in [("a", "First task"), ("b", "Second task")] {
+        let project_dir = test_dir.path().join(dir);
+        fs::create_dir_all(&project_dir).expect("Failed to create project directory");
+        fs::write(project_dir.join("package.json"), r#"{"name": "app"}"#)
+            .expect("Failed to write manifest");
+        fs::write(project_dir.join("index.js"), format!("// TODO: {task}\n"))
+            .expect("Failed to write file");
+    }
+
+    // Rescanning must neither merge nor swap the two projects
+    for _ in 0..2 {
+        Command::cargo_bin("codemarks")
+            .expect("Failed to find binary")
+            .env("HOME", temp_home.path())
+            .arg("scan")
+            .arg("--directory")
+            .arg(test_dir.path())
+            .assert()
+            .success();
+    }
+
+    let projects_file = temp_home.path().join(".codemarks").join("projects.json");
+    let content = fs::read_to_string(projects_file).expect("Failed to read projects file");
+    let db: serde_json::Value = serde_json::from_str(&content).expect("Invalid projects file");
+    let projects = db["projects"].as_object().expect("Missing projects");
+    let app_projects: Vec<_> = projects
+        .iter()
+        .filter(|(name, _)| name.starts_with("app"))
+        .collect();
+    assert_eq!(app_projects.len(), 2);
+    let mut descriptions: Vec<_> = app_projects
+        .iter()
+        .map(|(name, codemarks)| {
+            let codemarks = codemarks.as_array().expect("Invalid codemarks");
+            assert_eq!(codemarks.len(), 1, "{name} should hold one annotation");
+            assert_eq!(codemarks[0]["resolved"], false);
+            codemarks[0]["description"].as_str().unwrap().to_string()
+        })
+        .collect();
+    descriptions.sort();
+    assert_eq!(descriptions, ["First task", "Second task"]);
+    let (first, second) = (app_projects[0].0, app_projects[1].0);
+    assert_ne!(db["roots"][first], db["roots"][second]);
+}
+
+#[test]
+fn test_types_command() {
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+
+    cmd.arg("types")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rust: *.rs"));
+}
+
+#[test]
+fn test_ci_command_with_type_filter() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .arg("--type")
+        .arg("js")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Found 2 codemarks matching pattern",
+        ));
+}
+
+#[test]
+fn test_ci_command_with_repo_config_policy() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    fs::write(
+        test_dir.path().join(".codemarks.toml"),
+        "[ci]\nmax_annotations = 10\n",
+    )
+    .expect("Failed to write repo config");
+
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(test_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("within the maximum of 10"));
+}
+
+#[test]
+fn test_ci_reads_global_config() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    fs::write(test_dir.path().join("notes.rs"), "// NOTE: remember this\n")
+        .expect("Failed to write test file");
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path());
+        cmd
+    };
+    codemarks()
+        .args(["config", "kind", "add", "NOTE", "--severity", "info"])
+        .assert()
+        .success();
+
+    codemarks()
+        .args(["ci", "--directory"])
+        .arg(test_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Found 1 codemarks matching pattern.",
+        ));
+    // Ephemeral mode still skips the global configuration
+    codemarks()
+        .args(["--ephemeral", "ci", "--directory"])
+        .arg(test_dir.path())
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_ci_configuration_precedence() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    fs::write(
+        test_dir.path().join("notes.rs"),
+        "// NOTE: remember this\n// TODO: do this\n",
+    )
+    .expect("Failed to write test file");
+    let ci = |env: Option<&str>, args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .env_remove("CODEMARKS_KINDS");
+        if let Some(kinds) = env {
+            cmd.env("CODEMARKS_KINDS", kinds);
+        }
+        let output = cmd
+            .args(["ci", "--format", "csv"])
+            .args(args)
+            .arg("--directory")
+            .arg(test_dir.path())
+            .output()
+            .expect("Failed to run ci");
+        String::from_utf8(output.stdout).expect("Output is not UTF-8")
+    };
+    Command::cargo_bin("codemarks")
+        .expect("Failed to find binary")
+        .env("HOME", temp_home.path())
+        .args(["config", "kind", "add", "NOTE", "--severity", "info"])
+        .assert()
+        .success();
+
+    // The global kinds apply until the repository configuration replaces them
+    let output = ci(None, &[]);
+    assert!(output.contains(",NOTE,") && output.contains(",TODO,"));
+    fs::write(
+        test_dir.path().join(".codemarks.toml"),
+        "kinds = [\"TODO\"]\n",
+    )
+    .expect("Failed to write repo config");
+    let output = ci(None, &[]);
+    assert!(!output.contains(",NOTE,") && output.contains(",TODO,"));
+    // The environment overrides the repository, and the command line the environment
+    let output = ci(Some("NOTE"), &[]);
+    assert!(output.contains(",NOTE,") && !output.contains(",TODO,"));
+    let output = ci(Some("NOTE"), &["--pattern", "TODO:(.*)"]);
+    assert!(!output.contains("remember") && output.contains("do this"));
+}
+
+#[test]
+fn test_config_kind_commands() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path());
+        cmd
+    };
+
+    codemarks()
+        .args(["config", "kind", "add", "NOTE", "--severity", "info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Annotation kind NOTE saved"));
+
+    codemarks()
+        .args(["config", "kind", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("FIXME (error): FIXME"))
+        .stdout(predicate::str::contains("NOTE (info): NOTE"));
+
+    // The new kind is picked up by scan
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(temp_dir.path().join("notes.rs"), "// NOTE: remember this\n")
+        .expect("Failed to write test file");
+    codemarks()
+        .arg("scan")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found 1 code annotations"));
+
+    codemarks()
+        .args(["config", "kind", "remove", "UNKNOWN"])
+        .assert()
+        .stderr(predicate::str::contains("Unknown annotation kind: UNKNOWN"));
+}
+
+#[test]
+fn test_config_show_origin() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(
+        temp_dir.path().join(".codemarks.toml"),
+        "ignore = [\"vendor/\"]\n",
+    )
+    .expect("Failed to write repository config");
+
+    cmd.current_dir(temp_dir.path())
+        .env("CODEMARKS_MAX_ANNOTATIONS", "4")
+        .args(["config", "show", "--origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ignore = vendor/"))
+        .stdout(predicate::str::contains("from repository config"))
+        .stdout(predicate::str::contains("ci.max_annotations = 4"))
+        .stdout(predicate::str::contains(
+            "from environment variable CODEMARKS_MAX_ANNOTATIONS",
+        ));
+}
+
+#[test]
+fn test_ci_env_overrides_repo_policy() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    create_test_files(temp_dir.path());
+    fs::write(
+        temp_dir.path().join(".codemarks.toml"),
+        "[ci]\nmax_annotations = 1\n",
+    )
+    .expect("Failed to write repository config");
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.env("CODEMARKS_MAX_ANNOTATIONS", "100")
+        .arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("within the maximum of 100"));
+}
+
+#[test]
+fn test_config_test_command() {
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    create_test_files(temp_dir.path());
+
+    cmd.args(["config", "test", r"//\s*(?:TODO|XXX):?\s*(.*)"])
+        .arg("--line")
+        .arg("// XXX: not a default keyword")
+        .arg("--file")
+        .arg(temp_dir.path().join("test.rs"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "line 1: // XXX: not a default keyword",
+        ))
+        .stdout(predicate::str::contains(
+            "not matched by the configured pattern",
+        ))
+        .stdout(predicate::str::contains(
+            "description: Implement this function",
+        ))
+        .stdout(predicate::str::contains(
+            "Only matched by the configured pattern:",
+        ))
+        .stdout(predicate::str::contains("2 of 12 lines match"));
+}
+
+#[test]
+fn test_ci_comment_syntax_per_language() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(
+        temp_dir.path().join("schema.sql"),
+        "-- TODO: add an index\nSELECT 1; # FIXME not a SQL comment\n",
+    )
+    .expect("Failed to write test file");
+    fs::write(
+        temp_dir.path().join("build.bat"),
+        "@echo off\nREM HACK: hardcoded path\n",
+    )
+    .expect("Failed to write test file");
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "schema.sql:1: -- TODO: add an index",
+        ))
+        .stdout(predicate::str::contains(
+            "build.bat:2: REM HACK: hardcoded path",
+        ))
+        .stdout(predicate::str::contains("Found 2 codemarks"));
+}
+
+#[test]
+fn test_config_set_and_get_commands() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path());
+        cmd
+    };
+
+    codemarks()
+        .args(["config", "set", "debounce", "750"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Setting debounce updated"));
+    codemarks()
+        .args(["config", "get", "debounce"])
+        .assert()
+        .success()
+        .stdout("750\n");
+    codemarks()
+        .args(["config", "set", "output_format", "xml"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Invalid value for output_format"));
+    codemarks()
+        .args(["config", "set", "debounce", "abc"])
+        .assert()
+        .code(1);
+    codemarks()
+        .args(["config", "show", "--origin"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("debounce = 750"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_edit_discards_invalid_changes() {
+    let (mut cmd, temp_home) = cmd_with_temp_home();
+    let editor = temp_home.path().join("editor.sh");
+    fs::write(
+        &editor,
+        "#!/bin/sh\necho '{\"debounce\": \"soon\"}' > \"$1\"\n",
+    )
+    .expect("Failed to write editor script");
+    let mut permissions = fs::metadata(&editor).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    fs::set_permissions(&editor, permissions).expect("Failed to make editor executable");
+
+    cmd.env("VISUAL", &editor)
+        .args(["config", "edit"])
+        .assert()
+        .stderr(predicate::str::contains(
+            "Invalid configuration, changes discarded",
+        ));
+
+    let config_file = temp_home.path().join(".codemarks").join("config.json");
+    let config_content = fs::read_to_string(config_file).expect("Failed to read config file");
+    assert!(config_content.contains("annotation_pattern"));
+}
+
+#[test]
+fn test_ci_profile() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    create_test_files(temp_dir.path());
+    fs::write(
+        temp_dir.path().join(".codemarks.toml"),
+        "[ci]\nmax_annotations = 10\n\n[profile.strict.ci]\nmax_annotations = 0\n",
+    )
+    .expect("Failed to write repository config");
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.arg("--profile")
+        .arg("strict")
+        .arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .failure();
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.env("CODEMARKS_PROFILE", "unknown")
+        .arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Unknown profile 'unknown'"));
+
+    // list fails the same way instead of listing everything
+    let (mut cmd, _temp_home) = cmd_with_temp_home();
+    cmd.current_dir(temp_dir.path())
+        .args(["--profile", "unknown", "list"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Unknown profile 'unknown'"));
+}
+
+#[test]
+fn test_stored_project_ignore_patterns() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    create_test_files(temp_dir.path());
+    fs::create_dir(temp_dir.path().join("vendor")).expect("Failed to create vendor dir");
+    fs::write(
+        temp_dir.path().join("vendor").join("lib.rs"),
+        "// TODO: vendored\n",
+    )
+    .expect("Failed to write test file");
+    fs::create_dir(temp_dir.path().join("generated")).expect("Failed to create generated dir");
+    fs::write(
+        temp_dir.path().join("generated").join("api.rs"),
+        "// TODO: generated\n",
+    )
+    .expect("Failed to write test file");
+    fs::write(temp_dir.path().join(".codemarksignore"), "generated/\n")
+        .expect("Failed to write .codemarksignore");
+    fs::write(
+        temp_dir.path().join(".codemarks.toml"),
+        "project = \"demo\"\n",
+    )
+    .expect("Failed to write repository config");
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(temp_dir.path());
+        cmd
+    };
+
+    codemarks()
+        .args(["config", "ignore", "add", "vendor/"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Ignoring 'vendor/' in project 'demo'",
+        ));
+
+    // Neither the stored pattern nor the .codemarksignore entries are scanned
+    codemarks()
+        .arg("scan")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found 5 code annotations"));
+}
+
+#[test]
+fn test_ci_reports_suppressed_annotations() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(
+        temp_dir.path().join("main.rs"),
+        "// TODO: real work\n// codemarks-ignore-next-line\n// TODO: example of the convention\n",
+    )
+    .expect("Failed to write test file");
+    fs::write(
+        temp_dir.path().join("CONVENTIONS.txt"),
+        "# codemarks-ignore-file\n# TODO: explained here\n# FIXME: and here\n",
+    )
+    .expect("Failed to write test file");
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.arg("ci")
+        .arg("--directory")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("main.rs:1: // TODO: real work"))
+        .stdout(predicate::str::contains("explained here").not())
+        .stdout(predicate::str::contains(
+            "3 codemarks suppressed by codemarks-ignore directives.",
+        ))
+        .stdout(predicate::str::contains(
+            "Found 1 codemarks matching pattern.",
+        ));
+}
+
+#[test]
+fn test_report_command() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    write_projects(
+        temp_home.path(),
+        r#"{"projects": {
+            "api": [
+                {"file": "src/db.rs", "line_number": 3, "description": "add an <index>", "kind": "TODO", "owner": "alice"},
+                {"file": "src/db.rs", "line_number": 9, "description": "retry on timeout", "kind": "FIXME"},
+                {"file": "src/old.rs", "line_number": 5, "description": "drop the index", "kind": "TODO", "resolved": true}
+            ],
+            "web": [
+                {"file": "src/app.js", "line_number": 2, "description": "index the routes", "kind": "TODO"}
+            ]
+        }}"#,
+    );
+    let report = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(temp_home.path())
+            .arg("report")
+            .args(args)
+            .assert()
+            .success()
+    };
+
+    report(&["--project", "api"])
+        .stdout(predicate::str::contains("## api"))
+        .stdout(predicate::str::contains("| FIXME | 1 |\n| TODO | 1 |"))
+        .stdout(predicate::str::contains("### src/db.rs"))
+        .stdout(predicate::str::contains(
+            "| 3 | TODO | add an \\<index\\> | alice |",
+        ))
+        .stdout(predicate::str::contains("drop the index").not())
+        .stdout(predicate::str::contains("## web").not());
+
+    let output = temp_home.path().join("report.html");
+    report(&[
+        "--format",
+        "html",
+        "-o",
+        output.to_str().unwrap(),
+        "--repository-url",
+        "https://example.com/{project}/{file}#L{line}",
+    ])
+    .stdout(predicate::str::contains(
+        "Report of 3 annotations written to",
+    ));
+    let html = fs::read_to_string(&output).expect("Failed to read report");
+    assert!(html.contains("<a href=\"https://example.com/api/src/db.rs#L3\">3</a>"));
+    assert!(html.contains("<td>add an &lt;index&gt;</td>"));
+    assert!(html.contains("<h2>web</h2>"));
+    assert!(!html.contains("drop the index"));
+}
+
+#[test]
+fn test_list_sort_and_group() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    write_projects(
+        temp_home.path(),
+        r#"{"projects": {
+            "web": [
+                {"file": "src/app.js", "line_number": 2, "description": "index the routes", "kind": "TODO", "created_at": 300}
+            ],
+            "api": [
+                {"file": "src/db.rs", "line_number": 9, "description": "retry on timeout", "kind": "FIXME", "created_at": 200},
+                {"file": "src/db.rs", "line_number": 3, "description": "add an index", "kind": "TODO"}
+            ]
+        }}"#,
+    );
+    let list = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        let output = cmd
+            .env("HOME", temp_home.path())
+            .current_dir(temp_home.path())
+            .arg("list")
+            .args(args)
+            .output()
+            .expect("Failed to run list");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).expect("Output is not UTF-8")
+    };
+
+    // Projects in alphabetical order, annotations in file and line order
+    assert_eq!(
+        list(&[]),
+        "api\n   src/db.rs:3 add an index\n   src/db.rs:9 retry on timeout\n\n\
+         web\n   src/app.js:2 index the routes\n\n"
+    );
+    assert_eq!(
+        list(&["--sort", "age", "--group-by", "none"]),
+        "   api: src/db.rs:9 retry on timeout\n   web: src/app.js:2 index the routes\n   \
+         api: src/db.rs:3 add an index\n"
+    );
+    assert_eq!(
+        list(&["--group-by", "kind"]),
+        "FIXME\n   api: src/db.rs:9 retry on timeout\n\n\
+         TODO\n   api: src/db.rs:3 add an index\n   web: src/app.js:2 index the routes\n\n"
+    );
+    assert_eq!(
+        list(&["--sort", "age", "--limit", "1", "--format", "csv"])
+            .lines()
+            .nth(1)
+            .unwrap(),
+        "api,src/db.rs,9,,FIXME,open,retry on timeout,,,,200,,error,"
+    );
+}
+
+#[test]
+fn test_stats_command() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    let codemarks = || {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(temp_home.path());
+        cmd
+    };
+
+    codemarks()
+        .args(["scan", "--directory"])
+        .arg(test_dir.path())
+        .assert()
+        .success();
+    // Resolve the annotations of test.js with a second scan
+    fs::remove_file(test_dir.path().join("test.js")).expect("Failed to remove file");
+    codemarks()
+        .args(["scan", "--directory"])
+        .arg(test_dir.path())
+        .assert()
+        .success();
+
+    let output = codemarks()
+        .args(["stats", "--format", "json"])
+        .output()
+        .expect("Failed to run stats");
+    let stats: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stats did not print JSON");
+    assert_eq!(stats["open"], 3);
+    assert_eq!(stats["resolved"], 2);
+    assert_eq!(stats["resolved_by_last_scan"], 2);
+    assert_eq!(stats["top_files"][0]["file"], "test.rs");
+    assert_eq!(stats["top_files"][0]["open"], 3);
+    assert!(stats["projects"][0]["last_scan"].is_i64());
+
+    codemarks()
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Annotations: 3 open, 2 resolved"))
+        .stdout(predicate::str::contains("Last scan: "))
+        .stdout(predicate::str::contains("HACK"));
+}
+
+#[test]
+fn test_colored_output() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    let codemarks = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .current_dir(test_dir.path())
+            .args(args)
+            .output()
+            .expect("Failed to run codemarks")
+    };
+    let stdout = |args: &[&str]| String::from_utf8(codemarks(args).stdout).unwrap();
+
+    let colored = stdout(&["ci", "--color", "always"]);
+    // FIXME is red and TODO yellow by default, and locations link to their file
+    assert!(colored.contains("\x1b[31m    // FIXME: Add error handling\x1b[0m"));
+    assert!(colored.contains("\x1b[33m// TODO: Implement this function\x1b[0m"));
+    assert!(colored.contains("\x1b]8;;file:///"));
+    assert!(colored.contains("/test.rs#4\x1b\\"));
+    // Not a terminal, so no styling by default
+    assert!(!stdout(&["ci"]).contains('\x1b'));
+    assert!(!stdout(&["ci", "--color", "never"]).contains('\x1b'));
+
+    assert!(codemarks(&["scan"]).status.success());
+    let listed = stdout(&["list", "--color", "always"]);
+    assert!(listed.contains("/test.rs#8\x1b\\"));
+    assert!(listed.contains("test.rs:8\x1b]8;;\x1b\\ \x1b[35mQuick workaround\x1b[0m\n"));
+    assert!(!stdout(&["list"]).contains('\x1b'));
+}
+
+#[test]
+fn test_scan_current_directory_stores_clean_paths() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    fs::create_dir(test_dir.path().join("src")).expect("Failed to create src");
+    fs::write(test_dir.path().join("src/lib.rs"), "// TODO: nested path\n")
+        .expect("Failed to write file");
+
+    let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+    cmd.env("HOME", temp_home.path())
+        .current_dir(test_dir.path())
+        .args(["scan", "--directory", "."])
+        .assert()
+        .success();
+
+    let db: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(temp_home.path().join(".codemarks/projects.json"))
+            .expect("Failed to read projects database"),
+    )
+    .expect("Invalid projects database");
+    let mut files: Vec<&str> = db["projects"]
+        .as_object()
+        .expect("Expected projects")
+        .values()
+        .flat_map(|codemarks| codemarks.as_array().expect("Expected codemarks"))
+        .map(|codemark| codemark["file"].as_str().expect("Expected a file"))
+        .collect();
+    files.sort_unstable();
+    files.dedup();
+    assert_eq!(files, ["src/lib.rs", "test.js", "test.rs"]);
+}
+
+#[test]
+fn test_open_command() {
+    let temp_home = TempDir::new().expect("Failed to create temp directory");
+    let test_dir = TempDir::new().expect("Failed to create test directory");
+    create_test_files(test_dir.path());
+    let codemarks = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("codemarks").expect("Failed to find binary");
+        cmd.env("HOME", temp_home.path())
+            .env("EDITOR", "echo")
+            .env_remove("VISUAL")
+            .current_dir(temp_home.path())
+            .args(args)
+            .assert()
+    };
+    codemarks(&["scan", "--directory", test_dir.path().to_str().unwrap()]).success();
+    let test_rs = test_dir.path().canonicalize().unwrap().join("test.rs");
+
+    // echo has no known line syntax, so it only gets the file
+    codemarks(&["open", "kind:HACK"])
+        .success()
+        .stdout(format!("{}\n", test_rs.display()));
+
+    codemarks(&["config", "set", "editor", "echo {file}:{line}"]).success();
+    codemarks(&["open", "test.rs:4"])
+        .success()
+        .stdout(format!("{}:4\n", test_rs.display()));
+
+    let listed = codemarks(&["list", "--ids", "--kind", "FIXME", "--file", "*.js"]).success();
+    let listed = String::from_utf8(listed.get_output().stdout.clone()).unwrap();
+    let id = listed
+        .lines()
+        .find_map(|line| line.split_whitespace().next().filter(|id| id.len() == 7))
+        .expect("No id listed");
+    codemarks(&["open", id])
+        .success()
+        .stdout(predicate::str::ends_with("test.js:3\n"));
+
+    codemarks(&["open", "kind:TODO"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "matches 2 annotations, open one by its id",
+        ));
+    codemarks(&["open", "kind:XXX"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "No open annotation matches 'kind:XXX'",
+        ));
+}